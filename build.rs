@@ -0,0 +1,45 @@
+use std::process::Command;
+
+fn main() {
+    // Git hash for host.version
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_HASH={git_hash}");
+
+    // Resolved rquickjs version from Cargo.lock
+    let rquickjs_version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| {
+            let mut lines = lock.lines();
+            lines.find(|l| *l == "name = \"rquickjs\"")?;
+            lines
+                .next()?
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RQUICKJS_VERSION={rquickjs_version}");
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    // HEAD only changes on checkout - commits update the branch ref (loose
+    // or packed)
+    if let Some(head_ref) = std::fs::read_to_string(".git/HEAD")
+        .ok()
+        .and_then(|head| Some(head.strip_prefix("ref: ")?.trim().to_string()))
+    {
+        for path in [format!(".git/{head_ref}"), ".git/packed-refs".to_string()] {
+            // Missing paths would force a rerun on every build
+            if std::path::Path::new(&path).exists() {
+                println!("cargo:rerun-if-changed={path}");
+            }
+        }
+    }
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
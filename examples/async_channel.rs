@@ -5,6 +5,7 @@ use rquickjs::{
     Module, Value,
 };
 
+use rquickjs_test::event_loop::run_event_loop;
 use std::io::Read;
 use tokio::time::{timeout, Duration};
 
@@ -124,11 +125,10 @@ async fn main() -> anyhow::Result<()> {
 
     println!(">> Tasks Pending: {:?}", rt.is_job_pending().await);
 
-    rt.idle().await;
-
+    // Service jobs/host futures until resolved (or timeout)
     println!(
         "Channel RX: {}",
-        match timeout(Duration::from_secs(2), resolve_rx).await {
+        match run_event_loop(&rt, timeout(Duration::from_secs(2), resolve_rx)).await {
             Ok(Ok(m)) => m,
             Ok(Err(e)) => format!("Oneshot Err: {e}"),
             Err(_) => "Timeout".into(),
@@ -0,0 +1,28 @@
+use argh::FromArgs;
+
+use rquickjs_test::bundle::{bundle_key, encrypt_bundle, BUNDLE_EXT, BUNDLE_KEY_ENV};
+
+#[derive(FromArgs)]
+/// Encrypt script bundle (key from RQUICKJS_BUNDLE_KEY)
+struct CliArgs {
+    #[argh(positional)]
+    /// script file
+    script: String,
+    #[argh(option, short = 'o')]
+    /// output file (default <script>.enc)
+    output: Option<String>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: CliArgs = argh::from_env();
+    let key = bundle_key().map_err(|e| anyhow::anyhow!("{e} (export {BUNDLE_KEY_ENV}=<hex>)"))?;
+    let output = args
+        .output
+        .unwrap_or_else(|| format!("{}{BUNDLE_EXT}", args.script));
+    std::fs::write(
+        &output,
+        encrypt_bundle(&std::fs::read(&args.script)?, &key)?,
+    )?;
+    println!("[+] Bundle: {output}");
+    Ok(())
+}
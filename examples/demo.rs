@@ -1,11 +1,39 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
 use argh::FromArgs;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 
-use rquickjs::{async_with, AsyncContext, AsyncRuntime, Class, Module};
-use rquickjs_test::run::{call_fn, get_script, repl_rustyline, run_module, run_script};
+use rquickjs::{async_with, AsyncContext};
+use rquickjs_test::cache::{Cache, DEFAULT_CACHE_CAPACITY};
+use rquickjs_test::engine::RuntimeOptions;
+use rquickjs_test::env::JsEnvBuilder;
+use rquickjs_test::error::{report_error, ErrorFormat, ScriptExit};
+use rquickjs_test::event_loop;
+use rquickjs_test::history::default_history_file;
+use rquickjs_test::kv::{FileKvBackend, MemoryKvBackend};
+#[cfg(feature = "http_imports")]
+use rquickjs_test::loader::{default_http_cache, HttpLoader};
+use rquickjs_test::loader::{FileLoader, ImportMap};
+use rquickjs_test::native::NativeModuleSet;
+#[cfg(feature = "plugin")]
+use rquickjs_test::plugin::Plugin;
+use rquickjs_test::repl::{
+    default_rc_files, repl_rustyline, ReplOptions, DEFAULT_MAX_OUTPUT_BYTES,
+};
+use rquickjs_test::run::{
+    call_fn, compile_module, get_script, run_compiled_module, run_module, run_script,
+    take_exit_code, BYTECODE_EXT, PREV,
+};
+use rquickjs_test::timers::clear_timers;
+use rquickjs_test::trace;
 use rquickjs_test::util::{
-    json_to_value, register_fns, register_oneshot, register_rx_channel, register_tx_channel,
-    value_to_json,
+    json_to_value, limit_output, register_oneshot, register_rx_channel, register_tx_channel,
+    set_script_args, value_to_json,
 };
+use rquickjs_test::watchdog::{report_stall, spawn_watchdog, PendingFutures};
+use tokio::sync::mpsc::UnboundedReceiver;
 
 #[derive(FromArgs)]
 /// CLI Args
@@ -25,11 +53,160 @@ struct CliArgs {
     #[argh(option)]
     /// call args
     arg: Vec<String>,
+    #[argh(switch)]
+    /// don't load ~/.rquickjsrc.js or ./.rquickjsrc.js in REPL
+    no_rc: bool,
+    #[argh(option, default = "ErrorFormat::Text")]
+    /// error output format (text|json)
+    errors: ErrorFormat,
+    #[argh(option)]
+    /// max bytes of output printed per result (0 = unlimited)
+    max_output_bytes: Option<usize>,
+    #[argh(option)]
+    /// REPL history file (default ~/.rquickjs_history)
+    history_file: Option<std::path::PathBuf>,
+    #[argh(switch)]
+    /// fuzzy match REPL history search
+    fuzzy_history: bool,
+    #[argh(option)]
+    /// write chrome://tracing JSON of job/host future scheduling to file
+    /// (jobs run by host futures once scripts finish are not recorded)
+    trace: Option<std::path::PathBuf>,
+    #[argh(option)]
+    /// report host futures stalled for N seconds
+    watchdog: Option<u64>,
+    #[argh(option, from_str_fn(parse_set))]
+    /// set global string (name=value)
+    set: Vec<(String, String)>,
+    #[argh(option, from_str_fn(parse_set))]
+    /// set global from JSON (name=json)
+    set_json: Vec<(String, String)>,
+    #[argh(option, default = "std::path::PathBuf::from(\".\")")]
+    /// directory imports are resolved from (default .)
+    module_root: std::path::PathBuf,
+    #[argh(option)]
+    /// import map JSON file mapping bare specifiers to paths/modules
+    import_map: Option<std::path::PathBuf>,
+    #[argh(switch)]
+    /// allow https:// module imports (needs the http_imports feature)
+    allow_remote: bool,
+    #[argh(switch)]
+    /// also allow insecure http:// module imports
+    allow_http: bool,
+    #[argh(option)]
+    /// cache directory for http(s) imports (default ~/.cache/rquickjs/modules)
+    http_cache: Option<std::path::PathBuf>,
+    #[argh(switch)]
+    /// only load http(s) imports from cache
+    offline: bool,
+    #[argh(option)]
+    /// runtime memory limit (bytes)
+    max_mem: Option<usize>,
+    #[argh(option)]
+    /// runtime max stack size (bytes)
+    max_stack: Option<usize>,
+    #[argh(switch)]
+    /// re-run when @file scripts/modules change
+    watch: bool,
+    #[argh(option)]
+    /// load native plugin (shared library)
+    plugin: Vec<PathBuf>,
+    #[argh(switch)]
+    /// install `process` global
+    process: bool,
+    #[argh(option)]
+    /// expose environment variable via `process.env` (implies --process)
+    env: Vec<String>,
+    #[argh(option)]
+    /// persist `kv` values in directory (default in-memory)
+    kv_dir: Option<PathBuf>,
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Compile(CompileArgs),
+}
+
+#[derive(FromArgs)]
+/// Compile module to QuickJS bytecode (run with --module @out.qjsc)
+#[argh(subcommand, name = "compile")]
+struct CompileArgs {
+    #[argh(positional)]
+    /// module source
+    input: std::path::PathBuf,
+    #[argh(option, short = 'o')]
+    /// output file (default <input>.qjsc)
+    output: Option<std::path::PathBuf>,
+}
+
+/// Compile module (plain runtime - no host functions needed)
+fn compile(args: &CompileArgs) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(&args.input)?;
+    let output = args.output.clone().unwrap_or_else(|| {
+        args.input
+            .with_extension(BYTECODE_EXT.trim_start_matches('.'))
+    });
+    let rt = rquickjs::Runtime::new()?;
+    let ctx = rquickjs::Context::full(&rt)?;
+    let bytecode = ctx.with(|ctx| compile_module(&ctx, &args.input.to_string_lossy(), source))?;
+    std::fs::write(&output, bytecode)?;
+    println!(
+        ">> Compiled {} -> {}",
+        args.input.display(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Parse `name=value` arg
+fn parse_set(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("Expected name=value: {s}")),
+    }
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let args: CliArgs = argh::from_env();
+async fn main() {
+    let (args, script_args) = parse_args();
+    let format = args.errors;
+    if let Err(e) = run(args, script_args).await {
+        std::process::exit(report_error(&e, format));
+    }
+}
+
+/// Parse CLI args - anything after `--` is passed to scripts as `scriptArgs`
+fn parse_args() -> (CliArgs, Vec<String>) {
+    let mut argv = std::env::args().collect::<Vec<_>>();
+    let script_args = match argv.iter().position(|a| a == "--") {
+        Some(i) => argv.split_off(i).split_off(1),
+        None => vec![],
+    };
+    let argv = argv.iter().map(String::as_str).collect::<Vec<_>>();
+    let args =
+        CliArgs::from_args(&argv[..1], &argv[1..]).unwrap_or_else(|exit| match exit.status {
+            Ok(()) => {
+                println!("{}", exit.output);
+                std::process::exit(0)
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} --help for more information.",
+                    exit.output, argv[0]
+                );
+                std::process::exit(1)
+            }
+        });
+    (args, script_args)
+}
+
+async fn run(args: CliArgs, script_args: Vec<String>) -> anyhow::Result<()> {
+    if let Some(Command::Compile(compile_args)) = &args.command {
+        return compile(compile_args);
+    }
 
     // Check that we have something to do
     if args.script.is_empty() && args.module.is_empty() && args.call.is_empty() && !args.repl {
@@ -37,7 +214,143 @@ async fn main() -> anyhow::Result<()> {
         CliArgs::from_args(&[&name], &["--help"]).map_err(|exit| anyhow::anyhow!(exit.output))?;
     }
 
-    let rt = AsyncRuntime::new()?;
+    let max_output_bytes = match args.max_output_bytes {
+        Some(0) => None,
+        Some(n) => Some(n),
+        None => Some(DEFAULT_MAX_OUTPUT_BYTES),
+    };
+    let repl_opts = ReplOptions {
+        max_output_bytes,
+        history_file: args.history_file.clone().or_else(default_history_file),
+        fuzzy_history: args.fuzzy_history,
+        rc_files: if args.no_rc {
+            vec![]
+        } else {
+            default_rc_files()
+        },
+        timeout: None,
+    };
+
+    let trace_file = args.trace.clone();
+    if trace_file.is_some() {
+        trace::enable();
+    }
+    if let Some(secs) = args.watchdog {
+        if secs == 0 {
+            return Err(anyhow::anyhow!("--watchdog must be at least 1 second"));
+        }
+        spawn_watchdog(
+            PendingFutures::global().clone(),
+            Duration::from_secs(secs),
+            report_stall,
+        );
+    }
+
+    let plugins = Plugins::load(&args.plugin)?;
+
+    if !args.watch {
+        session(&args, &script_args, &plugins, &repl_opts, max_output_bytes).await?;
+        if let Some(path) = trace_file {
+            trace::write_trace(&path)?;
+        }
+        return Ok(());
+    }
+
+    // Watch mode - restart session with a fresh runtime/context on change
+    let (_watcher, mut changes) = watch_files(&watched_files(&args))?;
+    loop {
+        let changed = tokio::select! {
+            r = session(&args, &script_args, &plugins, &repl_opts, max_output_bytes) => {
+                if let Err(e) = r {
+                    report_error(&e, args.errors);
+                }
+                false
+            }
+            _ = changes.recv() => true,
+        };
+        if !changed {
+            println!(">> Waiting for changes");
+            if changes.recv().await.is_none() {
+                return Ok(());
+            }
+        }
+        // Editors often write files in several steps
+        tokio::time::sleep(WATCH_DEBOUNCE).await;
+        while changes.try_recv().is_ok() {}
+        println!(">> Reloading");
+    }
+}
+
+/// Delay before reloading after a change
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// `@file` scripts/modules (canonicalised to match watcher events)
+fn watched_files(args: &CliArgs) -> Vec<PathBuf> {
+    args.module
+        .iter()
+        .chain(&args.script)
+        .filter_map(|s| s.strip_prefix('@'))
+        .filter_map(|path| std::fs::canonicalize(path).ok())
+        .collect()
+}
+
+/// Watch `files` - parent directories are watched so files replaced by
+/// rename (as many editors do) are still picked up
+fn watch_files(files: &[PathBuf]) -> anyhow::Result<(RecommendedWatcher, UnboundedReceiver<()>)> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let watched = files.to_vec();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if (event.kind.is_modify() || event.kind.is_create())
+            && event.paths.iter().any(|p| watched.contains(p))
+        {
+            let _ = tx.send(());
+        }
+    })?;
+    let dirs = files
+        .iter()
+        .filter_map(|f| f.parent())
+        .collect::<std::collections::HashSet<_>>();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    Ok((watcher, rx))
+}
+
+/// Create runtime/context, install globals and channels and run
+/// scripts/modules/REPL/calls
+async fn session(
+    args: &CliArgs,
+    script_args: &[String],
+    plugins: &Plugins,
+    repl_opts: &ReplOptions,
+    max_output_bytes: Option<usize>,
+) -> anyhow::Result<()> {
+    let modules = NativeModuleSet::new().with::<js_test_mod>("stuff");
+    let mut rt_opts = RuntimeOptions::new();
+    if let Some(bytes) = args.max_mem {
+        rt_opts = rt_opts.memory_limit(bytes);
+    }
+    if let Some(bytes) = args.max_stack {
+        rt_opts = rt_opts.max_stack_size(bytes);
+    }
+    let rt = rt_opts.build().await?;
+    let import_map = match &args.import_map {
+        Some(path) => ImportMap::from_file(path)?,
+        None => ImportMap::default(),
+    };
+    let mut loader = FileLoader::new(&args.module_root)
+        .with_modules(&modules)
+        .with_import_map(import_map);
+    if args.allow_remote {
+        loader = with_remote_imports(loader, &args)?;
+    }
+    loader.install_async(&rt).await;
+    if args.trace.is_some() {
+        trace::install_promise_hook(&rt).await;
+    }
     let ctx = AsyncContext::full(&rt).await?;
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
@@ -73,28 +386,52 @@ async fn main() -> anyhow::Result<()> {
     });
 
     async_with!(ctx => |ctx| {
-        register_fns(&ctx)?;
+        let env = JsEnvBuilder::iot().modules(modules).serde_class::<Stuff>();
+        let mut env = plugins.install(env);
+        env = match &args.kv_dir {
+            Some(dir) => env.kv(Arc::new(FileKvBackend::new(dir)?)),
+            None => env.kv(Arc::new(MemoryKvBackend::new())),
+        };
+        env = env.cache(Arc::new(Cache::new(DEFAULT_CACHE_CAPACITY)));
+        if args.process || !args.env.is_empty() {
+            env = with_process(env, &args)?;
+        }
+        env.apply(ctx.clone()).await?;
         register_tx_channel(ctx.clone(), tx, "send")?;
         register_rx_channel(ctx.clone(), rx2, "recv")?;
         register_oneshot(ctx.clone(), oneshot_tx, "resolve")?;
-        Class::<Stuff>::define(&ctx.globals())?;
-
-        let (_, p) = Module::evaluate_def::<js_test_mod,_>(ctx.clone(),"stuff")?;
-        p.into_future::<()>().await?; // Ensure module evaluated
+        set_script_args(&ctx, script_args)?;
 
-        // Run modules
-        for module in args.module {
-            run_module(ctx.clone(),get_script(&module)?).await?;
+        // Set globals
+        for (name, value) in &args.set {
+            ctx.globals().set(name, value)?;
+        }
+        for (name, json) in &args.set_json {
+            let v = json_to_value(ctx.clone(), json)
+                .map_err(|e| anyhow::anyhow!("--set-json {name}: {e}"))?;
+            ctx.globals().set(name, v)?;
         }
 
-        // Run scripts
-        for script in args.script {
-            run_script(ctx.clone(),get_script(&script)?).await?;
+        // Run modules then scripts - each result is bound to $prev for the next
+        for module in &args.module {
+            let prev = match module.strip_prefix('@').filter(|m| m.ends_with(BYTECODE_EXT)) {
+                // SAFETY: compiled modules are only loaded from paths given
+                // on the command line (trusted like the scripts themselves)
+                Some(path) => unsafe { run_compiled_module(ctx.clone(), &std::fs::read(path)?) }.await?,
+                None => run_module(ctx.clone(),get_script(module)?).await?,
+            };
+            ctx.globals().set(PREV, prev)?;
+        }
+        for script in &args.script {
+            let prev = run_script(ctx.clone(),get_script(script)?).await?;
+            ctx.globals().set(PREV, prev)?;
         }
 
         // Run REPL
         if args.repl {
-            repl_rustyline(ctx.clone()).await?;
+            repl_rustyline(ctx.clone(), repl_opts).await?;
+            // Leaving the REPL ends the session - don't wait for intervals
+            clear_timers(&ctx);
         }
 
         // Call JS
@@ -104,7 +441,7 @@ async fn main() -> anyhow::Result<()> {
             } else {
                 call_fn(ctx.clone(),&f,(json_to_value(ctx.clone(),a)?,)).await?
             };
-            println!(">> [CALL] {f} ({a}) => {}", value_to_json(ctx.clone(),r)?);
+            println!(">> [CALL] {f} ({a}) => {}", limit_output(&value_to_json(ctx.clone(),r)?, max_output_bytes));
         }
         Ok::<(),anyhow::Error>(())
     })
@@ -112,12 +449,89 @@ async fn main() -> anyhow::Result<()> {
 
     println!(">> Tasks Pending: {:?}", rt.is_job_pending().await);
 
-    rt.idle().await;
+    event_loop::idle(&rt).await;
+
+    // exit() called from a timer/promise job after the scripts returned
+    if let Some(code) = async_with!(ctx => |ctx| { take_exit_code(&ctx) }).await {
+        return Err(ScriptExit(code).into());
+    }
 
     Ok(())
 }
 
-#[derive(Debug, Clone, rquickjs::class::Trace, rquickjs::JsLifetime)]
+/// Enable http(s) imports cached in `--http-cache`
+#[cfg(feature = "http_imports")]
+fn with_remote_imports(loader: FileLoader, args: &CliArgs) -> anyhow::Result<FileLoader> {
+    let cache = args
+        .http_cache
+        .clone()
+        .or_else(default_http_cache)
+        .ok_or(anyhow::anyhow!(
+            "--allow-remote needs --http-cache (HOME not set)"
+        ))?;
+    Ok(loader.with_http(
+        HttpLoader::new(cache)
+            .offline(args.offline)
+            .allow_http(args.allow_http),
+    ))
+}
+
+#[cfg(not(feature = "http_imports"))]
+fn with_remote_imports(_loader: FileLoader, _args: &CliArgs) -> anyhow::Result<FileLoader> {
+    Err(anyhow::anyhow!(
+        "--allow-remote needs the http_imports feature"
+    ))
+}
+
+/// Enable `process` global exposing `--env` variables
+#[cfg(feature = "process")]
+fn with_process(env: JsEnvBuilder, args: &CliArgs) -> anyhow::Result<JsEnvBuilder> {
+    Ok(args
+        .env
+        .iter()
+        .fold(env.process(true), |env, name| env.process_env(name)))
+}
+
+#[cfg(not(feature = "process"))]
+fn with_process(_env: JsEnvBuilder, _args: &CliArgs) -> anyhow::Result<JsEnvBuilder> {
+    Err(anyhow::anyhow!("--process/--env need the process feature"))
+}
+
+/// Native plugins from `--plugin` (loaded once, installed in each session)
+struct Plugins(#[cfg(feature = "plugin")] Vec<Plugin>);
+
+impl Plugins {
+    #[cfg(feature = "plugin")]
+    fn load(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        paths
+            .iter()
+            .map(Plugin::load)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    #[cfg(not(feature = "plugin"))]
+    fn load(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        if !paths.is_empty() {
+            return Err(anyhow::anyhow!("--plugin needs the plugin feature"));
+        }
+        Ok(Self())
+    }
+
+    #[cfg(feature = "plugin")]
+    fn install(&self, env: JsEnvBuilder) -> JsEnvBuilder {
+        self.0
+            .iter()
+            .fold(env, |env, plugin| env.plugin(plugin.clone()))
+    }
+
+    #[cfg(not(feature = "plugin"))]
+    fn install(&self, env: JsEnvBuilder) -> JsEnvBuilder {
+        env
+    }
+}
+
+#[derive(Debug, Clone, rquickjs::class::Trace, rquickjs::JsLifetime, serde::Serialize)]
 #[rquickjs::class]
 struct Stuff {
     #[qjs(get, set)]
@@ -0,0 +1,57 @@
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use rquickjs_test::plugin::{PluginVTable, PLUGIN_ABI_VERSION};
+
+// Example plugin
+//
+//   cargo build --example echo_plugin
+//   cargo run --example demo -- --plugin target/debug/examples/libecho_plugin.so \
+//       --script 'echo.upper("hello", "world").then(console.log)'
+
+static VTABLE: PluginVTable = PluginVTable {
+    abi_version: PLUGIN_ABI_VERSION,
+    name: c"echo".as_ptr(),
+    functions: c"echo,upper".as_ptr(),
+    call,
+    free,
+};
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rqjs_plugin_entry() -> *const PluginVTable {
+    &VTABLE
+}
+
+/// echo(...args) => args, upper(...strings) => "JOINED STRING"
+fn dispatch(function: &str, args: &str) -> Result<String, String> {
+    match function {
+        "echo" => Ok(args.to_string()),
+        "upper" => {
+            let args = serde_json::from_str::<Vec<String>>(args).map_err(|e| e.to_string())?;
+            serde_json::to_string(&args.join(" ").to_uppercase()).map_err(|e| e.to_string())
+        }
+        f => Err(format!("Unknown function: {f}")),
+    }
+}
+
+unsafe extern "C" fn call(
+    function: *const c_char,
+    args_json: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let function = unsafe { CStr::from_ptr(function) }.to_string_lossy();
+    let args = unsafe { CStr::from_ptr(args_json) }.to_string_lossy();
+    match dispatch(&function, &args) {
+        Ok(out) => CString::new(out).unwrap_or_default().into_raw(),
+        Err(e) => {
+            unsafe { *error = CString::new(e).unwrap_or_default().into_raw() };
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
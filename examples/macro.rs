@@ -1,6 +1,10 @@
 use anyhow::{anyhow, Result};
 use rquickjs::class::Trace;
-use rquickjs::{ArrayBuffer, CatchResultExt, Class, Context, Ctx, JsLifetime, Runtime, Value};
+use rquickjs::{
+    function::This, object::Accessor, ArrayBuffer, CatchResultExt, Class, Context, Ctx, JsLifetime,
+    Runtime, Value,
+};
+use rquickjs_test::class::{define_lazy_property, define_serde_class, PropertyOptions};
 
 #[rquickjs::module(rename_vars = "camelCase")]
 mod native_api {
@@ -29,29 +33,18 @@ mod native_api {
     }
 }
 
-#[derive(Trace, JsLifetime, Clone, Debug)]
+#[derive(Trace, JsLifetime, Clone, Debug, serde::Serialize)]
 #[rquickjs::class]
 pub struct TestClass {
     #[qjs(get, set)]
     pub name: String,
+    // Exposed as a lazy `data` ArrayBuffer (and via `text`)
+    #[serde(skip)]
     pub data: Vec<u8>,
 }
 
 #[rquickjs::methods]
 impl TestClass {
-    #[qjs(get, rename = "data")]
-    pub fn get_data<'js>(&self, ctx: Ctx<'js>) -> rquickjs::Result<ArrayBuffer<'js>> {
-        ArrayBuffer::new(ctx.clone(), self.data.clone())
-    }
-    #[qjs(get, rename = "text")]
-    pub fn get_text(&self) -> rquickjs::Result<String> {
-        Ok(String::from_utf8(self.data.clone())?)
-    }
-    #[qjs(set, rename = "text")]
-    pub fn set_text(&mut self, text: String) -> rquickjs::Result<()> {
-        self.data = text.as_bytes().to_vec();
-        Ok(())
-    }
     pub fn format(&self) -> rquickjs::Result<String> {
         Ok(format!("{:?}", self))
     }
@@ -113,7 +106,9 @@ fn main() -> Result<()> {
             const v = new Int8Array(t.data);
             print_v(v);
             t.text = "CHANGED";
+            print_v(t.data.byteLength);
             print(t.format());
+            print(`${t} ${JSON.stringify(t)}`);
 
             throw_ex("BYE");
 
@@ -125,6 +120,34 @@ fn main() -> Result<()> {
         ctx.globals().set("print", js_print).unwrap();
         ctx.globals().set("print_v", js_print_v).unwrap();
 
+        define_serde_class::<TestClass>(&ctx)?;
+        // `data` ArrayBuffer is only created when accessed (and hidden from enumeration)
+        define_lazy_property::<TestClass, _>(
+            &ctx,
+            "data",
+            PropertyOptions {
+                configurable: true,
+                ..Default::default()
+            },
+            |ctx, t| Ok(ArrayBuffer::new(ctx.clone(), t.data.clone())?.into_value()),
+        )?;
+        // `text` is defined outside #[methods] so the setter can drop the
+        // cached `data` buffer (recomputed on next access)
+        let proto =
+            Class::<TestClass>::prototype(&ctx)?.ok_or(anyhow!("TestClass has no prototype"))?;
+        proto.prop(
+            "text",
+            Accessor::new(
+                |this: This<Class<TestClass>>| -> rquickjs::Result<String> {
+                    Ok(String::from_utf8(this.0.borrow().data.clone())?)
+                },
+                |this: This<Class<TestClass>>, text: String| -> rquickjs::Result<()> {
+                    this.0.borrow_mut().data = text.into_bytes();
+                    this.0.as_inner().remove("data")
+                },
+            )
+            .configurable(),
+        )?;
         let cls = Class::instance(
             ctx.clone(),
             TestClass {
@@ -1,7 +1,8 @@
 use argh::FromArgs;
 
 use rquickjs::{async_with, AsyncContext, AsyncRuntime};
-use rquickjs_test::run::{call_fn, get_script, repl_rl, run_module, run_script};
+use rquickjs_test::repl::{repl_rustyline, ReplOptions};
+use rquickjs_test::run::{call_fn, get_script, run_module, run_script, PREV};
 use rquickjs_test::util::{
     json_to_value, register_fns, register_oneshot, register_tx_channel, value_to_json,
 };
@@ -70,19 +71,19 @@ async fn main() -> anyhow::Result<()> {
         register_oneshot(ctx.clone(), oneshot_tx, "resolve")?;
         register_tx_channel(ctx.clone(), tx, "send")?;
 
-        // Run modules
+        // Run modules then scripts - each result is bound to $prev for the next
         for module in args.module {
-            run_module(ctx.clone(),get_script(&module)?).await?;
+            let prev = run_module(ctx.clone(),get_script(&module)?).await?;
+            ctx.globals().set(PREV, prev)?;
         }
-
-        // Run scripts
         for script in args.script {
-            run_script(ctx.clone(),get_script(&script)?).await?;
+            let prev = run_script(ctx.clone(),get_script(&script)?).await?;
+            ctx.globals().set(PREV, prev)?;
         }
 
         // Run REPL
         if args.repl {
-            repl_rl(ctx.clone()).await?;
+            repl_rustyline(ctx.clone(), &ReplOptions::default()).await?;
         }
 
         // Call JS
@@ -0,0 +1,152 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use argh::FromArgs;
+use rquickjs::{async_with, AsyncContext, AsyncRuntime};
+
+use rquickjs_test::run::run_module;
+use rquickjs_test::util::{register_fns, register_rx_channel, register_tx_channel};
+
+#[derive(FromArgs)]
+/// Stress test host <-> JS channel messaging
+struct CliArgs {
+    #[argh(option, default = "10_000")]
+    /// number of messages
+    messages: u32,
+    #[argh(option, default = "0")]
+    /// messages per second sent by host (0 = unlimited)
+    rate: u32,
+    #[argh(option, default = "30")]
+    /// timeout (secs)
+    timeout: u64,
+}
+
+/// Receive messages from host and publish back while a timer chain keeps
+/// firing - returns number of timer ticks
+const SCRIPT: &str = r#"
+let done = false;
+let ticks = 0;
+const tick = () => {
+    ticks++;
+    if (!done) setTimeout(tick, 0);
+};
+setTimeout(tick, 0);
+for (let i = 0; i < MESSAGES; i++) {
+    const seq = await recv();
+    await publish(seq);
+}
+done = true;
+export default ticks;
+"#;
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args: CliArgs = argh::from_env();
+    let n = args.messages;
+
+    let rt = AsyncRuntime::new()?;
+    let ctx = AsyncContext::full(&rt).await?;
+
+    let (to_js_tx, to_js_rx) = tokio::sync::mpsc::unbounded_channel::<u32>();
+    let (from_js_tx, mut from_js_rx) = tokio::sync::mpsc::unbounded_channel::<u32>();
+
+    // Send time of each message (indexed by sequence number)
+    let sent = Arc::new(Mutex::new(vec![None::<Instant>; n as usize]));
+
+    let start = Instant::now();
+
+    let sender = {
+        let sent = sent.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                (args.rate > 0).then(|| tokio::time::interval(Duration::from_secs(1) / args.rate));
+            for seq in 0..n {
+                if let Some(interval) = interval.as_mut() {
+                    interval.tick().await;
+                }
+                if let Ok(mut sent) = sent.lock() {
+                    sent[seq as usize] = Some(Instant::now());
+                }
+                if to_js_tx.send(seq).is_err() {
+                    eprintln!("[-] Send Channel Closed");
+                    break;
+                }
+            }
+        })
+    };
+
+    // Collect latency (host send -> JS publish received) and check ordering
+    let receiver = {
+        let sent = sent.clone();
+        tokio::spawn(async move {
+            let mut latency = Vec::with_capacity(n as usize);
+            let mut out_of_order = 0_usize;
+            let mut expected = 0;
+            while expected < n {
+                let Some(seq) = from_js_rx.recv().await else {
+                    break;
+                };
+                if seq != expected {
+                    out_of_order += 1;
+                }
+                if let Some(t) = sent.lock().ok().and_then(|s| s[seq as usize]) {
+                    latency.push(t.elapsed());
+                }
+                expected += 1;
+            }
+            (latency, out_of_order)
+        })
+    };
+
+    let ticks = tokio::time::timeout(
+        Duration::from_secs(args.timeout),
+        async_with!(ctx => |ctx| {
+            register_fns(&ctx)?;
+            register_rx_channel(ctx.clone(), to_js_rx, "recv")?;
+            register_tx_channel(ctx.clone(), from_js_tx, "publish")?;
+            ctx.globals().set("MESSAGES", n)?;
+            let ticks = run_module(ctx.clone(), SCRIPT.to_string())
+                .await?
+                .as_number()
+                .unwrap_or_default();
+            Ok::<f64, anyhow::Error>(ticks)
+        }),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("Timeout"))??;
+
+    sender.await?;
+    let (mut latency, out_of_order) = receiver.await?;
+    let elapsed = start.elapsed();
+    latency.sort();
+
+    println!("[+] Messages:     {}", latency.len());
+    println!("[+] Elapsed:      {elapsed:?}");
+    println!(
+        "[+] Throughput:   {:.0} msg/s",
+        latency.len() as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "[+] Latency:      p50={:?} p99={:?} max={:?}",
+        percentile(&latency, 0.5),
+        percentile(&latency, 0.99),
+        latency.last().copied().unwrap_or_default()
+    );
+    println!("[+] Timer Ticks:  {ticks}");
+    println!("[+] Out of Order: {out_of_order}");
+
+    if out_of_order > 0 || latency.len() != n as usize {
+        return Err(anyhow::anyhow!(
+            "Ordering Error: {out_of_order} out of order, {}/{n} received",
+            latency.len()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+/* C ABI for rquickjs-test (build with
+ * `cargo rustc --lib --release --features ffi --crate-type cdylib`) */
+#ifndef RQJS_H
+#define RQJS_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ContextHandle rqjs_context;
+
+/* Receives call arguments as a JSON array and returns result as JSON (or
+ * NULL to throw) - returned string is owned by the host and only needs to
+ * stay valid until the callback is called again */
+typedef const char *(*rqjs_callback)(void *user_data, const char *args_json);
+
+/* Create context (NULL on failure) */
+rqjs_context *rqjs_context_new(void);
+
+/* Destroy context */
+void rqjs_context_free(rqjs_context *ctx);
+
+/* Evaluate script - returns result as JSON (free with rqjs_string_free)
+ * or NULL on error (see rqjs_last_error) */
+char *rqjs_eval(rqjs_context *ctx, const char *script);
+
+/* Call fn at dotted path with args_json (JSON array, NULL for no args) -
+ * returns result as JSON (free with rqjs_string_free) or NULL on error */
+char *rqjs_call(rqjs_context *ctx, const char *path, const char *args_json);
+
+/* Register global fn calling callback - returns 0 on success, -1 on error
+ * (including a NULL callback) */
+int rqjs_register(rqjs_context *ctx, const char *name, rqjs_callback callback,
+                  void *user_data);
+
+/* Last error message (NULL if previous call succeeded) - owned by context */
+const char *rqjs_last_error(const rqjs_context *ctx);
+
+/* Free string returned by rqjs_eval/rqjs_call */
+void rqjs_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,238 @@
+use std::future::Future;
+
+use rquickjs::{
+    class::{Trace, Tracer},
+    function::Opt,
+    CaughtError, Class, Ctx, Exception, Function, JsLifetime, Object, Value,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::class::define_class;
+use crate::error::JsError;
+use crate::events::{call_listeners, Listeners};
+use crate::timers::delay;
+use crate::util::sleep_lag;
+
+/// Host cancellation token backing an `AbortSignal`
+#[derive(Clone, Default)]
+struct Token(CancellationToken);
+
+impl<'js> Trace<'js> for Token {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+/// `AbortSignal` (created by `AbortController`, `AbortSignal.abort()` or
+/// `AbortSignal.timeout(ms)`)
+///
+/// Host async functions accepting a signal race their future against
+/// [`AbortSignal::token`] (see [`abortable`]) so aborting drops the Rust
+/// future rather than just ignoring its result
+#[derive(Trace, JsLifetime)]
+#[rquickjs::class]
+pub struct AbortSignal<'js> {
+    token: Token,
+    reason: Option<Value<'js>>,
+    listeners: Listeners<'js>,
+    onabort: Option<Function<'js>>,
+}
+
+impl<'js> AbortSignal<'js> {
+    /// New (not aborted) signal
+    pub fn instance(ctx: &Ctx<'js>) -> rquickjs::Result<Class<'js, Self>> {
+        Class::instance(
+            ctx.clone(),
+            Self {
+                token: Token::default(),
+                reason: None,
+                listeners: Listeners::default(),
+                onabort: None,
+            },
+        )
+    }
+
+    /// Token cancelled when the signal is aborted
+    pub fn token(&self) -> CancellationToken {
+        self.token.0.clone()
+    }
+}
+
+#[rquickjs::methods]
+impl<'js> AbortSignal<'js> {
+    #[qjs(get)]
+    pub fn aborted(&self) -> bool {
+        self.token.0.is_cancelled()
+    }
+
+    #[qjs(get)]
+    pub fn reason(&self, ctx: Ctx<'js>) -> Value<'js> {
+        self.reason
+            .clone()
+            .unwrap_or_else(|| Value::new_undefined(ctx))
+    }
+
+    #[qjs(get, rename = "onabort")]
+    pub fn onabort(&self, ctx: Ctx<'js>) -> Value<'js> {
+        match &self.onabort {
+            Some(f) => f.clone().into_value(),
+            None => Value::new_null(ctx),
+        }
+    }
+
+    #[qjs(set, rename = "onabort")]
+    pub fn set_onabort(&mut self, f: Value<'js>) {
+        self.onabort = f.as_function().cloned();
+    }
+
+    /// Throw `reason` if aborted
+    #[qjs(rename = "throwIfAborted")]
+    pub fn throw_if_aborted(&self, ctx: Ctx<'js>) -> rquickjs::Result<()> {
+        match &self.reason {
+            Some(reason) => Err(ctx.throw(reason.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// addEventListener("abort", fn, {once}?)
+    #[qjs(rename = "addEventListener")]
+    pub fn add_event_listener(
+        &mut self,
+        event: String,
+        f: Function<'js>,
+        options: Opt<Value<'js>>,
+    ) -> rquickjs::Result<()> {
+        self.listeners.add_with_options(&event, f, options.0)
+    }
+
+    /// removeEventListener("abort", fn)
+    #[qjs(rename = "removeEventListener")]
+    pub fn remove_event_listener(&mut self, event: String, f: Function<'js>) {
+        self.listeners.remove(&event, &f);
+    }
+
+    /// AbortSignal.abort(reason?) - already aborted signal
+    #[qjs(static, rename = "abort")]
+    pub fn new_aborted(
+        ctx: Ctx<'js>,
+        reason: Opt<Value<'js>>,
+    ) -> rquickjs::Result<Class<'js, Self>> {
+        let signal = Self::instance(&ctx)?;
+        abort_signal(&ctx, &signal, reason.0)?;
+        Ok(signal)
+    }
+
+    /// AbortSignal.timeout(ms) - signal aborted with `TimeoutError` after
+    /// `ms`, clamped as for `setTimeout` (the pending timer keeps the event
+    /// loop alive)
+    #[qjs(static)]
+    pub fn timeout(ctx: Ctx<'js>, ms: f64) -> rquickjs::Result<Class<'js, Self>> {
+        let signal = Self::instance(&ctx)?;
+        let token = signal.borrow().token();
+        let timer_ctx = ctx.clone();
+        let timer_signal = signal.clone();
+        let d = delay(Some(ms));
+        ctx.spawn(async move {
+            if token.run_until_cancelled(sleep_lag(d)).await.is_none() {
+                return;
+            }
+            let ctx = timer_ctx;
+            let reason = named_error(&ctx, "TimeoutError", "The operation timed out");
+            if let Err(e) = reason.and_then(|r| abort_signal(&ctx, &timer_signal, Some(r))) {
+                let caught = CaughtError::from_error(&ctx, e);
+                eprintln!("Uncaught {}", JsError::from_caught(&caught));
+            }
+        });
+        Ok(signal)
+    }
+}
+
+/// `AbortController` - `abort(reason?)` aborts `signal`
+#[derive(Trace, JsLifetime)]
+#[rquickjs::class]
+pub struct AbortController<'js> {
+    signal: Class<'js, AbortSignal<'js>>,
+}
+
+#[rquickjs::methods]
+impl<'js> AbortController<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>) -> rquickjs::Result<Self> {
+        Ok(Self {
+            signal: AbortSignal::instance(&ctx)?,
+        })
+    }
+
+    #[qjs(get)]
+    pub fn signal(&self) -> Class<'js, AbortSignal<'js>> {
+        self.signal.clone()
+    }
+
+    pub fn abort(&self, ctx: Ctx<'js>, reason: Opt<Value<'js>>) -> rquickjs::Result<()> {
+        abort_signal(&ctx, &self.signal, reason.0)
+    }
+}
+
+/// Register `AbortController` and `AbortSignal` classes
+pub fn register_abort(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    define_class::<AbortController>(ctx)?;
+    define_class::<AbortSignal>(ctx)?;
+    Ok(())
+}
+
+/// Abort `signal` with `reason` (default `AbortError`), cancelling its
+/// token and calling `abort` listeners - no-op if already aborted
+pub fn abort_signal<'js>(
+    ctx: &Ctx<'js>,
+    signal: &Class<'js, AbortSignal<'js>>,
+    reason: Option<Value<'js>>,
+) -> rquickjs::Result<()> {
+    let reason = match reason.filter(|r| !r.is_undefined()) {
+        Some(reason) => reason,
+        None => named_error(ctx, "AbortError", "This operation was aborted")?,
+    };
+    let listeners = {
+        let mut signal = signal.borrow_mut();
+        if signal.aborted() {
+            return Ok(());
+        }
+        signal.reason = Some(reason);
+        signal.token.0.cancel();
+        let mut listeners = signal.onabort.iter().cloned().collect::<Vec<_>>();
+        listeners.extend(signal.listeners.take("abort"));
+        listeners
+    };
+    let event = Object::new(ctx.clone())?;
+    event.set("type", "abort")?;
+    event.set("target", signal.clone())?;
+    call_listeners(ctx, listeners, &event.into_value())
+}
+
+/// Run `fut` unless `signal` is aborted first - the future is then dropped
+/// (cancelling the host work) and the signal's reason is thrown
+pub async fn abortable<'js, F: Future>(
+    ctx: &Ctx<'js>,
+    signal: Option<&Class<'js, AbortSignal<'js>>>,
+    fut: F,
+) -> rquickjs::Result<F::Output> {
+    let Some(signal) = signal else {
+        return Ok(fut.await);
+    };
+    let token = signal.borrow().token();
+    match token.run_until_cancelled(fut).await {
+        Some(output) => Ok(output),
+        None => {
+            let reason = signal.borrow().reason(ctx.clone());
+            Err(ctx.throw(reason))
+        }
+    }
+}
+
+/// Error with `name` (eg. `AbortError`) used as an abort reason
+pub(crate) fn named_error<'js>(
+    ctx: &Ctx<'js>,
+    name: &str,
+    msg: &str,
+) -> rquickjs::Result<Value<'js>> {
+    let ex = Exception::from_message(ctx.clone(), msg)?;
+    ex.set("name", name)?;
+    Ok(ex.into_value())
+}
@@ -0,0 +1,313 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use rquickjs::{
+    function::{Args, Async, Opt, Rest},
+    object::Accessor,
+    promise::MaybePromise,
+    CatchResultExt, CaughtError, Ctx, Exception, Function, Object, Value,
+};
+
+use crate::compat::Instant;
+use crate::error::JsError;
+use crate::run::exit_requested;
+use crate::timers::delay;
+
+/// Default consecutive failures before the circuit opens
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+/// Default time the circuit stays open before a probe call is allowed
+pub const DEFAULT_RESET: Duration = Duration::from_secs(30);
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls pass through
+    Closed,
+    /// Calls are rejected until the reset period has elapsed
+    Open,
+    /// One probe call is allowed - success closes the circuit, failure
+    /// re-opens it
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        }
+    }
+}
+
+/// State change reported by [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: CircuitState,
+    pub to: CircuitState,
+}
+
+/// Host-side circuit breaker state machine
+///
+/// Methods return a [`Transition`] when they change the state (so callers
+/// can report it). Results are recorded against the generation returned by
+/// [`CircuitBreaker::try_acquire`] - results of calls started before the
+/// last transition are ignored (so a slow call acquired while closed can't
+/// close a circuit that has since opened).
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    reset: Duration,
+    state: CircuitState,
+    failures: u32,
+    opened: Instant,
+    probing: bool,
+    generation: u64,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            reset,
+            state: CircuitState::Closed,
+            failures: 0,
+            opened: Instant::now(),
+            probing: false,
+            generation: 0,
+        }
+    }
+
+    /// Current state (open circuits become half-open once `reset` elapses)
+    pub fn state(&self) -> CircuitState {
+        if self.reset_elapsed() {
+            CircuitState::HalfOpen
+        } else {
+            self.state
+        }
+    }
+
+    /// Consecutive failures while closed
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Move from open to half-open after the reset period
+    pub fn poll(&mut self) -> Option<Transition> {
+        if self.reset_elapsed() {
+            self.transition(CircuitState::HalfOpen)
+        } else {
+            None
+        }
+    }
+
+    /// Generation to record the call result against if a call may proceed
+    /// (only one probe at a time when half-open)
+    pub fn try_acquire(&mut self) -> Option<u64> {
+        match self.state {
+            CircuitState::Closed => {}
+            CircuitState::Open => return None,
+            CircuitState::HalfOpen if self.probing => return None,
+            CircuitState::HalfOpen => self.probing = true,
+        }
+        Some(self.generation)
+    }
+
+    /// Call acquired at `generation` succeeded - closes the circuit
+    pub fn record_success(&mut self, generation: u64) -> Option<Transition> {
+        if generation != self.generation {
+            return None;
+        }
+        self.failures = 0;
+        self.transition(CircuitState::Closed)
+    }
+
+    /// Call acquired at `generation` failed - opens the circuit after
+    /// `failure_threshold` consecutive failures (or a failed probe)
+    pub fn record_failure(&mut self, generation: u64) -> Option<Transition> {
+        if generation != self.generation {
+            return None;
+        }
+        match self.state {
+            CircuitState::Closed => {
+                self.failures += 1;
+                if self.failures < self.failure_threshold {
+                    return None;
+                }
+            }
+            CircuitState::Open => return None,
+            CircuitState::HalfOpen => {}
+        }
+        self.opened = Instant::now();
+        self.transition(CircuitState::Open)
+    }
+
+    fn reset_elapsed(&self) -> bool {
+        self.state == CircuitState::Open && self.opened.elapsed() >= self.reset
+    }
+
+    fn transition(&mut self, state: CircuitState) -> Option<Transition> {
+        self.probing = false;
+        if self.state == state {
+            return None;
+        }
+        if state != CircuitState::Closed {
+            self.failures = 0;
+        }
+        self.generation += 1;
+        let from = std::mem::replace(&mut self.state, state);
+        Some(Transition { from, to: state })
+    }
+}
+
+/// Register `circuitBreaker(fn, opts?)` global
+pub fn register_breaker(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    ctx.globals().set("circuitBreaker", js_circuit_breaker)?;
+    Ok(())
+}
+
+/// circuitBreaker(fn, {failureThreshold = 5, resetMs = 30000, onStateChange})
+///
+/// Returns an async wrapper which rejects with `CircuitOpenError` (without
+/// calling `fn`) after `failureThreshold` consecutive failures. Once
+/// `resetMs` has elapsed a single probe call is allowed through - success
+/// closes the circuit, failure re-opens it. `onStateChange({from, to})` is
+/// called on each transition and the wrapper has `state`/`failures` getters
+///
+/// `resetMs` is clamped as for `setTimeout`
+#[rquickjs::function]
+fn circuit_breaker<'js>(
+    ctx: Ctx<'js>,
+    f: Function<'js>,
+    opts: Opt<Object<'js>>,
+) -> rquickjs::Result<Function<'js>> {
+    let (threshold, reset, on_change) = match opts.0 {
+        Some(opts) => (
+            opts.get::<_, Option<f64>>("failureThreshold")?,
+            opts.get::<_, Option<f64>>("resetMs")?,
+            opts.get::<_, Option<Function>>("onStateChange")?,
+        ),
+        None => (None, None, None),
+    };
+    let threshold = threshold.map_or(DEFAULT_FAILURE_THRESHOLD, |n| n.max(1.0) as u32);
+    let reset = reset.map_or(DEFAULT_RESET, |ms| delay(Some(ms)));
+    let breaker = Rc::new(RefCell::new(CircuitBreaker::new(threshold, reset)));
+    let notify = Rc::new(StateChange(on_change));
+    let b = breaker.clone();
+    let wrapper = Function::new(
+        ctx.clone(),
+        Async(move |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+            let breaker = b.clone();
+            let notify = notify.clone();
+            let f = f.clone();
+            async move {
+                let acquired = {
+                    let mut breaker = breaker.borrow_mut();
+                    let changed = breaker.poll();
+                    (breaker.try_acquire(), changed)
+                };
+                notify.report(&ctx, acquired.1)?;
+                let Some(generation) = acquired.0 else {
+                    return Err(circuit_open(&ctx));
+                };
+                let mut call_args = Args::new(ctx.clone(), args.len());
+                call_args.push_args(args.iter())?;
+                let result = match f.call_arg::<MaybePromise>(call_args) {
+                    Ok(p) => p.into_future::<Value>().await,
+                    Err(e) => Err(e),
+                };
+                if result.is_err() && exit_requested(&ctx) {
+                    return result;
+                }
+                // Catch before calling onStateChange so the error is kept
+                let result = result.catch(&ctx);
+                let changed = if result.is_ok() {
+                    breaker.borrow_mut().record_success(generation)
+                } else {
+                    breaker.borrow_mut().record_failure(generation)
+                };
+                notify.report(&ctx, changed)?;
+                result.map_err(|e| e.throw(&ctx))
+            }
+        }),
+    )?;
+    let b = breaker.clone();
+    wrapper.prop(
+        "state",
+        Accessor::new_get(move || b.borrow().state().as_str()).configurable(),
+    )?;
+    wrapper.prop(
+        "failures",
+        Accessor::new_get(move || breaker.borrow().failures()).configurable(),
+    )?;
+    Ok(wrapper)
+}
+
+/// Optional `onStateChange` callback
+struct StateChange<'js>(Option<Function<'js>>);
+
+impl<'js> StateChange<'js> {
+    fn report(&self, ctx: &Ctx<'js>, changed: Option<Transition>) -> rquickjs::Result<()> {
+        let (Some(transition), Some(on_change)) = (changed, &self.0) else {
+            return Ok(());
+        };
+        let event = Object::new(ctx.clone())?;
+        event.set("from", transition.from.as_str())?;
+        event.set("to", transition.to.as_str())?;
+        match on_change.call::<_, ()>((event,)) {
+            Ok(()) => Ok(()),
+            Err(e) if exit_requested(ctx) => Err(e),
+            Err(e) => {
+                let caught = CaughtError::from_error(ctx, e);
+                eprintln!("Uncaught {}", JsError::from_caught(&caught));
+                Ok(())
+            }
+        }
+    }
+}
+
+fn circuit_open(ctx: &Ctx<'_>) -> rquickjs::Error {
+    match Exception::from_message(ctx.clone(), "Circuit open")
+        .and_then(|ex| ex.set("name", "CircuitOpenError").map(|_| ex))
+    {
+        Ok(ex) => ctx.throw(ex.into_value()),
+        Err(e) => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let mut b = CircuitBreaker::new(2, Duration::from_secs(60));
+        let g = b.try_acquire().unwrap();
+        assert_eq!(b.record_failure(g), None);
+        let t = b.record_failure(g).unwrap();
+        assert_eq!((t.from, t.to), (CircuitState::Closed, CircuitState::Open));
+        assert_eq!(b.try_acquire(), None);
+    }
+
+    #[test]
+    fn test_stale_results_ignored() {
+        let mut b = CircuitBreaker::new(1, Duration::ZERO);
+        let slow = b.try_acquire().unwrap();
+        let g = b.try_acquire().unwrap();
+        assert!(b.record_failure(g).is_some());
+        // Call started while closed can't close the open circuit
+        assert_eq!(b.record_success(slow), None);
+        assert_eq!(b.state(), CircuitState::HalfOpen);
+        assert!(b.poll().is_some());
+        let probe = b.try_acquire().unwrap();
+        assert_eq!(b.try_acquire(), None);
+        // ...or fail the probe
+        assert_eq!(b.record_failure(slow), None);
+        let t = b.record_success(probe).unwrap();
+        assert_eq!(
+            (t.from, t.to),
+            (CircuitState::HalfOpen, CircuitState::Closed)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use anyhow::anyhow;
+
+/// Environment variable holding the bundle key (64 hex chars / 256 bits)
+pub const BUNDLE_KEY_ENV: &str = "RQUICKJS_BUNDLE_KEY";
+
+/// File extension for encrypted bundles (`@script.js.enc`)
+pub const BUNDLE_EXT: &str = ".enc";
+
+const NONCE_LEN: usize = 12;
+
+/// Read bundle key from environment
+pub fn bundle_key() -> anyhow::Result<Vec<u8>> {
+    let hex = std::env::var(BUNDLE_KEY_ENV).map_err(|_| anyhow!("{BUNDLE_KEY_ENV} not set"))?;
+    decode_hex(hex.trim())
+}
+
+/// Decode hex bytewise (slicing the str could split a multi-byte char)
+fn decode_hex(s: &str) -> anyhow::Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return Err(anyhow!("Invalid hex key"));
+    }
+    let digit = |b: u8| char::from(b).to_digit(16);
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match (digit(pair[0]), digit(pair[1])) {
+            (Some(hi), Some(lo)) => Ok(((hi << 4) | lo) as u8),
+            _ => Err(anyhow!("Invalid hex key")),
+        })
+        .collect()
+}
+
+/// Encrypt script with AES-256-GCM (output is `nonce || ciphertext`)
+pub fn encrypt_bundle(script: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("Invalid key length"))?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, script)
+        .map_err(|_| anyhow!("Bundle encryption failed"))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+/// Decrypt bundle created by [`encrypt_bundle`]
+pub fn decrypt_bundle(bundle: &[u8], key: &[u8]) -> anyhow::Result<String> {
+    if bundle.len() < NONCE_LEN {
+        return Err(anyhow!("Invalid bundle"));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("Invalid key length"))?;
+    let (nonce, ciphertext) = bundle.split_at(NONCE_LEN);
+    let script = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Bundle decryption failed (wrong key or corrupt bundle)"))?;
+    Ok(String::from_utf8(script)?)
+}
+
+/// Read and decrypt bundle file using key from environment
+pub fn read_bundle(path: &str) -> anyhow::Result<String> {
+    decrypt_bundle(&std::fs::read(path)?, &bundle_key()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("00ff7A").unwrap(), vec![0x00, 0xff, 0x7a]);
+        for key in ["abc", "zz", "+f", "é", "aé0"] {
+            assert_eq!(decode_hex(key).unwrap_err().to_string(), "Invalid hex key");
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let key = decode_hex(&"11".repeat(32)).unwrap();
+        let bundle = encrypt_bundle(b"console.log(1)", &key).unwrap();
+        assert_eq!(decrypt_bundle(&bundle, &key).unwrap(), "console.log(1)");
+        assert!(decrypt_bundle(&bundle, &[0; 32]).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use rquickjs::{
+    function::{Async, Func},
+    promise::MaybePromise,
+    CaughtError, Ctx, Exception, Function, Object, Value,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::compat::Instant;
+use crate::convert::{json_to_value, value_to_json};
+use crate::error::JsError;
+
+/// Default max entries for [`Cache`]
+pub const DEFAULT_CACHE_CAPACITY: usize = 1000;
+
+/// Result of an in-flight computation (None until complete)
+type Computed = Option<Result<String, String>>;
+
+/// Host-side TTL/LRU cache of JSON values shared between contexts
+///
+/// Unlike `kv` entries expire and the least recently used entry is evicted
+/// once `capacity` is reached. Concurrent [`Cache::get_or_compute`] calls
+/// for the same key are coalesced - only the first computes the value and
+/// the others wait for its result (so serve-mode requests don't stampede
+/// an expensive fetch)
+pub struct Cache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    inflight: HashMap<String, watch::Receiver<Computed>>,
+    /// Access counter used for LRU ordering
+    tick: u64,
+}
+
+struct CacheEntry {
+    json: String,
+    /// None if the ttl is too large to represent (never expires)
+    expires: Option<Instant>,
+    used: u64,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self, now: Instant) -> bool {
+        self.expires.is_none_or(|expires| expires > now)
+    }
+}
+
+impl CacheInner {
+    /// Fresh value for key (marks entry as recently used)
+    fn fresh(&mut self, key: &str) -> Option<String> {
+        self.tick += 1;
+        let tick = self.tick;
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.is_fresh(Instant::now()) => {
+                entry.used = tick;
+                Some(entry.json.clone())
+            }
+            Some(_) => {
+                self.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&mut self, key: &str, json: String, ttl: Duration, capacity: usize) {
+        let now = Instant::now();
+        self.entries.retain(|_, entry| entry.is_fresh(now));
+        while self.entries.len() >= capacity && !self.entries.contains_key(key) {
+            let Some(lru) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&lru);
+        }
+        self.tick += 1;
+        self.entries.insert(
+            key.to_string(),
+            CacheEntry {
+                json,
+                expires: now.checked_add(ttl),
+                used: self.tick,
+            },
+        );
+    }
+}
+
+/// Removes in-flight marker if the computing future is dropped before
+/// completing (waiters then retry)
+struct Inflight<'a> {
+    cache: &'a Cache,
+    key: &'a str,
+    tx: Option<watch::Sender<Computed>>,
+}
+
+impl Inflight<'_> {
+    fn finish(mut self, result: &anyhow::Result<String>, ttl: Duration) {
+        if let Ok(mut inner) = self.cache.lock() {
+            inner.inflight.remove(self.key);
+            if let Ok(json) = result {
+                inner.insert(self.key, json.clone(), ttl, self.cache.capacity);
+            }
+        }
+        if let Some(tx) = self.tx.take() {
+            let computed = match result {
+                Ok(json) => Ok(json.clone()),
+                Err(e) => Err(e.to_string()),
+            };
+            let _ = tx.send(Some(computed));
+        }
+    }
+}
+
+impl Drop for Inflight<'_> {
+    fn drop(&mut self) {
+        if self.tx.is_none() {
+            return;
+        }
+        if let Ok(mut inner) = self.cache.lock() {
+            inner.inflight.remove(self.key);
+        }
+    }
+}
+
+impl Cache {
+    /// Cache holding at most `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::default(),
+        }
+    }
+
+    fn lock(&self) -> anyhow::Result<MutexGuard<'_, CacheInner>> {
+        self.inner
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Cache Mutex Error"))
+    }
+
+    /// Get unexpired value
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.lock()?.fresh(key))
+    }
+
+    /// Insert value expiring after `ttl`
+    pub fn insert(&self, key: &str, json: String, ttl: Duration) -> anyhow::Result<()> {
+        self.lock()?.insert(key, json, ttl, self.capacity);
+        Ok(())
+    }
+
+    /// Remove entry - returns false if missing
+    pub fn remove(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.lock()?.entries.remove(key).is_some())
+    }
+
+    /// Number of entries (including expired entries not yet evicted)
+    pub fn len(&self) -> usize {
+        self.lock().map(|inner| inner.entries.len()).unwrap_or(0)
+    }
+
+    /// Cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get unexpired value or compute it (once for concurrent callers) -
+    /// errors are returned to all waiters but not cached
+    pub async fn get_or_compute<F, Fut>(
+        &self,
+        key: &str,
+        ttl: Duration,
+        compute: F,
+    ) -> anyhow::Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<String>>,
+    {
+        let tx = loop {
+            let mut rx = {
+                let mut inner = self.lock()?;
+                if let Some(json) = inner.fresh(key) {
+                    return Ok(json);
+                }
+                match inner.inflight.get(key) {
+                    Some(rx) => rx.clone(),
+                    None => {
+                        let (tx, rx) = watch::channel(None);
+                        inner.inflight.insert(key.to_string(), rx);
+                        break tx;
+                    }
+                }
+            };
+            // Wait for in-flight computation (retry if it was dropped)
+            let computed = rx
+                .wait_for(Option::is_some)
+                .await
+                .ok()
+                .and_then(|c| c.clone());
+            if let Some(result) = computed {
+                return result.map_err(anyhow::Error::msg);
+            }
+        };
+        let inflight = Inflight {
+            cache: self,
+            key,
+            tx: Some(tx),
+        };
+        let result = compute().await;
+        inflight.finish(&result, ttl);
+        result
+    }
+}
+
+/// Register `cache` object with `getOrCompute(key, ttl_ms, async fn)`,
+/// `get(key)` and `delete(key)` (values must be JSON serialisable - a
+/// `ttl_ms` of `Infinity` never expires)
+///
+/// Pass the same [`Cache`] to each context to share entries between them
+pub fn register_cache<'js>(ctx: &Ctx<'js>, cache: Arc<Cache>) -> anyhow::Result<()> {
+    let obj = Object::new(ctx.clone())?;
+    let c = cache.clone();
+    obj.set(
+        "getOrCompute",
+        Func::new(Async(
+            move |ctx: Ctx<'js>, key: String, ttl: f64, f: Function<'js>| {
+                let cache = c.clone();
+                async move {
+                    let ttl = Duration::from_millis(ttl.max(0.0) as u64);
+                    let json = cache
+                        .get_or_compute(&key, ttl, || async {
+                            let v = match f.call::<_, MaybePromise>(()) {
+                                Ok(p) => p.into_future::<Value>().await,
+                                Err(e) => Err(e),
+                            };
+                            match v {
+                                Ok(v) => value_to_json(ctx.clone(), v),
+                                Err(e) => {
+                                    Err(JsError::from_caught(&CaughtError::from_error(&ctx, e))
+                                        .into())
+                                }
+                            }
+                        })
+                        .await
+                        .map_err(|e| cache_error(&ctx, &key, e))?;
+                    json_to_value(ctx.clone(), &json).map_err(|e| cache_error(&ctx, &key, e))
+                }
+            },
+        )),
+    )?;
+    let c = cache.clone();
+    obj.set(
+        "get",
+        Func::new(move |ctx: Ctx<'js>, key: String| {
+            match c.get(&key).map_err(|e| cache_error(&ctx, &key, e))? {
+                Some(json) => {
+                    json_to_value(ctx.clone(), &json).map_err(|e| cache_error(&ctx, &key, e))
+                }
+                None => Ok(Value::new_undefined(ctx.clone())),
+            }
+        }),
+    )?;
+    obj.set(
+        "delete",
+        Func::new(move |ctx: Ctx<'js>, key: String| {
+            cache.remove(&key).map_err(|e| cache_error(&ctx, &key, e))
+        }),
+    )?;
+    ctx.globals().set("cache", obj)?;
+    Ok(())
+}
+
+fn cache_error(ctx: &Ctx<'_>, key: &str, e: anyhow::Error) -> rquickjs::Error {
+    Exception::throw_message(ctx, &format!("Cache Error: {key} [{e}]"))
+}
@@ -0,0 +1,339 @@
+use anyhow::anyhow;
+use rquickjs::{
+    atom::PredefinedAtom,
+    class::JsClass,
+    function::{Func, This},
+    object::{Accessor, Property},
+    proxy::{ProxyHandler, ProxyProperty, ProxyReceiver, ProxyTarget},
+    Class, Ctx, Exception, Function, IntoJs, Object, Proxy, Symbol, Value,
+};
+use serde::Serialize;
+use std::cell::Cell;
+
+/// Class definition stored by [`crate::env::JsEnvBuilder`]
+pub(crate) type DefineFn = for<'js> fn(&Ctx<'js>) -> anyhow::Result<()>;
+
+/// Define class `C` on globals and set `Symbol.toStringTag` (so instances
+/// format as `[object Name]`) - returns the class prototype
+pub fn define_class<'js, C: JsClass<'js>>(ctx: &Ctx<'js>) -> anyhow::Result<Object<'js>> {
+    Class::<C>::define(&ctx.globals())?;
+    let proto = Class::<C>::prototype(ctx)?.ok_or(anyhow!("{} has no prototype", C::NAME))?;
+    proto.prop(
+        PredefinedAtom::SymbolToStringTag,
+        Property::from(C::NAME).configurable(),
+    )?;
+    Ok(proto)
+}
+
+/// As [`define_class`] with `toJSON` serialising instances via serde
+pub fn define_serde_class<'js, C>(ctx: &Ctx<'js>) -> anyhow::Result<Object<'js>>
+where
+    C: JsClass<'js> + Serialize,
+{
+    let proto = define_class::<C>(ctx)?;
+    proto.set(
+        "toJSON",
+        Func::new(
+            |ctx: Ctx<'js>, this: This<Class<'js, C>>| -> rquickjs::Result<Value<'js>> {
+                let json = serde_json::to_string(&*this.0.borrow()).map_err(|e| {
+                    Exception::throw_message(&ctx, &format!("{} toJSON: {e}", C::NAME))
+                })?;
+                ctx.json_parse(json)
+            },
+        ),
+    )?;
+    Ok(proto)
+}
+
+/// Primitive conversion hooks for classes wrapping numbers/strings (eg.
+/// durations, byte sizes) - see [`define_primitive`]
+pub trait JsPrimitive {
+    /// Numeric value (arithmetic, comparison, `valueOf`)
+    fn to_number(&self) -> Option<f64> {
+        None
+    }
+
+    /// String value (template strings, `toString`)
+    fn to_js_string(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Install `Symbol.toPrimitive`, `valueOf` and `toString` on the prototype
+/// of `C` (defining the class if necessary)
+///
+/// `toPrimitive` follows the hint - `"string"` prefers [`JsPrimitive::to_js_string`],
+/// `"number"`/`"default"` prefer [`JsPrimitive::to_number`]
+pub fn define_primitive<'js, C>(ctx: &Ctx<'js>) -> anyhow::Result<Object<'js>>
+where
+    C: JsClass<'js> + JsPrimitive,
+{
+    let proto = define_class::<C>(ctx)?;
+    proto.set(
+        Symbol::to_primitive(ctx.clone()),
+        Func::new(
+            |ctx: Ctx<'js>,
+             this: This<Class<'js, C>>,
+             hint: String|
+             -> rquickjs::Result<Value<'js>> {
+                let this = this.0.borrow();
+                let (number, string) = (this.to_number(), this.to_js_string());
+                match (hint.as_str(), number, string) {
+                    ("string", _, Some(s)) | (_, None, Some(s)) => s.into_js(&ctx),
+                    (_, Some(n), _) => n.into_js(&ctx),
+                    _ => Err(Exception::throw_type(
+                        &ctx,
+                        &format!("Cannot convert {} to primitive", C::NAME),
+                    )),
+                }
+            },
+        ),
+    )?;
+    proto.set(
+        PredefinedAtom::ValueOf,
+        Func::new(
+            |ctx: Ctx<'js>, this: This<Class<'js, C>>| -> rquickjs::Result<Value<'js>> {
+                match this.0.borrow().to_number() {
+                    Some(n) => n.into_js(&ctx),
+                    None => Ok(this.0.clone().into_value()),
+                }
+            },
+        ),
+    )?;
+    proto.set(
+        PredefinedAtom::ToString,
+        Func::new(|this: This<Class<'js, C>>| -> String {
+            let this = this.0.borrow();
+            this.to_js_string()
+                .or(this.to_number().map(|n| n.to_string()))
+                .unwrap_or_else(|| format!("[object {}]", C::NAME))
+        }),
+    )?;
+    Ok(proto)
+}
+
+/// Indexed collection backing a class (`Vec`, sorted map etc) - see
+/// [`define_iterable`]
+///
+/// Map-like collections should return `[key, value]` entries from `item`
+pub trait JsCollection {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Item at `index` (< `len()`)
+    fn item<'js>(&self, ctx: &Ctx<'js>, index: usize) -> rquickjs::Result<Value<'js>>;
+}
+
+/// Install `Symbol.iterator` and `length` on the prototype of `C` so
+/// instances work with `for...of`/spread without copying to an array
+pub fn define_iterable<'js, C>(ctx: &Ctx<'js>) -> anyhow::Result<Object<'js>>
+where
+    C: JsClass<'js> + JsCollection,
+{
+    let proto = define_class::<C>(ctx)?;
+    proto.set(
+        Symbol::iterator(ctx.clone()),
+        Func::new(
+            |ctx: Ctx<'js>, this: This<Class<'js, C>>| -> rquickjs::Result<Object<'js>> {
+                let collection = this.0;
+                let index = Cell::new(0);
+                let iter = Object::new(ctx.clone())?;
+                iter.set(
+                    "next",
+                    Func::new(move |ctx: Ctx<'js>| -> rquickjs::Result<Object<'js>> {
+                        let result = Object::new(ctx.clone())?;
+                        let i = index.get();
+                        let collection = collection.borrow();
+                        if i < collection.len() {
+                            result.set("value", collection.item(&ctx, i)?)?;
+                            result.set("done", false)?;
+                            index.set(i + 1);
+                        } else {
+                            result.set("done", true)?;
+                        }
+                        Ok(result)
+                    }),
+                )?;
+                Ok(iter)
+            },
+        ),
+    )?;
+    proto.prop(
+        "length",
+        Accessor::new_get(|this: This<Class<'js, C>>| this.0.borrow().len()).configurable(),
+    )?;
+    Ok(proto)
+}
+
+/// Wrap collection instance in a `Proxy` supporting index access (`c[0]`)
+///
+/// Other properties are read from the instance, with methods bound to it
+/// (so `this` is the class instance rather than the proxy)
+pub fn index_proxy<'js, C>(ctx: &Ctx<'js>, instance: Class<'js, C>) -> rquickjs::Result<Proxy<'js>>
+where
+    C: JsClass<'js> + JsCollection,
+{
+    let handler = ProxyHandler::new(ctx.clone())?.with_getter(
+        |target: ProxyTarget<'js>,
+         property: ProxyProperty<'js>,
+         _receiver: ProxyReceiver<'js>|
+         -> rquickjs::Result<Value<'js>> {
+            let ctx = target.0.ctx().clone();
+            if property.is_string() {
+                if let Ok(index) = property.to_string()?.parse::<usize>() {
+                    let collection = Class::<C>::from_object(&target.0)
+                        .ok_or(Exception::throw_type(&ctx, "Invalid proxy target"))?;
+                    let collection = collection.borrow();
+                    return if index < collection.len() {
+                        collection.item(&ctx, index)
+                    } else {
+                        Ok(Value::new_undefined(ctx))
+                    };
+                }
+            }
+            let v = target.0.get::<_, Value>(property.0)?;
+            match v.as_function() {
+                Some(f) => f
+                    .get::<_, Function>("bind")?
+                    .call((This(f.clone()), target.0.clone())),
+                None => Ok(v),
+            }
+        },
+    )?;
+    Proxy::new(ctx.clone(), instance, handler)
+}
+
+/// Property descriptor flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PropertyOptions {
+    pub enumerable: bool,
+    pub writable: bool,
+    pub configurable: bool,
+}
+
+impl PropertyOptions {
+    fn apply<'js>(&self, desc: &Object<'js>) -> rquickjs::Result<()> {
+        desc.set("enumerable", self.enumerable)?;
+        desc.set("configurable", self.configurable)?;
+        // Accessors can't have writable flag
+        if desc.contains_key("value")? {
+            desc.set("writable", self.writable)?;
+        }
+        Ok(())
+    }
+}
+
+/// Change descriptor flags of existing prototype property `name` of `C`
+/// (eg. hide a getter from enumeration)
+pub fn set_property_options<'js, C: JsClass<'js>>(
+    ctx: &Ctx<'js>,
+    name: &str,
+    opts: PropertyOptions,
+) -> anyhow::Result<()> {
+    let proto = Class::<C>::prototype(ctx)?.ok_or(anyhow!("{} has no prototype", C::NAME))?;
+    let object = ctx.globals().get::<_, Object>("Object")?;
+    let desc = object
+        .get::<_, Function>("getOwnPropertyDescriptor")?
+        .call::<_, Option<Object>>((proto.clone(), name))?
+        .ok_or(anyhow!("{}.{name} not defined", C::NAME))?;
+    opts.apply(&desc)?;
+    object
+        .get::<_, Function>("defineProperty")?
+        .call::<_, Value>((proto, name, desc))?;
+    Ok(())
+}
+
+/// Define lazy property `name` on the prototype of `C`
+///
+/// `compute` is called on first access for each instance and the result
+/// cached as an own property with `opts` - with `enumerable: false` the
+/// value is never materialised by `JSON.stringify`/`Object.keys`. Note the
+/// cached value isn't updated if the underlying Rust data changes.
+pub fn define_lazy_property<'js, C, F>(
+    ctx: &Ctx<'js>,
+    name: &str,
+    opts: PropertyOptions,
+    compute: F,
+) -> anyhow::Result<()>
+where
+    C: JsClass<'js>,
+    F: Fn(&Ctx<'js>, &C) -> rquickjs::Result<Value<'js>> + 'js,
+{
+    let proto = Class::<C>::prototype(ctx)?.ok_or(anyhow!("{} has no prototype", C::NAME))?;
+    let key = name.to_string();
+    proto.prop(
+        name,
+        Accessor::new_get(
+            move |ctx: Ctx<'js>, this: This<Class<'js, C>>| -> rquickjs::Result<Value<'js>> {
+                let v = compute(&ctx, &this.0.borrow())?;
+                let mut prop = Property::from(v.clone());
+                if opts.enumerable {
+                    prop = prop.enumerable();
+                }
+                if opts.writable {
+                    prop = prop.writable();
+                }
+                if opts.configurable {
+                    prop = prop.configurable();
+                }
+                this.0.as_inner().prop(key.as_str(), prop)?;
+                Ok(v)
+            },
+        )
+        .configurable(),
+    )?;
+    Ok(())
+}
+
+/// Add static `Name.extend({ method() { ... } })` to class `C` allowing
+/// scripts to add methods to the prototype
+///
+/// Only functions may be added and existing (native) prototype properties
+/// can't be replaced. Note `class X extends Name { ... }` also works - the
+/// Rust constructor uses the prototype of `new.target`, so subclass
+/// instances keep native methods and pass `instanceof` checks.
+pub fn allow_extend<'js, C: JsClass<'js>>(ctx: &Ctx<'js>) -> anyhow::Result<()> {
+    Class::<C>::prototype(ctx)?.ok_or(anyhow!("{} has no prototype", C::NAME))?;
+    let constructor = ctx
+        .globals()
+        .get::<_, Object>(C::NAME)
+        .map_err(|e| anyhow!("{} not defined [{e}]", C::NAME))?;
+    constructor.set(
+        "extend",
+        Func::new(
+            |ctx: Ctx<'js>, methods: Object<'js>| -> rquickjs::Result<()> {
+                // Looked up per call - capturing the prototype in the
+                // constructor's function would create an uncollectable cycle
+                let proto = Class::<C>::prototype(&ctx)?.ok_or_else(|| {
+                    Exception::throw_type(&ctx, &format!("{} has no prototype", C::NAME))
+                })?;
+                let native = ctx
+                    .globals()
+                    .get::<_, Object>("Object")?
+                    .get::<_, Function>("getOwnPropertyNames")?
+                    .call::<_, Vec<String>>((proto.clone(),))?;
+                for prop in methods.props::<String, Value>() {
+                    let (name, f) = prop?;
+                    if !f.is_function() {
+                        return Err(Exception::throw_type(
+                            &ctx,
+                            &format!("{}.extend: {name} is not a function", C::NAME),
+                        ));
+                    }
+                    if native.contains(&name) {
+                        return Err(Exception::throw_type(
+                            &ctx,
+                            &format!("{}.extend: {name} already defined", C::NAME),
+                        ));
+                    }
+                    proto.prop(name.as_str(), Property::from(f).writable().configurable())?;
+                }
+                Ok(())
+            },
+        ),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::task::Poll;
+
+use rquickjs::{promise::MaybePromise, CaughtError, Ctx, Exception, FromJs, Object, Value};
+
+use crate::abort::{abort_signal, named_error, AbortSignal};
+use crate::run::exit_requested;
+use crate::timers::delay;
+use crate::util::sleep_lag;
+
+type TaskFuture<'js> = Pin<Box<dyn Future<Output = rquickjs::Result<Value<'js>>> + 'js>>;
+
+/// Register `withTimeout(task, ms)` and `allSettledLimited(tasks, n)`
+///
+/// Deadlines and concurrency limits are enforced by the host - timed out
+/// tasks are cancelled through an `AbortSignal` and limited tasks are only
+/// started once a slot is free
+pub fn register_combinators(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    ctx.globals().set("withTimeout", js_with_timeout)?;
+    ctx.globals()
+        .set("allSettledLimited", js_all_settled_limited)?;
+    Ok(())
+}
+
+/// Run task - either a promise/value or a function returning one (called
+/// when the task starts)
+fn start_task<'js>(ctx: &Ctx<'js>, task: Value<'js>) -> TaskFuture<'js> {
+    let task = match task.as_function() {
+        Some(f) => f.call::<_, MaybePromise>(()),
+        None => MaybePromise::from_js(ctx, task),
+    };
+    Box::pin(async move { task?.into_future::<Value>().await })
+}
+
+/// withTimeout(task, ms) - settles with the task or rejects with
+/// `TimeoutError` once `ms` has elapsed
+///
+/// Function tasks are called with an `AbortSignal` which is aborted (with
+/// the `TimeoutError` as reason) on timeout - pass it on to host functions
+/// accepting a signal or check it so the work actually stops. A task given
+/// as a promise has already started and can't be cancelled, its result is
+/// just ignored.
+#[rquickjs::function]
+async fn with_timeout<'js>(
+    ctx: Ctx<'js>,
+    task: Value<'js>,
+    ms: f64,
+) -> rquickjs::Result<Value<'js>> {
+    let signal = AbortSignal::instance(&ctx)?;
+    let task = match task.as_function() {
+        Some(f) => f.call::<_, MaybePromise>((signal.clone(),)),
+        None => MaybePromise::from_js(&ctx, task),
+    };
+    let mut task = pin!(async move { task?.into_future::<Value>().await });
+    let mut deadline = pin!(sleep_lag(delay(Some(ms))));
+    let result = poll_fn(|cx| match task.as_mut().poll(cx) {
+        Poll::Ready(result) => Poll::Ready(Some(result)),
+        Poll::Pending => deadline.as_mut().poll(cx).map(|_| None),
+    })
+    .await;
+    match result {
+        Some(result) => result,
+        None => {
+            let reason = named_error(&ctx, "TimeoutError", &format!("Timed out after {ms}ms"))?;
+            abort_signal(&ctx, &signal, Some(reason.clone()))?;
+            Err(ctx.throw(reason))
+        }
+    }
+}
+
+/// allSettledLimited(tasks, concurrency) - as `Promise.allSettled` with at
+/// most `concurrency` tasks pending at once
+///
+/// Function tasks are called when they start so later tasks don't run
+/// until a slot is free
+#[rquickjs::function]
+async fn all_settled_limited<'js>(
+    ctx: Ctx<'js>,
+    tasks: Vec<Value<'js>>,
+    concurrency: f64,
+) -> rquickjs::Result<Vec<Object<'js>>> {
+    if concurrency.is_nan() || concurrency < 1.0 {
+        return Err(Exception::throw_range(
+            &ctx,
+            "allSettledLimited: concurrency must be >= 1",
+        ));
+    }
+    let limit = concurrency as usize;
+    let mut results = vec![None; tasks.len()];
+    let mut queue = tasks.into_iter().enumerate();
+    let mut running: Vec<(usize, TaskFuture<'js>)> = Vec::with_capacity(limit);
+    loop {
+        while running.len() < limit {
+            match queue.next() {
+                Some((i, task)) => running.push((i, start_task(&ctx, task))),
+                None => break,
+            }
+        }
+        if running.is_empty() {
+            break;
+        }
+        let (i, result) = poll_fn(|cx| {
+            for (i, task) in running.iter_mut() {
+                if let Poll::Ready(result) = task.as_mut().poll(cx) {
+                    return Poll::Ready((*i, result));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+        running.retain(|(n, _)| *n != i);
+        if result.is_err() && exit_requested(&ctx) {
+            return result.map(|_| Vec::new());
+        }
+        // Take rejection value before starting the next task
+        results[i] = Some(settled(&ctx, result)?);
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// `{status, value}`/`{status, reason}` result object
+fn settled<'js>(
+    ctx: &Ctx<'js>,
+    result: rquickjs::Result<Value<'js>>,
+) -> rquickjs::Result<Object<'js>> {
+    let obj = Object::new(ctx.clone())?;
+    match result {
+        Ok(value) => {
+            obj.set("status", "fulfilled")?;
+            obj.set("value", value)?;
+        }
+        Err(e) => {
+            let reason = match CaughtError::from_error(ctx, e) {
+                CaughtError::Exception(ex) => ex.into_value(),
+                CaughtError::Value(v) => v,
+                CaughtError::Error(e) => return Err(e),
+            };
+            obj.set("status", "rejected")?;
+            obj.set("reason", reason)?;
+        }
+    }
+    Ok(obj)
+}
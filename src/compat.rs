@@ -0,0 +1,19 @@
+/// Monotonic clock (`std::time::Instant` panics on wasm32)
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
+
+/// Sleep for `d` ([`DefaultExecutor`] timer or browser `setTimeout` on wasm32)
+///
+/// [`DefaultExecutor`]: crate::executor::DefaultExecutor
+#[cfg(feature = "async")]
+pub async fn sleep(d: std::time::Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use crate::executor::{DefaultExecutor, Executor};
+        DefaultExecutor::sleep(d).await;
+    }
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(d).await;
+}
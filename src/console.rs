@@ -0,0 +1,371 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use rquickjs::{
+    function::{Opt, Rest},
+    Coerced, Ctx, JsLifetime, Object, Value,
+};
+
+use crate::compat::Instant;
+use crate::convert::{inspect, stderr_color, stdout_color, DEFAULT_INSPECT_DEPTH};
+#[cfg(feature = "text")]
+use crate::text::display_width;
+
+/// Width of a table cell (chars without the `text` feature)
+#[cfg(not(feature = "text"))]
+fn display_width(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Spaces added per `console.group` level
+const GROUP_INDENT: usize = 2;
+
+/// Current `console.group` indent (context userdata)
+#[derive(Default, JsLifetime)]
+struct GroupIndent(Cell<usize>);
+
+/// `console.time` start times by label (context userdata)
+#[derive(Default, JsLifetime)]
+struct Timers(RefCell<HashMap<String, Instant>>);
+
+/// `console.count` counters by label (context userdata)
+#[derive(Default, JsLifetime)]
+struct Counters(RefCell<HashMap<String, u64>>);
+
+/// Label used when none is given
+const DEFAULT_LABEL: &str = "default";
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Register `console` global (log/info/debug to stdout, error/warn to
+/// stderr, table, assert, group/groupEnd, time/timeEnd, count)
+pub fn register_console(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let _ = ctx.store_userdata(GroupIndent::default());
+    let _ = ctx.store_userdata(Timers::default());
+    let _ = ctx.store_userdata(Counters::default());
+    let console = Object::new(ctx.clone())?;
+    console.set("log", js_log)?;
+    console.set("info", js_log)?;
+    console.set("debug", js_log)?;
+    console.set("error", js_error)?;
+    console.set("warn", js_error)?;
+    console.set("table", js_table)?;
+    console.set("assert", js_assert)?;
+    console.set("group", js_group)?;
+    console.set("groupCollapsed", js_group)?;
+    console.set("groupEnd", js_group_end)?;
+    console.set("time", js_time)?;
+    console.set("timeLog", js_time_log)?;
+    console.set("timeEnd", js_time_end)?;
+    console.set("count", js_count)?;
+    console.set("countReset", js_count_reset)?;
+    ctx.globals().set("console", console)?;
+    Ok(())
+}
+
+/// Format args (strings are printed as-is, other values via [`inspect`])
+///
+/// Args are separated by a single space as in node/browsers - note
+/// `console.log` previously joined JSON encoded args with `", "`
+fn format_values<'js>(ctx: &Ctx<'js>, args: &[Value<'js>], color: bool) -> String {
+    args.iter()
+        .map(|a| match a.as_string() {
+            Some(s) => s.to_string().unwrap_or_else(|_| "<ERR>".to_string()),
+            None => inspect(ctx, a, DEFAULT_INSPECT_DEPTH, color),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn indent(ctx: &Ctx<'_>) -> usize {
+    ctx.userdata::<GroupIndent>()
+        .map(|i| i.0.get())
+        .unwrap_or_default()
+}
+
+/// Write output indented to the current group level
+fn write(ctx: &Ctx<'_>, stream: Stream, s: &str) {
+    let pad = " ".repeat(indent(ctx));
+    let out = s
+        .lines()
+        .map(|line| format!("{pad}{line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    match stream {
+        Stream::Stdout => println!("{out}"),
+        Stream::Stderr => eprintln!("{out}"),
+    }
+}
+
+/// console.log/info/debug
+#[rquickjs::function]
+fn log<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> rquickjs::Result<()> {
+    write(
+        &ctx,
+        Stream::Stdout,
+        &format_values(&ctx, &args, stdout_color()),
+    );
+    Ok(())
+}
+
+/// console.error/warn
+#[rquickjs::function]
+fn error<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> rquickjs::Result<()> {
+    write(
+        &ctx,
+        Stream::Stderr,
+        &format_values(&ctx, &args, stderr_color()),
+    );
+    Ok(())
+}
+
+/// console.assert (prints to stderr if `cond` is falsy)
+#[rquickjs::function]
+fn assert<'js>(
+    ctx: Ctx<'js>,
+    cond: Opt<Coerced<bool>>,
+    args: Rest<Value<'js>>,
+) -> rquickjs::Result<()> {
+    if !cond.0.is_some_and(|c| c.0) {
+        let message = match format_values(&ctx, &args, stderr_color()) {
+            s if s.is_empty() => "Assertion failed".to_string(),
+            s => format!("Assertion failed: {s}"),
+        };
+        write(&ctx, Stream::Stderr, &message);
+    }
+    Ok(())
+}
+
+/// console.group/groupCollapsed (prints optional label and indents
+/// subsequent output)
+#[rquickjs::function]
+fn group<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> rquickjs::Result<()> {
+    if !args.is_empty() {
+        write(
+            &ctx,
+            Stream::Stdout,
+            &format_values(&ctx, &args, stdout_color()),
+        );
+    }
+    if let Some(indent) = ctx.userdata::<GroupIndent>() {
+        indent.0.set(indent.0.get() + GROUP_INDENT);
+    }
+    Ok(())
+}
+
+/// console.groupEnd
+#[rquickjs::function]
+fn group_end(ctx: Ctx<'_>) -> rquickjs::Result<()> {
+    if let Some(indent) = ctx.userdata::<GroupIndent>() {
+        indent.0.set(indent.0.get().saturating_sub(GROUP_INDENT));
+    }
+    Ok(())
+}
+
+/// console.time (warns if timer already exists)
+#[rquickjs::function]
+fn time(ctx: Ctx<'_>, label: Opt<String>) -> rquickjs::Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let Some(timers) = ctx.userdata::<Timers>() else {
+        return Ok(());
+    };
+    let mut timers = timers.0.borrow_mut();
+    if timers.contains_key(&label) {
+        write(
+            &ctx,
+            Stream::Stderr,
+            &format!("Timer '{label}' already exists"),
+        );
+    } else {
+        timers.insert(label, Instant::now());
+    }
+    Ok(())
+}
+
+/// console.timeLog (prints elapsed time and args without stopping timer)
+#[rquickjs::function]
+fn time_log<'js>(
+    ctx: Ctx<'js>,
+    label: Opt<String>,
+    args: Rest<Value<'js>>,
+) -> rquickjs::Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let start = ctx
+        .userdata::<Timers>()
+        .and_then(|timers| timers.0.borrow().get(&label).copied());
+    let mut message = elapsed(&label, start);
+    if start.is_some() && !args.is_empty() {
+        message = format!("{message} {}", format_values(&ctx, &args, stdout_color()));
+    }
+    let stream = if start.is_some() {
+        Stream::Stdout
+    } else {
+        Stream::Stderr
+    };
+    write(&ctx, stream, &message);
+    Ok(())
+}
+
+/// console.timeEnd (prints elapsed time and removes timer)
+#[rquickjs::function]
+fn time_end(ctx: Ctx<'_>, label: Opt<String>) -> rquickjs::Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let start = ctx
+        .userdata::<Timers>()
+        .and_then(|timers| timers.0.borrow_mut().remove(&label));
+    let stream = if start.is_some() {
+        Stream::Stdout
+    } else {
+        Stream::Stderr
+    };
+    write(&ctx, stream, &elapsed(&label, start));
+    Ok(())
+}
+
+/// `label: 1.234ms` (or missing timer warning)
+fn elapsed(label: &str, start: Option<Instant>) -> String {
+    match start {
+        Some(start) => format!("{label}: {:.3}ms", start.elapsed().as_secs_f64() * 1000.0),
+        None => format!("Timer '{label}' does not exist"),
+    }
+}
+
+/// console.count (prints number of calls with label)
+#[rquickjs::function]
+fn count(ctx: Ctx<'_>, label: Opt<String>) -> rquickjs::Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let n = ctx.userdata::<Counters>().map(|counters| {
+        let mut counters = counters.0.borrow_mut();
+        let n = counters.entry(label.clone()).or_default();
+        *n += 1;
+        *n
+    });
+    if let Some(n) = n {
+        write(&ctx, Stream::Stdout, &format!("{label}: {n}"));
+    }
+    Ok(())
+}
+
+/// console.countReset
+#[rquickjs::function]
+fn count_reset(ctx: Ctx<'_>, label: Opt<String>) -> rquickjs::Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let removed = ctx
+        .userdata::<Counters>()
+        .and_then(|counters| counters.0.borrow_mut().remove(&label));
+    if removed.is_none() {
+        write(
+            &ctx,
+            Stream::Stderr,
+            &format!("Count for '{label}' does not exist"),
+        );
+    }
+    Ok(())
+}
+
+/// console.table (array/object rows rendered as aligned columns - other
+/// values are logged)
+#[rquickjs::function]
+fn table<'js>(ctx: Ctx<'js>, data: Value<'js>, columns: Opt<Vec<String>>) -> rquickjs::Result<()> {
+    match data.as_object() {
+        Some(obj) if !data.is_function() => {
+            let table = render_table(&ctx, obj, columns.0)?;
+            write(&ctx, Stream::Stdout, &table);
+        }
+        _ => write(
+            &ctx,
+            Stream::Stdout,
+            &format_values(&ctx, std::slice::from_ref(&data), stdout_color()),
+        ),
+    }
+    Ok(())
+}
+
+/// Render rows as box table with `(index)` column, a column per row key
+/// (or `columns` if given) and `Values` for primitive rows
+fn render_table<'js>(
+    ctx: &Ctx<'js>,
+    obj: &Object<'js>,
+    columns: Option<Vec<String>>,
+) -> rquickjs::Result<String> {
+    let cell = |v: &Value<'js>| inspect(ctx, v, 0, false);
+    let mut keys = columns.clone().unwrap_or_default();
+    let mut values = false;
+    let mut rows = Vec::new();
+    for prop in obj.props::<String, Value>() {
+        let (index, row) = prop?;
+        let mut cells = Vec::new();
+        let mut value = None;
+        match row.as_object() {
+            Some(row) if !row.is_function() => {
+                for prop in row.props::<String, Value>() {
+                    let (k, v) = prop?;
+                    if columns.is_none() && !keys.contains(&k) {
+                        keys.push(k.clone());
+                    }
+                    cells.push((k, cell(&v)));
+                }
+            }
+            _ => {
+                values = true;
+                value = Some(cell(&row));
+            }
+        }
+        rows.push((index, cells, value));
+    }
+    let mut header = vec!["(index)".to_string()];
+    header.extend(keys.iter().cloned());
+    if values {
+        header.push("Values".into());
+    }
+    let body = rows
+        .into_iter()
+        .map(|(index, cells, value)| {
+            let mut line = vec![index];
+            for k in &keys {
+                line.push(
+                    cells
+                        .iter()
+                        .find(|(c, _)| c == k)
+                        .map(|(_, v)| v.clone())
+                        .unwrap_or_default(),
+                );
+            }
+            if values {
+                line.push(value.unwrap_or_default());
+            }
+            line
+        })
+        .collect::<Vec<_>>();
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            body.iter()
+                .map(|line| display_width(&line[i]))
+                .chain([display_width(h)])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let rule = |left: &str, mid: &str, right: &str| {
+        let cols = widths.iter().map(|w| "─".repeat(w + 2)).collect::<Vec<_>>();
+        format!("{left}{}{right}", cols.join(mid))
+    };
+    let line = |cells: &[String]| {
+        let cols = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!(" {c}{} ", " ".repeat(w - display_width(c))))
+            .collect::<Vec<_>>();
+        format!("│{}│", cols.join("│"))
+    };
+    let mut out = vec![rule("┌", "┬", "┐"), line(&header), rule("├", "┼", "┤")];
+    out.extend(body.iter().map(|cells| line(cells)));
+    out.push(rule("└", "┴", "┘"));
+    Ok(out.join("\n"))
+}
@@ -0,0 +1,472 @@
+use rquickjs::{
+    atom::PredefinedAtom, function::This, promise::PromiseState, ArrayBuffer, Coerced, Ctx, FromJs,
+    Function, IntoJs, Object, Type, Value,
+};
+use std::io::IsTerminal;
+
+/// Convert Value to JSON String
+pub fn value_to_json<'js>(ctx: Ctx<'js>, v: Value<'js>) -> anyhow::Result<String> {
+    if v.is_undefined() {
+        Ok("null".into())
+    } else {
+        ctx.json_stringify(v)?
+            .and_then(|s| s.as_string().map(|s| s.to_string().ok()).flatten())
+            .ok_or(anyhow::anyhow!("JSON Error"))
+    }
+}
+
+/// Convert fn args to JSON array String
+pub fn args_to_json<'js>(ctx: Ctx<'js>, args: Vec<Value<'js>>) -> anyhow::Result<String> {
+    let array = args.into_js(&ctx)?;
+    value_to_json(ctx, array)
+}
+
+/// Default nesting depth for [`inspect`]
+pub const DEFAULT_INSPECT_DEPTH: usize = 2;
+
+/// Max array/collection items shown by [`inspect`]
+const INSPECT_MAX_ITEMS: usize = 100;
+
+const STYLE_NUMBER: &str = "\x1b[33m";
+const STYLE_STRING: &str = "\x1b[32m";
+const STYLE_SPECIAL: &str = "\x1b[36m";
+const STYLE_NULL: &str = "\x1b[1m";
+const STYLE_UNDEFINED: &str = "\x1b[90m";
+const STYLE_RESET: &str = "\x1b[0m";
+
+/// Format value for display (similar to node `util.inspect`)
+///
+/// Unlike `JSON.stringify` handles functions, symbols, Map/Set, class
+/// instances and circular references - objects nested deeper than `depth`
+/// are abbreviated (`[Object]`) and `color` adds ANSI colours
+pub fn inspect<'js>(ctx: &Ctx<'js>, v: &Value<'js>, depth: usize, color: bool) -> String {
+    Inspector {
+        ctx,
+        depth,
+        color,
+        seen: Vec::new(),
+    }
+    .format(v, 0)
+}
+
+/// Check if stdout should be coloured
+pub fn stdout_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Check if stderr should be coloured
+pub fn stderr_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+struct Inspector<'a, 'js> {
+    ctx: &'a Ctx<'js>,
+    depth: usize,
+    color: bool,
+    /// Objects currently being formatted (cycle detection)
+    seen: Vec<Object<'js>>,
+}
+
+impl<'js> Inspector<'_, 'js> {
+    fn style(&self, style: &str, s: &str) -> String {
+        if self.color {
+            format!("{style}{s}{STYLE_RESET}")
+        } else {
+            s.to_string()
+        }
+    }
+
+    /// JS `String(v)`
+    fn coerce(&self, v: &Value<'js>) -> String {
+        Coerced::<String>::from_js(self.ctx, v.clone())
+            .map(|s| s.0)
+            .unwrap_or_else(|_| "<ERR>".to_string())
+    }
+
+    fn format(&mut self, v: &Value<'js>, level: usize) -> String {
+        match v.type_of() {
+            Type::Uninitialized | Type::Undefined => self.style(STYLE_UNDEFINED, "undefined"),
+            Type::Null => self.style(STYLE_NULL, "null"),
+            Type::Bool | Type::Int | Type::Float => self.style(STYLE_NUMBER, &self.coerce(v)),
+            Type::BigInt => self.style(STYLE_NUMBER, &format!("{}n", self.coerce(v))),
+            Type::String => self.style(STYLE_STRING, &quote(&self.coerce(v))),
+            Type::Symbol => {
+                let desc = v
+                    .as_symbol()
+                    .and_then(|s| s.description().ok())
+                    .filter(|d| !d.is_undefined())
+                    .map(|d| self.coerce(&d))
+                    .unwrap_or_default();
+                self.style(STYLE_STRING, &format!("Symbol({desc})"))
+            }
+            Type::Function | Type::Constructor => self.function(v),
+            Type::Exception => self.error(v),
+            Type::Promise => self.promise(v, level),
+            Type::Array | Type::Object | Type::Proxy => self.object(v, level),
+            Type::Module | Type::Unknown => {
+                self.style(STYLE_SPECIAL, &format!("[{}]", v.type_name()))
+            }
+        }
+    }
+
+    fn function(&self, v: &Value<'js>) -> String {
+        let Some(f) = v.as_object() else {
+            return "[Function]".into();
+        };
+        let name = f
+            .get::<_, Option<String>>("name")
+            .ok()
+            .flatten()
+            .filter(|n| !n.is_empty());
+        let is_class = f
+            .get::<_, Function>(PredefinedAtom::ToString)
+            .and_then(|to_string| to_string.call::<_, String>((This(f.clone()),)))
+            .is_ok_and(|src| src.starts_with("class"));
+        let s = match (is_class, name) {
+            (true, Some(name)) => format!("[class {name}]"),
+            (true, None) => "[class (anonymous)]".into(),
+            (false, Some(name)) => format!("[Function: {name}]"),
+            (false, None) => "[Function (anonymous)]".into(),
+        };
+        self.style(STYLE_SPECIAL, &s)
+    }
+
+    fn error(&self, v: &Value<'js>) -> String {
+        let Some(e) = v.as_exception() else {
+            return self.coerce(v);
+        };
+        let name = e
+            .get::<_, Option<String>>("name")
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "Error".into());
+        let mut s = format!("{name}: {}", e.message().unwrap_or_default());
+        if let Some(stack) = e.stack().filter(|s| !s.is_empty()) {
+            s.push('\n');
+            s.push_str(stack.trim_end());
+        }
+        s
+    }
+
+    fn promise(&mut self, v: &Value<'js>, level: usize) -> String {
+        let Some(p) = v.as_promise() else {
+            return "Promise {}".into();
+        };
+        let state = match p.state() {
+            PromiseState::Pending => self.style(STYLE_SPECIAL, "<pending>"),
+            PromiseState::Rejected => self.style(STYLE_SPECIAL, "<rejected>"),
+            PromiseState::Resolved => match p.result::<Value>() {
+                Some(Ok(v)) => self.format(&v, level + 1),
+                _ => "<ERR>".into(),
+            },
+        };
+        format!("Promise {{ {state} }}")
+    }
+
+    fn object(&mut self, v: &Value<'js>, level: usize) -> String {
+        let Some(obj) = v.as_object() else {
+            return self.coerce(v);
+        };
+        if self.seen.contains(obj) {
+            return self.style(STYLE_SPECIAL, "[Circular]");
+        }
+        let name = self.class_name(obj);
+        if level > self.depth {
+            let name = match (&name, v.is_array()) {
+                (_, true) => "Array",
+                (Some(name), _) => name.as_str(),
+                (None, _) => "Object",
+            };
+            return self.style(STYLE_SPECIAL, &format!("[{name}]"));
+        }
+        self.seen.push(obj.clone());
+        let s = self.object_body(v, obj, name, level);
+        self.seen.pop();
+        s
+    }
+
+    fn object_body(
+        &mut self,
+        v: &Value<'js>,
+        obj: &Object<'js>,
+        name: Option<String>,
+        level: usize,
+    ) -> String {
+        if let Some(array) = v.as_array() {
+            let items = array
+                .iter::<Value>()
+                .take(INSPECT_MAX_ITEMS)
+                .map(|item| match item {
+                    Ok(item) => self.format(&item, level + 1),
+                    Err(_) => "<ERR>".into(),
+                })
+                .collect::<Vec<_>>();
+            return braces("[", &items, array.len(), "]");
+        }
+        if let Some(buf) = ArrayBuffer::from_object(obj.clone()) {
+            let len = self.style(STYLE_NUMBER, &buf.len().to_string());
+            return format!("ArrayBuffer {{ byteLength: {len} }}");
+        }
+        let globals = self.ctx.globals();
+        for (collection, is_map) in [("Map", true), ("Set", false)] {
+            let Ok(constructor) = globals.get::<_, Value>(collection) else {
+                continue;
+            };
+            if !obj.is_instance_of(constructor) {
+                continue;
+            }
+            let entries = globals
+                .get::<_, Object>("Array")
+                .and_then(|a| a.get::<_, Function>("from"))
+                .and_then(|from| from.call::<_, Vec<Value>>((obj.clone(),)))
+                .unwrap_or_default();
+            let items = entries
+                .iter()
+                .take(INSPECT_MAX_ITEMS)
+                .map(|entry| match (is_map, entry.as_array()) {
+                    (true, Some(kv)) => {
+                        let k = kv.get::<Value>(0).map(|k| self.format(&k, level + 1));
+                        let v = kv.get::<Value>(1).map(|v| self.format(&v, level + 1));
+                        match (k, v) {
+                            (Ok(k), Ok(v)) => format!("{k} => {v}"),
+                            _ => "<ERR>".into(),
+                        }
+                    }
+                    _ => self.format(entry, level + 1),
+                })
+                .collect::<Vec<_>>();
+            let name = name.unwrap_or_else(|| collection.into());
+            let open = format!("{name}({}) {{", entries.len());
+            return braces(&open, &items, entries.len(), "}");
+        }
+        let describe = globals
+            .get::<_, Object>("Object")
+            .and_then(|o| o.get::<_, Function>("getOwnPropertyDescriptor"));
+        let items = obj
+            .keys::<String>()
+            .map(|k| match (k, &describe) {
+                (Ok(k), Ok(describe)) => {
+                    format!("{}: {}", key(&k), self.property(describe, obj, &k, level))
+                }
+                _ => "<ERR>".into(),
+            })
+            .collect::<Vec<_>>();
+        let open = match name {
+            Some(name) => format!("{name} {{"),
+            None => "{".into(),
+        };
+        braces(&open, &items, items.len(), "}")
+    }
+
+    /// Own property `k` of `obj` formatted from its descriptor, so getters
+    /// aren't run (accessors are shown as `[Getter]`/`[Setter]`)
+    fn property(
+        &mut self,
+        describe: &Function<'js>,
+        obj: &Object<'js>,
+        k: &str,
+        level: usize,
+    ) -> String {
+        let Ok(desc) = describe.call::<_, Object>((obj.clone(), k)) else {
+            return "<ERR>".into();
+        };
+        let accessor = |name: &str| {
+            desc.get::<_, Option<Function>>(name)
+                .ok()
+                .flatten()
+                .is_some()
+        };
+        match (accessor("get"), accessor("set")) {
+            (true, true) => self.style(STYLE_SPECIAL, "[Getter/Setter]"),
+            (true, false) => self.style(STYLE_SPECIAL, "[Getter]"),
+            (false, true) => self.style(STYLE_SPECIAL, "[Setter]"),
+            (false, false) => match desc.get::<_, Value>("value") {
+                Ok(v) => self.format(&v, level + 1),
+                Err(_) => "<ERR>".into(),
+            },
+        }
+    }
+
+    /// `Symbol.toStringTag` or constructor name (None for plain objects)
+    fn class_name(&self, obj: &Object<'js>) -> Option<String> {
+        obj.get::<_, Option<String>>(PredefinedAtom::SymbolToStringTag)
+            .ok()
+            .flatten()
+            .or_else(|| {
+                obj.get::<_, Option<Object>>("constructor")
+                    .ok()
+                    .flatten()?
+                    .get::<_, Option<String>>("name")
+                    .ok()
+                    .flatten()
+            })
+            .filter(|name| !name.is_empty() && name != "Object" && name != "Array")
+    }
+}
+
+/// Quote string (single quotes, escaping as JS string literal)
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Object key - quoted unless valid identifier
+fn key(k: &str) -> String {
+    let mut chars = k.chars();
+    let ident = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if ident {
+        k.to_string()
+    } else {
+        quote(k)
+    }
+}
+
+fn braces(open: &str, items: &[String], total: usize, close: &str) -> String {
+    let mut items = items.to_vec();
+    if total > items.len() {
+        items.push(format!("... {} more items", total - items.len()));
+    }
+    if items.is_empty() {
+        format!("{}{close}", open.trim_end())
+    } else {
+        format!("{open} {} {close}", items.join(", "))
+    }
+}
+
+/// Truncate output to at most `max` bytes (on a char boundary), returning
+/// the truncated output and number of bytes elided
+pub fn truncate_output(s: &str, max: usize) -> (&str, usize) {
+    if s.len() <= max {
+        return (s, 0);
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&s[..end], s.len() - end)
+}
+
+/// Limit output to `max` bytes with elision marker
+pub fn limit_output(s: &str, max: Option<usize>) -> String {
+    match max.map(|max| truncate_output(s, max)) {
+        Some((out, elided)) if elided > 0 => format!("{out}... <{elided} bytes elided>"),
+        _ => s.to_string(),
+    }
+}
+
+/// Convert JSON String to Value
+pub fn json_to_value<'js>(ctx: Ctx<'js>, json: &str) -> anyhow::Result<Value<'js>> {
+    match ctx.json_parse(json.as_bytes()) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            if let Ok(ex) = rquickjs::Exception::from_value(ctx.catch()) {
+                Err(anyhow::anyhow!(
+                    "JSON Error: {}\n{}",
+                    ex.message().unwrap_or("-".into()),
+                    ex.stack().unwrap_or("-".into())
+                ))
+            } else {
+                Err(anyhow::anyhow!("JSON Error: {e}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rquickjs::{Context, Runtime};
+
+    fn inspect_js(src: &str) -> String {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            let v = ctx.eval::<Value, _>(src).unwrap();
+            inspect(&ctx, &v, DEFAULT_INSPECT_DEPTH, false)
+        })
+    }
+
+    #[test]
+    fn inspect_values() {
+        assert_eq!(inspect_js("undefined"), "undefined");
+        assert_eq!(inspect_js("null"), "null");
+        assert_eq!(inspect_js("1.5"), "1.5");
+        assert_eq!(inspect_js("10n"), "10n");
+        assert_eq!(inspect_js("'a'"), "'a'");
+        assert_eq!(inspect_js("Symbol('s')"), "Symbol(s)");
+        assert_eq!(inspect_js("[]"), "[]");
+        assert_eq!(inspect_js("({})"), "{}");
+        assert_eq!(
+            inspect_js("({a: 1, 'b-c': 'x', d: [1, 2]})"),
+            "{ a: 1, 'b-c': 'x', d: [ 1, 2 ] }"
+        );
+        assert_eq!(inspect_js("new Map([[1, 'a']])"), "Map(1) { 1 => 'a' }");
+        assert_eq!(inspect_js("new Set([1])"), "Set(1) { 1 }");
+    }
+
+    #[test]
+    fn inspect_accessors_without_calling_them() {
+        assert_eq!(
+            inspect_js(
+                "({ a: 1, get b() { return 2 }, set c(v) {}, get d() { return 3 }, set d(v) {} })"
+            ),
+            "{ a: 1, b: [Getter], c: [Setter], d: [Getter/Setter] }"
+        );
+        // A getter that was run would show as <ERR>
+        assert_eq!(
+            inspect_js("({ get boom() { throw new Error('ran') } })"),
+            "{ boom: [Getter] }"
+        );
+    }
+
+    #[test]
+    fn inspect_functions() {
+        assert_eq!(inspect_js("(function foo() {})"), "[Function: foo]");
+        assert_eq!(inspect_js("(() => {})"), "[Function (anonymous)]");
+        assert_eq!(inspect_js("(class A {})"), "[class A]");
+    }
+
+    #[test]
+    fn inspect_circular_and_depth() {
+        assert_eq!(
+            inspect_js("const o = {}; o.self = o; o"),
+            "{ self: [Circular] }"
+        );
+        assert_eq!(
+            inspect_js("({a: {b: {c: {d: 1}}}})"),
+            "{ a: { b: { c: [Object] } } }"
+        );
+    }
+
+    #[test]
+    fn quote_escapes() {
+        assert_eq!(quote("it's"), "'it\\'s'");
+        assert_eq!(quote("a\\b\n\t"), "'a\\\\b\\n\\t'");
+        assert_eq!(quote("\u{1}"), "'\\u0001'");
+    }
+
+    #[test]
+    fn key_quoting() {
+        assert_eq!(key("abc"), "abc");
+        assert_eq!(key("_a$1"), "_a$1");
+        assert_eq!(key("a b"), "'a b'");
+        assert_eq!(key("1a"), "'1a'");
+        assert_eq!(key(""), "''");
+    }
+}
@@ -0,0 +1,150 @@
+use rquickjs::{qjs, ArrayBuffer, Coerced, Ctx, Exception, FromJs, Object, Value};
+use sha2::Digest;
+
+/// Max bytes filled by a single `getRandomValues` call (as WebCrypto)
+const MAX_RANDOM_BYTES: usize = 65536;
+
+/// Register `crypto` object with `getRandomValues(typedArray)`,
+/// `randomUUID()` and `subtle.digest(algorithm, data)` (SHA-256/384/512)
+pub fn register_crypto(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let crypto = Object::new(ctx.clone())?;
+    crypto.set("getRandomValues", js_get_random_values)?;
+    crypto.set("randomUUID", js_random_uuid)?;
+    let subtle = Object::new(ctx.clone())?;
+    subtle.set("digest", js_digest)?;
+    crypto.set("subtle", subtle)?;
+    ctx.globals().set("crypto", crypto)?;
+    Ok(())
+}
+
+/// crypto.getRandomValues(typedArray) - fills integer typed array in place
+/// and returns it
+#[rquickjs::function]
+fn get_random_values<'js>(ctx: Ctx<'js>, array: Value<'js>) -> rquickjs::Result<Value<'js>> {
+    if !is_integer_typed_array(&array) {
+        return Err(named_error(
+            &ctx,
+            "TypeMismatchError",
+            "getRandomValues: argument must be an integer typed array",
+        ));
+    }
+    with_view_bytes(&ctx, &array, |bytes| {
+        if bytes.len() > MAX_RANDOM_BYTES {
+            return Err(named_error(
+                &ctx,
+                "QuotaExceededError",
+                &format!("getRandomValues: length exceeds {MAX_RANDOM_BYTES} bytes"),
+            ));
+        }
+        rand::fill(bytes);
+        Ok(())
+    })??;
+    Ok(array)
+}
+
+/// crypto.randomUUID() - random (v4) UUID string
+#[rquickjs::function]
+fn random_uuid() -> String {
+    let mut b = [0u8; 16];
+    rand::fill(&mut b);
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let hex =
+        |r: std::ops::Range<usize>| b[r].iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex(0..4),
+        hex(4..6),
+        hex(6..8),
+        hex(8..10),
+        hex(10..16)
+    )
+}
+
+/// crypto.subtle.digest(algorithm, data) - algorithm is a name or
+/// `{name}`, data an ArrayBuffer/TypedArray/DataView (resolves to an
+/// ArrayBuffer)
+#[rquickjs::function]
+async fn digest<'js>(
+    ctx: Ctx<'js>,
+    algorithm: Value<'js>,
+    data: Value<'js>,
+) -> rquickjs::Result<ArrayBuffer<'js>> {
+    let name = match algorithm.as_object() {
+        Some(obj) => obj.get::<_, String>("name")?,
+        None => Coerced::<String>::from_js(&ctx, algorithm.clone())?.0,
+    };
+    let data = with_view_bytes(&ctx, &data, |bytes| bytes.to_vec())?;
+    let hash = match name.to_ascii_uppercase().as_str() {
+        "SHA-256" => sha2::Sha256::digest(&data).to_vec(),
+        "SHA-384" => sha2::Sha384::digest(&data).to_vec(),
+        "SHA-512" => sha2::Sha512::digest(&data).to_vec(),
+        _ => {
+            return Err(named_error(
+                &ctx,
+                "NotSupportedError",
+                &format!("digest: unsupported algorithm {name}"),
+            ))
+        }
+    };
+    ArrayBuffer::new(ctx, hash)
+}
+
+/// `v` is one of the integer TypedArray kinds accepted by WebCrypto (so
+/// not a Float16/32/64Array, DataView or array-like object)
+fn is_integer_typed_array(v: &Value<'_>) -> bool {
+    let Some(obj) = v.as_object() else {
+        return false;
+    };
+    obj.is_typed_array::<i8>()
+        || obj.is_typed_array::<u8>()
+        || obj.is_typed_array::<i16>()
+        || obj.is_typed_array::<u16>()
+        || obj.is_typed_array::<i32>()
+        || obj.is_typed_array::<u32>()
+        || obj.is_typed_array::<i64>()
+        || obj.is_typed_array::<u64>()
+        // Uint8ClampedArray has no `TypedArray<T>` equivalent
+        // SAFETY: obj is a live object value
+        || unsafe { qjs::JS_GetTypedArrayType(obj.as_raw()) }
+            == qjs::JSTypedArrayEnum_JS_TYPED_ARRAY_UINT8C as i32
+}
+
+/// Call `f` with the bytes viewed by an ArrayBuffer, TypedArray or DataView
+fn with_view_bytes<'js, R>(
+    ctx: &Ctx<'js>,
+    v: &Value<'js>,
+    f: impl FnOnce(&mut [u8]) -> R,
+) -> rquickjs::Result<R> {
+    let expected = || Exception::throw_type(ctx, "Expected ArrayBuffer, TypedArray or DataView");
+    let (buffer, range) = match ArrayBuffer::from_value(v.clone()) {
+        Some(buffer) => (buffer, None),
+        None => {
+            let obj = v.as_object().ok_or_else(expected)?;
+            let buffer = ArrayBuffer::from_value(obj.get("buffer")?).ok_or_else(expected)?;
+            let offset = obj.get::<_, usize>("byteOffset")?;
+            let len = obj.get::<_, usize>("byteLength")?;
+            (buffer, Some((offset, len)))
+        }
+    };
+    let raw = buffer
+        .as_raw()
+        .ok_or_else(|| Exception::throw_type(ctx, "ArrayBuffer is detached"))?;
+    let (offset, len) = range.unwrap_or((0, raw.len));
+    if offset.checked_add(len).is_none_or(|end| end > raw.len) {
+        return Err(Exception::throw_range(ctx, "View out of bounds"));
+    }
+    // SAFETY: range checked against the buffer, which is kept alive by
+    // `buffer` and not accessed by JS while `f` runs
+    let bytes = unsafe { std::slice::from_raw_parts_mut(raw.ptr.as_ptr().add(offset), len) };
+    Ok(f(bytes))
+}
+
+/// Error with `name` set (as DOMException names)
+fn named_error(ctx: &Ctx<'_>, name: &str, msg: &str) -> rquickjs::Error {
+    match Exception::from_message(ctx.clone(), msg).and_then(|ex| ex.set("name", name).map(|_| ex))
+    {
+        Ok(ex) => ctx.throw(ex.into_value()),
+        Err(e) => e,
+    }
+}
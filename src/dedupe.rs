@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use rquickjs::{
+    function::{Args, Opt, Rest, This},
+    Ctx, Exception, Function, Promise, Value,
+};
+
+use crate::convert::{args_to_json, value_to_json};
+use crate::run::exit_requested;
+
+/// Pending promises by call key (shared by all calls to one wrapper)
+///
+/// Only the wrapper holds the map strongly - settle callbacks hold a weak
+/// reference so a promise that never settles doesn't keep itself alive
+/// through its own callback. The map isn't traced by the GC, so a pending
+/// promise whose callbacks reference the wrapper is only freed once it
+/// settles (or the runtime is dropped).
+type Inflight<'js> = Rc<RefCell<HashMap<String, Promise<'js>>>>;
+
+/// Register `dedupe(fn, keyFn?)` global
+pub fn register_dedupe(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    ctx.globals().set("dedupe", js_dedupe)?;
+    Ok(())
+}
+
+/// dedupe(fn, keyFn?) - returns a wrapper where concurrent calls with the
+/// same key share one pending promise (so `fn` runs once per key at a time)
+///
+/// The key is `keyFn(...args)` or the JSON encoded args - entries are
+/// removed once the promise settles, so results are not cached
+#[rquickjs::function]
+fn dedupe<'js>(
+    ctx: Ctx<'js>,
+    f: Function<'js>,
+    key_fn: Opt<Function<'js>>,
+) -> rquickjs::Result<Function<'js>> {
+    let inflight = Inflight::default();
+    let key_fn = key_fn.0;
+    Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>, args: Rest<Value<'js>>| -> rquickjs::Result<Promise<'js>> {
+            let key = call_key(&ctx, key_fn.as_ref(), &args)?;
+            if let Some(promise) = inflight.borrow().get(&key) {
+                return Ok(promise.clone());
+            }
+            // Settle via our own promise so sync results/throws are shared too
+            let (promise, resolve, reject) = Promise::new(&ctx)?;
+            match f.call_arg::<Value>(to_args(&ctx, &args)?) {
+                Ok(v) => resolve.call::<_, ()>((v,))?,
+                Err(e @ rquickjs::Error::Exception) if exit_requested(&ctx) => return Err(e),
+                Err(rquickjs::Error::Exception) => reject.call::<_, ()>((ctx.catch(),))?,
+                Err(e) => return Err(e),
+            }
+            let settled = {
+                let inflight = Rc::downgrade(&inflight);
+                let key = key.clone();
+                Function::new(ctx.clone(), move |_: Rest<Value<'js>>| {
+                    if let Some(inflight) = Weak::upgrade(&inflight) {
+                        inflight.borrow_mut().remove(&key);
+                    }
+                })?
+            };
+            promise
+                .then()?
+                .call::<_, Value>((This(promise.clone()), settled.clone(), settled))?;
+            inflight.borrow_mut().insert(key, promise.clone());
+            Ok(promise)
+        },
+    )
+}
+
+/// Call key from `key_fn` (strings used as-is, other values as JSON) or args
+fn call_key<'js>(
+    ctx: &Ctx<'js>,
+    key_fn: Option<&Function<'js>>,
+    args: &[Value<'js>],
+) -> rquickjs::Result<String> {
+    let key = match key_fn {
+        Some(key_fn) => {
+            let key = key_fn.call_arg::<Value>(to_args(ctx, args)?)?;
+            match key.as_string() {
+                Some(s) => return s.to_string(),
+                None => value_to_json(ctx.clone(), key),
+            }
+        }
+        None => args_to_json(ctx.clone(), args.to_vec()),
+    };
+    key.map_err(|e| Exception::throw_type(ctx, &format!("dedupe key: {e}")))
+}
+
+fn to_args<'js>(ctx: &Ctx<'js>, args: &[Value<'js>]) -> rquickjs::Result<Args<'js>> {
+    let mut call_args = Args::new(ctx.clone(), args.len());
+    call_args.push_args(args.iter())?;
+    Ok(call_args)
+}
@@ -0,0 +1,211 @@
+use hickory_resolver::TokioAsyncResolver;
+use rquickjs::{
+    function::{Async, Func},
+    Ctx, Exception, IntoJs, Object, Value,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+/// DNS record returned to JS
+#[derive(Debug, Clone)]
+pub enum DnsRecord {
+    Addr(String),
+    Txt(String),
+    Mx {
+        exchange: String,
+        priority: u16,
+    },
+    Srv {
+        name: String,
+        port: u16,
+        priority: u16,
+        weight: u16,
+    },
+}
+
+impl<'js> IntoJs<'js> for DnsRecord {
+    fn into_js(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        match self {
+            DnsRecord::Addr(s) | DnsRecord::Txt(s) => s.into_js(ctx),
+            DnsRecord::Mx { exchange, priority } => {
+                let obj = Object::new(ctx.clone())?;
+                obj.set("exchange", exchange)?;
+                obj.set("priority", priority)?;
+                Ok(obj.into_value())
+            }
+            DnsRecord::Srv {
+                name,
+                port,
+                priority,
+                weight,
+            } => {
+                let obj = Object::new(ctx.clone())?;
+                obj.set("name", name)?;
+                obj.set("port", port)?;
+                obj.set("priority", priority)?;
+                obj.set("weight", weight)?;
+                Ok(obj.into_value())
+            }
+        }
+    }
+}
+
+/// DNS query type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DnsQuery {
+    Lookup,
+    Txt,
+    Mx,
+    Srv,
+}
+
+/// DNS answers cached until their TTL expires
+///
+/// `dns.*` functions use [`DnsCache::global`] so answers are shared by
+/// every context in the process
+#[derive(Default)]
+pub struct DnsCache {
+    entries: Mutex<HashMap<(DnsQuery, String), (Instant, Vec<DnsRecord>)>>,
+}
+
+impl DnsCache {
+    /// Process wide cache
+    pub fn global() -> &'static DnsCache {
+        static GLOBAL: OnceLock<DnsCache> = OnceLock::new();
+        GLOBAL.get_or_init(DnsCache::default)
+    }
+
+    fn get(&self, query: DnsQuery, host: &str) -> Option<Vec<DnsRecord>> {
+        let entries = self.entries.lock().ok()?;
+        match entries.get(&(query, host.to_string())) {
+            Some((valid_until, records)) if *valid_until > Instant::now() => Some(records.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, query: DnsQuery, host: &str, valid_until: Instant, records: &[DnsRecord]) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, (t, _)| *t > Instant::now());
+            entries.insert((query, host.to_string()), (valid_until, records.to_vec()));
+        }
+    }
+
+    /// Resolve host with `resolver`, using cached answer if still valid
+    pub async fn resolve(
+        &self,
+        resolver: &TokioAsyncResolver,
+        query: DnsQuery,
+        host: &str,
+    ) -> anyhow::Result<Vec<DnsRecord>> {
+        if let Some(records) = self.get(query, host) {
+            return Ok(records);
+        }
+        let (valid_until, records) = match query {
+            DnsQuery::Lookup => {
+                let r = resolver.lookup_ip(host).await?;
+                let records = r.iter().map(|ip| DnsRecord::Addr(ip.to_string()));
+                (r.valid_until(), records.collect::<Vec<_>>())
+            }
+            DnsQuery::Txt => {
+                let r = resolver.txt_lookup(host).await?;
+                let records = r.iter().map(|txt| {
+                    DnsRecord::Txt(
+                        txt.iter()
+                            .map(|s| String::from_utf8_lossy(s).to_string())
+                            .collect::<String>(),
+                    )
+                });
+                (r.valid_until(), records.collect::<Vec<_>>())
+            }
+            DnsQuery::Mx => {
+                let r = resolver.mx_lookup(host).await?;
+                let records = r.iter().map(|mx| DnsRecord::Mx {
+                    exchange: mx.exchange().to_utf8(),
+                    priority: mx.preference(),
+                });
+                (r.valid_until(), records.collect::<Vec<_>>())
+            }
+            DnsQuery::Srv => {
+                let r = resolver.srv_lookup(host).await?;
+                let records = r.iter().map(|srv| DnsRecord::Srv {
+                    name: srv.target().to_utf8(),
+                    port: srv.port(),
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                });
+                (r.valid_until(), records.collect::<Vec<_>>())
+            }
+        };
+        self.insert(query, host, valid_until, &records);
+        Ok(records)
+    }
+}
+
+/// Register `dns` object (lookup, resolveTxt, resolveMx, resolveSrv) using
+/// the system resolver configuration (/etc/resolv.conf)
+///
+/// Note there is no runtime permission flag for network access - `dns` is
+/// only available when built with the `dns` feature and enabled on the
+/// [`crate::env::JsEnvBuilder`] (calls can be vetted per context with
+/// [`crate::policy::install_policy_hook`])
+pub fn register_dns(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let resolver = Arc::new(TokioAsyncResolver::tokio_from_system_conf()?);
+    let dns = Object::new(ctx.clone())?;
+    for (name, query) in [
+        ("lookup", DnsQuery::Lookup),
+        ("resolveTxt", DnsQuery::Txt),
+        ("resolveMx", DnsQuery::Mx),
+        ("resolveSrv", DnsQuery::Srv),
+    ] {
+        let resolver = resolver.clone();
+        dns.set(
+            name,
+            Func::new(Async(move |ctx, host: String| {
+                let resolver = resolver.clone();
+                async move {
+                    let records = DnsCache::global().resolve(&resolver, query, &host).await;
+                    records.map_err(|e| {
+                        Exception::throw_message(&ctx, &format!("DNS Error: {host} [{e}]"))
+                    })
+                }
+            })),
+        )?;
+    }
+    ctx.globals().set("dns", dns)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_entries_expire() {
+        let cache = DnsCache::default();
+        let records = [DnsRecord::Addr("127.0.0.1".to_string())];
+        let now = Instant::now();
+        cache.insert(
+            DnsQuery::Lookup,
+            "a",
+            now + Duration::from_millis(20),
+            &records,
+        );
+        cache.insert(
+            DnsQuery::Lookup,
+            "b",
+            now + Duration::from_secs(60),
+            &records,
+        );
+        assert!(cache.get(DnsQuery::Lookup, "a").is_some());
+        // Cached per query type
+        assert!(cache.get(DnsQuery::Txt, "a").is_none());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(DnsQuery::Lookup, "a").is_none());
+        assert!(cache.get(DnsQuery::Lookup, "b").is_some());
+        // Expired entries are dropped on the next insert
+        cache.insert(DnsQuery::Mx, "c", now + Duration::from_secs(60), &[]);
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use rquickjs::AsyncRuntime;
+use rquickjs::{Context, Runtime};
+
+use crate::compat::Instant;
+use crate::convert::{json_to_value, value_to_json};
+use crate::error::{error_kind, ErrorKind};
+use crate::run::{resolve_blocking, run_script_blocking, ScriptDeadline};
+
+/// Runtime resource limits applied before any context is created
+///
+/// Allocations beyond the memory limit fail with an `out of memory`
+/// exception which is returned as [`ErrorKind::OutOfMemory`] (and can be
+/// caught by scripts) rather than aborting the process:
+///
+/// ```ignore
+/// let rt = RuntimeOptions::new()
+///     .memory_limit(64 * 1024 * 1024)
+///     .max_stack_size(512 * 1024)
+///     .build()
+///     .await?;
+/// ```
+///
+/// [`ErrorKind::OutOfMemory`]: crate::error::ErrorKind::OutOfMemory
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuntimeOptions {
+    memory_limit: Option<usize>,
+    max_stack_size: Option<usize>,
+    gc_threshold: Option<usize>,
+}
+
+impl RuntimeOptions {
+    /// QuickJS defaults (no memory limit, 256KB stack)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max bytes allocated by the runtime (0 = unlimited)
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Max stack size in bytes (0 = unlimited)
+    pub fn max_stack_size(mut self, bytes: usize) -> Self {
+        self.max_stack_size = Some(bytes);
+        self
+    }
+
+    /// Allocated bytes which trigger garbage collection
+    pub fn gc_threshold(mut self, bytes: usize) -> Self {
+        self.gc_threshold = Some(bytes);
+        self
+    }
+
+    /// Create runtime with limits applied
+    #[cfg(feature = "async")]
+    pub async fn build(&self) -> anyhow::Result<AsyncRuntime> {
+        let rt = AsyncRuntime::new()?;
+        self.apply(&rt).await;
+        Ok(rt)
+    }
+
+    /// Apply limits to existing runtime
+    #[cfg(feature = "async")]
+    pub async fn apply(&self, rt: &AsyncRuntime) {
+        if let Some(limit) = self.memory_limit {
+            rt.set_memory_limit(limit).await;
+        }
+        if let Some(limit) = self.max_stack_size {
+            rt.set_max_stack_size(limit).await;
+        }
+        if let Some(threshold) = self.gc_threshold {
+            rt.set_gc_threshold(threshold).await;
+        }
+    }
+
+    /// Apply limits to existing (sync) runtime
+    pub fn apply_blocking(&self, rt: &Runtime) {
+        if let Some(limit) = self.memory_limit {
+            rt.set_memory_limit(limit);
+        }
+        if let Some(limit) = self.max_stack_size {
+            rt.set_max_stack_size(limit);
+        }
+        if let Some(threshold) = self.gc_threshold {
+            rt.set_gc_threshold(threshold);
+        }
+    }
+}
+
+/// Options for [`eval_to_json`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EvalOptions {
+    limits: RuntimeOptions,
+    timeout: Option<Duration>,
+}
+
+impl EvalOptions {
+    /// No limits or timeout
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runtime resource limits
+    pub fn limits(mut self, limits: RuntimeOptions) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Abort evaluation (including resolving a returned promise) after
+    /// `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Evaluate `source` in a fresh runtime/context and return the result
+///
+/// `inputs` (JSON object or null) are set as globals - a returned promise
+/// is resolved by running pending jobs (no host futures/timers are
+/// available):
+///
+/// ```ignore
+/// let out = eval_to_json("a + b", json!({ "a": 1, "b": 2 }), EvalOptions::new())?;
+/// ```
+pub fn eval_to_json(
+    source: &str,
+    inputs: serde_json::Value,
+    opts: EvalOptions,
+) -> anyhow::Result<serde_json::Value> {
+    let rt = Runtime::new()?;
+    opts.limits.apply_blocking(&rt);
+    if let Some(timeout) = opts.timeout {
+        let deadline = ScriptDeadline::default();
+        deadline.set(Some(Instant::now() + timeout));
+        rt.set_interrupt_handler(Some(deadline.interrupt_handler()));
+    }
+    let ctx = Context::full(&rt)?;
+    let json = ctx.with(|ctx| {
+        match inputs {
+            serde_json::Value::Object(inputs) => {
+                for (name, v) in inputs {
+                    let v = json_to_value(ctx.clone(), &v.to_string())?;
+                    ctx.globals().set(name, v)?;
+                }
+            }
+            serde_json::Value::Null => {}
+            _ => return Err(anyhow::anyhow!("Inputs must be a JSON object")),
+        }
+        let v = run_script_blocking(ctx.clone(), source.to_string())?;
+        let v = resolve_blocking(&ctx, v, "await")?;
+        value_to_json(ctx, v)
+    });
+    let json = json.map_err(|e| match (error_kind(&e), opts.timeout) {
+        (ErrorKind::Timeout, Some(timeout)) => {
+            e.context(format!("Script timed out after {timeout:?}"))
+        }
+        _ => e,
+    })?;
+    Ok(serde_json::from_str(&json)?)
+}
@@ -0,0 +1,479 @@
+use rquickjs::{class::JsClass, Ctx};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::abort::register_abort;
+use crate::breaker::register_breaker;
+use crate::cache::{register_cache, Cache};
+use crate::class::{define_class, define_serde_class, DefineFn};
+use crate::combinators::register_combinators;
+use crate::dedupe::register_dedupe;
+use crate::events::register_events;
+use crate::fs::{register_fs, FsAccess};
+use crate::host::{register_host, register_semver};
+use crate::json::register_json;
+use crate::kv::{register_kv, KvBackend};
+use crate::lazy::register_lazy_module;
+use crate::lock::{register_lock, FileLockBackend};
+use crate::metrics::register_metrics;
+use crate::native::NativeModuleSet;
+#[cfg(all(feature = "plugin", not(target_arch = "wasm32")))]
+use crate::plugin::{register_plugin, Plugin};
+use crate::query::register_query;
+use crate::sql::register_sql;
+#[cfg(feature = "text")]
+use crate::text::register_text;
+use crate::util::register_fns;
+use crate::web::register_web;
+
+/// Configure which host globals/modules are installed into a context
+///
+/// Presets bundle coherent sets of modules - optional modules can only be
+/// enabled when the corresponding cargo feature is enabled:
+///
+/// ```ignore
+/// async_with!(ctx => |ctx| {
+///     JsEnvBuilder::server().apply(ctx.clone()).await?;
+///     ...
+/// })
+/// ```
+#[derive(Clone, Default)]
+pub struct JsEnvBuilder {
+    fns: bool,
+    query: bool,
+    sql: bool,
+    dedupe: bool,
+    circuit_breaker: bool,
+    combinators: bool,
+    url: bool,
+    text: bool,
+    json: bool,
+    json_stdin: bool,
+    events: bool,
+    abort: bool,
+    crypto: bool,
+    lock_dir: Option<PathBuf>,
+    fs_roots: Vec<PathBuf>,
+    kv: Option<Arc<dyn KvBackend>>,
+    cache: Option<Arc<Cache>>,
+    dns: bool,
+    net: bool,
+    os: bool,
+    intl: bool,
+    process: bool,
+    process_env: Vec<String>,
+    lazy: bool,
+    modules: NativeModuleSet,
+    classes: Vec<DefineFn>,
+    #[cfg(all(feature = "plugin", not(target_arch = "wasm32")))]
+    plugins: Vec<Plugin>,
+}
+
+impl JsEnvBuilder {
+    /// Empty environment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Basic helpers only (print, console.log, setTimeout etc)
+    pub fn minimal() -> Self {
+        Self::new().fns(true)
+    }
+
+    /// Helpers, web style globals and local locks for scripts run from the
+    /// CLI
+    pub fn scripting() -> Self {
+        let builder = Self::minimal()
+            .query(true)
+            .sql(true)
+            .dedupe(true)
+            .circuit_breaker(true)
+            .combinators(true)
+            .url(true)
+            .json(true)
+            .events(true)
+            .abort(true);
+        #[cfg(feature = "text")]
+        let builder = builder.text(true);
+        #[cfg(feature = "crypto")]
+        let builder = builder.crypto(true);
+        builder.lock_dir(std::env::temp_dir().join("rquickjs-locks"))
+    }
+
+    /// Scripting + network helpers (dns/net)
+    pub fn server() -> Self {
+        let builder = Self::scripting();
+        #[cfg(feature = "dns")]
+        let builder = builder.dns(true);
+        #[cfg(feature = "net")]
+        let builder = builder.net(true);
+        builder
+    }
+
+    /// Server + host info for monitoring/agent scripts
+    pub fn iot() -> Self {
+        let builder = Self::server();
+        #[cfg(feature = "os")]
+        let builder = builder.os(true);
+        builder
+    }
+
+    /// Register basic helpers (see [`register_fns`])
+    pub fn fns(mut self, enable: bool) -> Self {
+        self.fns = enable;
+        self
+    }
+
+    /// Register `query(obj, expr)` global
+    pub fn query(mut self, enable: bool) -> Self {
+        self.query = enable;
+        self
+    }
+
+    /// Register `sql` template tag and `SqlQuery` class
+    pub fn sql(mut self, enable: bool) -> Self {
+        self.sql = enable;
+        self
+    }
+
+    /// Register `dedupe(key, fn)` global
+    pub fn dedupe(mut self, enable: bool) -> Self {
+        self.dedupe = enable;
+        self
+    }
+
+    /// Register `circuitBreaker(fn, opts)` global
+    pub fn circuit_breaker(mut self, enable: bool) -> Self {
+        self.circuit_breaker = enable;
+        self
+    }
+
+    /// Register `withTimeout` and `allSettledLimited` globals
+    pub fn combinators(mut self, enable: bool) -> Self {
+        self.combinators = enable;
+        self
+    }
+
+    /// Register `URL` and `URLSearchParams` classes
+    pub fn url(mut self, enable: bool) -> Self {
+        self.url = enable;
+        self
+    }
+
+    /// Register `text` module
+    #[cfg(feature = "text")]
+    pub fn text(mut self, enable: bool) -> Self {
+        self.text = enable;
+        self
+    }
+
+    /// Register `json` module (`json.stream` reads files under the
+    /// [`Self::fs_root`] directories)
+    pub fn json(mut self, enable: bool) -> Self {
+        self.json = enable;
+        self
+    }
+
+    /// Allow `json.stream("-")` to read the host's stdin (off by default)
+    pub fn json_stdin(mut self, enable: bool) -> Self {
+        self.json_stdin = enable;
+        self
+    }
+
+    /// Register `EventTarget` and `CustomEvent` classes
+    pub fn events(mut self, enable: bool) -> Self {
+        self.events = enable;
+        self
+    }
+
+    /// Register `AbortController` and `AbortSignal` classes
+    pub fn abort(mut self, enable: bool) -> Self {
+        self.abort = enable;
+        self
+    }
+
+    /// Register `crypto` global
+    #[cfg(feature = "crypto")]
+    pub fn crypto(mut self, enable: bool) -> Self {
+        self.crypto = enable;
+        self
+    }
+
+    /// Register `lock` module using lock files in `dir`
+    pub fn lock_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.lock_dir = Some(dir.into());
+        self
+    }
+
+    /// Register `fs` module with read access to files under `dir` (may be
+    /// called more than once)
+    pub fn fs_root(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.fs_roots.push(dir.into());
+        self
+    }
+
+    /// Register `kv` module using `backend` (eg. [`crate::kv::FileKvBackend`])
+    pub fn kv(mut self, backend: Arc<dyn KvBackend>) -> Self {
+        self.kv = Some(backend);
+        self
+    }
+
+    /// Register `cache` module (share `cache` between builders to share
+    /// entries between contexts)
+    pub fn cache(mut self, cache: Arc<Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Register `dns` module
+    #[cfg(feature = "dns")]
+    pub fn dns(mut self, enable: bool) -> Self {
+        self.dns = enable;
+        self
+    }
+
+    /// Register `net` module
+    #[cfg(feature = "net")]
+    pub fn net(mut self, enable: bool) -> Self {
+        self.net = enable;
+        self
+    }
+
+    /// Register `os` module
+    #[cfg(feature = "os")]
+    pub fn os(mut self, enable: bool) -> Self {
+        self.os = enable;
+        self
+    }
+
+    /// Register `intl` module
+    #[cfg(feature = "intl")]
+    pub fn intl(mut self, enable: bool) -> Self {
+        self.intl = enable;
+        self
+    }
+
+    /// Register `process` global (off by default)
+    #[cfg(feature = "process")]
+    pub fn process(mut self, enable: bool) -> Self {
+        self.process = enable;
+        self
+    }
+
+    /// Expose environment variable `name` as `process.env[name]` (may be
+    /// called more than once - no variables are exposed by default)
+    #[cfg(feature = "process")]
+    pub fn process_env(mut self, name: impl Into<String>) -> Self {
+        self.process_env.push(name.into());
+        self
+    }
+
+    /// Install optional globals (everything except [`Self::fns`], plugins and
+    /// classes) on first access rather than eagerly, reducing context
+    /// startup cost
+    pub fn lazy(mut self, enable: bool) -> Self {
+        self.lazy = enable;
+        self
+    }
+
+    /// Native modules to evaluate in context
+    pub fn modules(mut self, modules: NativeModuleSet) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    /// Register native plugin global (see [`register_plugin`])
+    #[cfg(all(feature = "plugin", not(target_arch = "wasm32")))]
+    pub fn plugin(mut self, plugin: Plugin) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// Define class `C` (see [`define_class`])
+    pub fn class<C>(mut self) -> Self
+    where
+        C: for<'js> JsClass<'js>,
+    {
+        self.classes.push(|ctx| define_class::<C>(ctx).map(|_| ()));
+        self
+    }
+
+    /// Define class `C` with serde `toJSON` (see [`define_serde_class`])
+    pub fn serde_class<C>(mut self) -> Self
+    where
+        C: for<'js> JsClass<'js> + Serialize,
+    {
+        self.classes
+            .push(|ctx| define_serde_class::<C>(ctx).map(|_| ()));
+        self
+    }
+
+    /// Optional modules available in this build/configuration
+    pub fn features(&self) -> Vec<(&'static str, bool)> {
+        vec![
+            ("fns", self.fns),
+            ("query", self.query),
+            ("sql", self.sql),
+            ("dedupe", self.dedupe),
+            ("circuitBreaker", self.circuit_breaker),
+            ("combinators", self.combinators),
+            ("url", self.url),
+            ("text", self.text),
+            ("json", self.json),
+            ("events", self.events),
+            ("abort", self.abort),
+            ("crypto", self.crypto),
+            ("lock", self.lock_dir.is_some()),
+            ("fs", !self.fs_roots.is_empty()),
+            ("kv", self.kv.is_some()),
+            ("cache", self.cache.is_some()),
+            ("dns", self.dns),
+            ("net", self.net),
+            ("ping", self.net && cfg!(feature = "icmp")),
+            ("os", self.os),
+            ("intl", self.intl),
+            ("process", self.process),
+            (
+                "repl",
+                cfg!(feature = "repl_rustyline") || cfg!(feature = "repl_rustyline_async"),
+            ),
+        ]
+    }
+
+    /// Install configured globals/modules into context
+    pub async fn apply<'js>(&self, ctx: Ctx<'js>) -> anyhow::Result<()> {
+        register_host(&ctx, &self.features())?;
+        register_semver(&ctx)?;
+        register_metrics(&ctx)?;
+        if self.fns {
+            register_fns(&ctx)?;
+        }
+        if self.query {
+            self.install(&ctx, "query", register_query)?;
+        }
+        if self.sql {
+            self.install_all(&ctx, &["sql", "SqlQuery"], register_sql)?;
+        }
+        if self.dedupe {
+            self.install(&ctx, "dedupe", register_dedupe)?;
+        }
+        if self.circuit_breaker {
+            self.install(&ctx, "circuitBreaker", register_breaker)?;
+        }
+        if self.combinators {
+            self.install_all(
+                &ctx,
+                &["withTimeout", "allSettledLimited"],
+                register_combinators,
+            )?;
+        }
+        if self.url {
+            self.install_all(&ctx, &["URL", "URLSearchParams"], register_web)?;
+        }
+        #[cfg(feature = "text")]
+        if self.text {
+            self.install(&ctx, "text", register_text)?;
+        }
+        // Shared by `fs` and `json.stream`
+        let access = if self.fs_roots.is_empty() {
+            None
+        } else {
+            Some(Arc::new(FsAccess::new(&self.fs_roots)?))
+        };
+        if self.json {
+            let (access, stdin) = (access.clone(), self.json_stdin);
+            self.install(&ctx, "json", move |ctx| {
+                register_json(ctx, access.clone(), stdin)
+            })?;
+        }
+        if self.events {
+            self.install_all(&ctx, &["EventTarget", "CustomEvent"], register_events)?;
+        }
+        if self.abort {
+            self.install_all(&ctx, &["AbortController", "AbortSignal"], register_abort)?;
+        }
+        #[cfg(feature = "crypto")]
+        if self.crypto {
+            self.install(&ctx, "crypto", crate::crypto::register_crypto)?;
+        }
+        if let Some(dir) = &self.lock_dir {
+            let dir = dir.clone();
+            self.install(&ctx, "lock", move |ctx| {
+                register_lock(ctx, Arc::new(FileLockBackend::new(dir.clone())?))
+            })?;
+        }
+        if let Some(access) = access {
+            self.install(&ctx, "fs", move |ctx| register_fs(ctx, access.clone()))?;
+        }
+        if let Some(backend) = &self.kv {
+            let backend = backend.clone();
+            self.install(&ctx, "kv", move |ctx| register_kv(ctx, backend.clone()))?;
+        }
+        if let Some(cache) = &self.cache {
+            let cache = cache.clone();
+            self.install(&ctx, "cache", move |ctx| register_cache(ctx, cache.clone()))?;
+        }
+        #[cfg(feature = "dns")]
+        if self.dns {
+            self.install(&ctx, "dns", crate::dns::register_dns)?;
+        }
+        #[cfg(feature = "net")]
+        if self.net {
+            self.install(&ctx, "net", crate::net::register_net)?;
+        }
+        #[cfg(feature = "os")]
+        if self.os {
+            self.install(&ctx, "os", crate::os::register_os)?;
+        }
+        #[cfg(feature = "intl")]
+        if self.intl {
+            self.install(&ctx, "intl", crate::intl::register_intl)?;
+        }
+        #[cfg(feature = "process")]
+        if self.process {
+            let env = self.process_env.clone();
+            self.install(&ctx, "process", move |ctx| {
+                crate::util::register_process(ctx, &env)
+            })?;
+        }
+        #[cfg(all(feature = "plugin", not(target_arch = "wasm32")))]
+        for plugin in &self.plugins {
+            register_plugin(&ctx, plugin)?;
+        }
+        for define in &self.classes {
+            define(&ctx)?;
+        }
+        self.modules.evaluate(ctx.clone()).await?;
+        Ok(())
+    }
+
+    fn install<'js, F>(&self, ctx: &Ctx<'js>, name: &str, install: F) -> anyhow::Result<()>
+    where
+        F: Fn(&Ctx<'js>) -> anyhow::Result<()> + 'js,
+    {
+        if self.lazy {
+            register_lazy_module(ctx, name, install)
+        } else {
+            install(ctx)
+        }
+    }
+
+    /// As [`Self::install`] for modules defining several globals (each
+    /// global gets its own stub)
+    fn install_all<'js, F>(&self, ctx: &Ctx<'js>, names: &[&str], install: F) -> anyhow::Result<()>
+    where
+        F: Fn(&Ctx<'js>) -> anyhow::Result<()> + 'js,
+    {
+        if !self.lazy {
+            return install(ctx);
+        }
+        let install = Rc::new(install);
+        for name in names {
+            let install = install.clone();
+            register_lazy_module(ctx, name, move |ctx| install(ctx))?;
+        }
+        Ok(())
+    }
+}
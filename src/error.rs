@@ -0,0 +1,171 @@
+use rquickjs::{CaughtError, Exception};
+use serde::Serialize;
+
+use crate::source::source_snippet;
+
+/// Exception name given to calls denied by a policy hook (see
+/// `policy::install_policy_hook`) - mapped to [`ErrorKind::Permission`]
+pub const POLICY_DENIED_ERROR: &str = "PolicyDeniedError";
+
+/// Error category (determines CLI exit code)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Script failed to parse
+    Syntax,
+    /// Uncaught JS exception
+    Exception,
+    /// Script interrupted (timeout)
+    Timeout,
+    /// Call denied by policy hook
+    Permission,
+    /// Runtime memory limit exceeded
+    OutOfMemory,
+    /// Error in host (IO, config etc)
+    Host,
+}
+
+impl ErrorKind {
+    /// Process exit code (sysexits.h values)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Syntax => 65,
+            ErrorKind::Exception => 70,
+            ErrorKind::Timeout => 75,
+            ErrorKind::Permission => 77,
+            ErrorKind::OutOfMemory => 69,
+            ErrorKind::Host => 71,
+        }
+    }
+}
+
+/// Structured JS error
+#[derive(Debug, Clone, Serialize)]
+pub struct JsError {
+    pub kind: ErrorKind,
+    /// Exception name (eg. `TypeError`)
+    pub name: Option<String>,
+    pub message: String,
+    pub stack: Option<String>,
+    /// Offending source line with a caret under the column (see
+    /// [`source_snippet`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+impl JsError {
+    pub fn from_exception(ex: &Exception<'_>) -> Self {
+        let name = ex.get::<_, String>("name").ok();
+        let message = ex.message().unwrap_or_default();
+        let kind = match name.as_deref() {
+            Some("SyntaxError") => ErrorKind::Syntax,
+            Some(POLICY_DENIED_ERROR) => ErrorKind::Permission,
+            Some("InternalError") if message == "interrupted" => ErrorKind::Timeout,
+            Some("InternalError") if message == "out of memory" => ErrorKind::OutOfMemory,
+            _ => ErrorKind::Exception,
+        };
+        let stack = ex.stack();
+        let snippet = stack
+            .as_deref()
+            .and_then(|stack| source_snippet(ex.ctx(), stack));
+        Self {
+            kind,
+            name,
+            message,
+            stack,
+            snippet,
+        }
+    }
+
+    pub fn from_caught(e: &CaughtError<'_>) -> Self {
+        match e {
+            CaughtError::Exception(ex) => Self::from_exception(ex),
+            e => Self {
+                kind: ErrorKind::Exception,
+                name: None,
+                message: e.to_string(),
+                stack: None,
+                snippet: None,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for JsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.message)?;
+        if let Some(snippet) = &self.snippet {
+            writeln!(f, "{snippet}")?;
+        }
+        write!(f, "{}", self.stack.as_deref().unwrap_or("-"))
+    }
+}
+
+impl std::error::Error for JsError {}
+
+/// Script called `exit(code)` - carries the requested exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptExit(pub i32);
+
+impl std::fmt::Display for ScriptExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit({})", self.0)
+    }
+}
+
+impl std::error::Error for ScriptExit {}
+
+/// CLI error output format (`--errors text|json`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Invalid error format: {s} (text|json)")),
+        }
+    }
+}
+
+/// Get error kind (errors not from JS are [`ErrorKind::Host`])
+pub fn error_kind(e: &anyhow::Error) -> ErrorKind {
+    e.downcast_ref::<JsError>()
+        .map(|e| e.kind)
+        .unwrap_or(ErrorKind::Host)
+}
+
+/// Print error to stderr (as text or JSON) and return exit code
+///
+/// [`ScriptExit`] isn't printed - its code is returned as is
+pub fn report_error(e: &anyhow::Error, format: ErrorFormat) -> i32 {
+    if let Some(ScriptExit(code)) = e.downcast_ref::<ScriptExit>() {
+        return *code;
+    }
+    let kind = error_kind(e);
+    if format == ErrorFormat::Json {
+        let err = match e.downcast_ref::<JsError>() {
+            Some(err) => err.clone(),
+            None => JsError {
+                kind,
+                name: None,
+                message: format!("{e:#}"),
+                stack: None,
+                snippet: None,
+            },
+        };
+        eprintln!(
+            "{}",
+            serde_json::json!({ "error": err, "exit_code": kind.exit_code() })
+        );
+    } else {
+        eprintln!("[-] Error: {e:#}");
+    }
+    kind.exit_code()
+}
@@ -0,0 +1,78 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rquickjs::{async_with, AsyncContext, AsyncRuntime};
+
+use crate::compat;
+use crate::run::{exit_requested, script_error};
+use crate::trace::{self, TraceCategory};
+
+/// Interval between checks for new work once the runtime is idle
+pub const IDLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Run event loop until `shutdown` completes (returns its output)
+///
+/// Pending JS jobs and spawned host futures (timers, channel bridges) are
+/// driven via `rt.idle()`. Unlike a single `rt.idle().await` the loop keeps
+/// running once idle, so work queued later by host tasks is still serviced.
+///
+/// When tracing is enabled jobs are executed one at a time so each is
+/// recorded as a separate span.
+pub async fn run_event_loop<F, T>(rt: &AsyncRuntime, shutdown: F) -> T
+where
+    F: Future<Output = T>,
+{
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            biased;
+            out = &mut shutdown => return out,
+            _ = async {
+                while trace::enabled() && rt.is_job_pending().await {
+                    let _span = trace::span(TraceCategory::Job, "job");
+                    let _ = rt.execute_pending_job().await;
+                }
+                rt.idle().await;
+                compat::sleep(IDLE_INTERVAL).await;
+            } => {}
+        }
+    }
+}
+
+/// Drive runtime until all jobs and host futures are finished (as
+/// `rt.idle()`)
+///
+/// When tracing is enabled jobs already queued are executed one at a time
+/// so each is recorded as a span. Jobs queued later by host futures run
+/// inside `rt.idle()` and are not recorded - use [`run_event_loop`] for a
+/// complete trace.
+pub async fn idle(rt: &AsyncRuntime) {
+    while trace::enabled() && rt.is_job_pending().await {
+        let _span = trace::span(TraceCategory::Job, "job");
+        let _ = rt.execute_pending_job().await;
+    }
+    rt.idle().await;
+}
+
+/// Execute pending JS jobs (promise reactions, `queueMicrotask`) until the
+/// queue is empty without polling host futures or timers - returns number of
+/// jobs executed
+///
+/// Lets embedders/tests check state once all microtasks queued by a script
+/// have run (in FIFO order) but before any timer fires, rather than sleeping.
+/// Jobs are run with the runtime locked by `ctx` (`rt.execute_pending_job()`
+/// would also poll host futures once the queue is empty). `exit()` from a job
+/// is returned as [`crate::error::ScriptExit`].
+pub async fn drain_jobs(ctx: &AsyncContext) -> anyhow::Result<usize> {
+    async_with!(ctx => |ctx| {
+        let mut executed = 0;
+        while ctx.execute_pending_job() {
+            executed += 1;
+            if exit_requested(&ctx) {
+                return Err(script_error(&ctx, rquickjs::Error::Exception));
+            }
+        }
+        Ok(executed)
+    })
+    .await
+}
@@ -0,0 +1,315 @@
+use rquickjs::{
+    class::Trace,
+    function::{Func, Opt, This},
+    CaughtError, Class, Ctx, Exception, Function, JsLifetime, Object, Value,
+};
+
+use crate::class::define_class;
+use crate::error::JsError;
+use crate::run::exit_requested;
+
+/// Registered listener (`once` listeners are removed when dispatched)
+#[derive(Trace, Clone)]
+struct Listener<'js> {
+    event: String,
+    f: Function<'js>,
+    once: bool,
+}
+
+/// Listener list shared by event emitting classes ([`EventTarget`],
+/// `AbortSignal`)
+#[derive(Trace, Default)]
+pub struct Listeners<'js>(Vec<Listener<'js>>);
+
+impl<'js> Listeners<'js> {
+    /// Add listener for `event` - adding the same function twice is a no-op
+    /// (as the DOM)
+    pub fn add(&mut self, event: &str, f: Function<'js>, once: bool) {
+        if !self.contains(event, &f) {
+            self.0.push(Listener {
+                event: event.to_string(),
+                f,
+                once,
+            });
+        }
+    }
+
+    /// Add listener with DOM style options (`{once}` object or ignored
+    /// `useCapture` boolean)
+    pub fn add_with_options(
+        &mut self,
+        event: &str,
+        f: Function<'js>,
+        options: Option<Value<'js>>,
+    ) -> rquickjs::Result<()> {
+        let once = match options.as_ref().and_then(|o| o.as_object()) {
+            Some(opts) => opts.get::<_, Option<bool>>("once")?.unwrap_or(false),
+            None => false,
+        };
+        self.add(event, f, once);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, event: &str, f: &Function<'js>) {
+        self.0
+            .retain(|l| l.event != event || l.f.as_value() != f.as_value());
+    }
+
+    pub fn contains(&self, event: &str, f: &Function<'js>) -> bool {
+        self.0
+            .iter()
+            .any(|l| l.event == event && l.f.as_value() == f.as_value())
+    }
+
+    /// Listeners registered for `event` when dispatch starts (listeners
+    /// added during dispatch aren't called) - check each with
+    /// [`Self::claim`] before calling it
+    pub fn matching(&self, event: &str) -> Vec<Function<'js>> {
+        self.0
+            .iter()
+            .filter(|l| l.event == event)
+            .map(|l| l.f.clone())
+            .collect()
+    }
+
+    /// Listener `f` is still registered for `event` (so wasn't removed by
+    /// an earlier listener) - `once` listeners are removed
+    pub fn claim(&mut self, event: &str, f: &Function<'js>) -> bool {
+        let Some(i) = self
+            .0
+            .iter()
+            .position(|l| l.event == event && l.f.as_value() == f.as_value())
+        else {
+            return false;
+        };
+        if self.0[i].once {
+            self.0.remove(i);
+        }
+        true
+    }
+
+    /// Listeners to call for `event` (removes `once` listeners) - collected
+    /// first so callbacks can add/remove listeners
+    pub fn take(&mut self, event: &str) -> Vec<Function<'js>> {
+        let fs = self
+            .0
+            .iter()
+            .filter(|l| l.event == event)
+            .map(|l| l.f.clone())
+            .collect();
+        self.0.retain(|l| l.event != event || !l.once);
+        fs
+    }
+}
+
+/// Call `listeners` with `event` - errors are reported (as uncaught) rather
+/// than stopping dispatch, unless `exit()` was called
+pub fn call_listeners<'js>(
+    ctx: &Ctx<'js>,
+    listeners: Vec<Function<'js>>,
+    event: &Value<'js>,
+) -> rquickjs::Result<()> {
+    for f in listeners {
+        call_listener(ctx, &f, event)?;
+    }
+    Ok(())
+}
+
+/// Call listener `f` with `event` (errors reported as by [`call_listeners`])
+fn call_listener<'js>(
+    ctx: &Ctx<'js>,
+    f: &Function<'js>,
+    event: &Value<'js>,
+) -> rquickjs::Result<()> {
+    match f.call::<_, ()>((event.clone(),)) {
+        Ok(()) => Ok(()),
+        Err(e) if exit_requested(ctx) => Err(e),
+        Err(e) => {
+            let caught = CaughtError::from_error(ctx, e);
+            eprintln!("Uncaught {}", JsError::from_caught(&caught));
+            Ok(())
+        }
+    }
+}
+
+/// `EventTarget` - base eventing model for host objects (create with
+/// [`EventTarget::instance`] and emit with [`dispatch_event`])
+#[derive(Trace, JsLifetime, Default)]
+#[rquickjs::class]
+pub struct EventTarget<'js> {
+    listeners: Listeners<'js>,
+}
+
+impl<'js> EventTarget<'js> {
+    pub fn instance(ctx: &Ctx<'js>) -> rquickjs::Result<Class<'js, Self>> {
+        Class::instance(ctx.clone(), Self::default())
+    }
+}
+
+#[rquickjs::methods]
+impl<'js> EventTarget<'js> {
+    #[qjs(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// addEventListener(type, fn, {once}?)
+    #[qjs(rename = "addEventListener")]
+    pub fn add_event_listener(
+        &mut self,
+        event: String,
+        f: Function<'js>,
+        options: Opt<Value<'js>>,
+    ) -> rquickjs::Result<()> {
+        self.listeners.add_with_options(&event, f, options.0)
+    }
+
+    /// removeEventListener(type, fn)
+    #[qjs(rename = "removeEventListener")]
+    pub fn remove_event_listener(&mut self, event: String, f: Function<'js>) {
+        self.listeners.remove(&event, &f);
+    }
+}
+
+/// `CustomEvent(type, {detail, cancelable})`
+#[derive(Trace, JsLifetime)]
+#[rquickjs::class]
+pub struct CustomEvent<'js> {
+    event_type: String,
+    detail: Option<Value<'js>>,
+    cancelable: bool,
+    default_prevented: bool,
+    target: Option<Value<'js>>,
+}
+
+impl<'js> CustomEvent<'js> {
+    /// New event with `detail` (as `new CustomEvent(type, {detail})`)
+    pub fn instance(
+        ctx: &Ctx<'js>,
+        event_type: &str,
+        detail: Option<Value<'js>>,
+    ) -> rquickjs::Result<Class<'js, Self>> {
+        Class::instance(
+            ctx.clone(),
+            Self {
+                event_type: event_type.to_string(),
+                detail,
+                cancelable: false,
+                default_prevented: false,
+                target: None,
+            },
+        )
+    }
+}
+
+#[rquickjs::methods]
+impl<'js> CustomEvent<'js> {
+    #[qjs(constructor)]
+    pub fn new(event_type: String, options: Opt<Object<'js>>) -> rquickjs::Result<Self> {
+        let (detail, cancelable) = match &options.0 {
+            Some(opts) => (
+                opts.get::<_, Option<Value>>("detail")?,
+                opts.get::<_, Option<bool>>("cancelable")?.unwrap_or(false),
+            ),
+            None => (None, false),
+        };
+        Ok(Self {
+            event_type,
+            detail,
+            cancelable,
+            default_prevented: false,
+            target: None,
+        })
+    }
+
+    #[qjs(get, rename = "type")]
+    pub fn event_type(&self) -> String {
+        self.event_type.clone()
+    }
+
+    #[qjs(get)]
+    pub fn detail(&self, ctx: Ctx<'js>) -> Value<'js> {
+        self.detail.clone().unwrap_or_else(|| Value::new_null(ctx))
+    }
+
+    #[qjs(get)]
+    pub fn cancelable(&self) -> bool {
+        self.cancelable
+    }
+
+    #[qjs(get, rename = "defaultPrevented")]
+    pub fn default_prevented(&self) -> bool {
+        self.default_prevented
+    }
+
+    #[qjs(get)]
+    pub fn target(&self, ctx: Ctx<'js>) -> Value<'js> {
+        self.target.clone().unwrap_or_else(|| Value::new_null(ctx))
+    }
+
+    /// Mark cancelable event as cancelled (`dispatchEvent` returns false)
+    #[qjs(rename = "preventDefault")]
+    pub fn prevent_default(&mut self) {
+        if self.cancelable {
+            self.default_prevented = true;
+        }
+    }
+}
+
+/// Register `EventTarget` and `CustomEvent` classes
+pub fn register_events<'js>(ctx: &Ctx<'js>) -> anyhow::Result<()> {
+    define_class::<CustomEvent>(ctx)?;
+    let proto = define_class::<EventTarget>(ctx)?;
+    // Defined outside the class so listeners can add/remove listeners
+    proto.set(
+        "dispatchEvent",
+        Func::new(
+            |ctx: Ctx<'js>, this: This<Class<'js, EventTarget<'js>>>, event: Value<'js>| {
+                dispatch_event(&ctx, &this.0, event)
+            },
+        ),
+    )?;
+    Ok(())
+}
+
+/// Dispatch `event` (`CustomEvent` or object with `type`) to listeners on
+/// `target` - returns false if a listener called `preventDefault()`
+///
+/// As the DOM, listeners added during dispatch aren't called and those
+/// removed by an earlier listener are skipped
+pub fn dispatch_event<'js>(
+    ctx: &Ctx<'js>,
+    target: &Class<'js, EventTarget<'js>>,
+    event: Value<'js>,
+) -> rquickjs::Result<bool> {
+    let custom = Class::<CustomEvent>::from_value(&event).ok();
+    let event_type = match (&custom, event.as_object()) {
+        (Some(custom), _) => custom.borrow().event_type.clone(),
+        (None, Some(obj)) => obj.get::<_, String>("type")?,
+        (None, None) => {
+            return Err(Exception::throw_type(
+                ctx,
+                "dispatchEvent: argument must be an event",
+            ))
+        }
+    };
+    match (&custom, event.as_object()) {
+        (Some(custom), _) => custom.borrow_mut().target = Some(target.clone().into_value()),
+        (None, Some(obj)) => obj.set("target", target.clone())?,
+        (None, None) => {}
+    }
+    let listeners = target.borrow().listeners.matching(&event_type);
+    for f in listeners {
+        if target.borrow_mut().listeners.claim(&event_type, &f) {
+            call_listener(ctx, &f, &event)?;
+        }
+    }
+    let prevented = match (&custom, event.as_object()) {
+        (Some(custom), _) => custom.borrow().default_prevented,
+        (None, Some(obj)) => obj
+            .get::<_, Option<bool>>("defaultPrevented")?
+            .unwrap_or(false),
+        (None, None) => false,
+    };
+    Ok(!prevented)
+}
@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::time::Duration;
+
+/// Executor dependent operations used by the host bridges (timers,
+/// watchdog, lock retry, plugin calls)
+///
+/// The channel bridges use `tokio::sync` channels which don't depend on the
+/// tokio runtime so are shared by all executors. The `dns`/`net` modules
+/// and the REPL use tokio IO so can't be built with `executor_smol`.
+pub trait Executor {
+    /// Run future in the background
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static;
+
+    /// Sleep for `d`
+    fn sleep(d: Duration) -> impl Future<Output = ()> + Send;
+
+    /// Run blocking `f` on a thread pool
+    fn spawn_blocking<F, T>(f: F) -> impl Future<Output = anyhow::Result<T>> + Send
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static;
+}
+
+#[cfg(all(
+    feature = "executor_smol",
+    any(
+        feature = "dns",
+        feature = "net",
+        feature = "repl_rustyline",
+        feature = "repl_rustyline_async"
+    )
+))]
+compile_error!("executor_smol can't be combined with dns/net/repl features (they use tokio IO)");
+
+/// tokio executor
+#[cfg(not(target_arch = "wasm32"))]
+pub struct TokioExecutor;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Executor for TokioExecutor {
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+
+    fn sleep(d: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(d)
+    }
+
+    fn spawn_blocking<F, T>(f: F) -> impl Future<Output = anyhow::Result<T>> + Send
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        async move { Ok(tokio::task::spawn_blocking(f).await?) }
+    }
+}
+
+/// smol executor
+#[cfg(feature = "executor_smol")]
+pub struct SmolExecutor;
+
+#[cfg(feature = "executor_smol")]
+impl Executor for SmolExecutor {
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        smol::spawn(future).detach();
+    }
+
+    fn sleep(d: Duration) -> impl Future<Output = ()> + Send {
+        async move {
+            smol::Timer::after(d).await;
+        }
+    }
+
+    fn spawn_blocking<F, T>(f: F) -> impl Future<Output = anyhow::Result<T>> + Send
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        async move { Ok(smol::unblock(f).await) }
+    }
+}
+
+/// Executor selected by feature (`executor_smol`, otherwise tokio)
+#[cfg(feature = "executor_smol")]
+pub type DefaultExecutor = SmolExecutor;
+#[cfg(all(not(feature = "executor_smol"), not(target_arch = "wasm32")))]
+pub type DefaultExecutor = TokioExecutor;
@@ -0,0 +1,272 @@
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use rquickjs::{function::Rest, Context, Ctx, Exception, Function, Runtime, Value};
+
+use crate::convert::{args_to_json, json_to_value, value_to_json};
+use crate::run::{call_fn_blocking, run_script_blocking};
+
+/// Host callback registered with [`rqjs_register`]
+///
+/// Receives the call arguments as a JSON array and returns the result as
+/// JSON (or NULL to throw) - the returned string is owned by the host and
+/// only needs to stay valid until the callback is called again
+///
+/// Nullable so a NULL function pointer from C is rejected rather than UB
+pub type Callback =
+    Option<extern "C" fn(user_data: *mut c_void, args_json: *const c_char) -> *const c_char>;
+
+/// Embedded runtime/context (opaque pointer on the C side)
+///
+/// Uses a plain (sync) runtime - pending jobs are executed before each
+/// call returns so scripts can't await host futures
+pub struct ContextHandle {
+    // Context must be dropped before the runtime
+    ctx: Context,
+    _rt: Runtime,
+    last_error: Option<CString>,
+}
+
+impl ContextHandle {
+    /// Create runtime and full context
+    pub fn new() -> anyhow::Result<Self> {
+        let rt = Runtime::new()?;
+        let ctx = Context::full(&rt)?;
+        Ok(Self {
+            ctx,
+            _rt: rt,
+            last_error: None,
+        })
+    }
+
+    /// Evaluate script returning result as JSON
+    pub fn eval(&self, script: &str) -> anyhow::Result<String> {
+        self.ctx.with(|ctx| {
+            let v = run_script_blocking(ctx.clone(), script.to_string())?;
+            value_to_json(ctx, v)
+        })
+    }
+
+    /// Call fn at dotted `path` with `args_json` (JSON array) returning
+    /// result as JSON
+    pub fn call(&self, path: &str, args_json: &str) -> anyhow::Result<String> {
+        self.ctx.with(|ctx| {
+            let args = json_to_value(ctx.clone(), args_json)?
+                .into_array()
+                .ok_or(anyhow::anyhow!("Args must be a JSON array"))?
+                .iter::<Value>()
+                .collect::<rquickjs::Result<Vec<_>>>()?;
+            let v = call_fn_blocking(ctx.clone(), path, Rest(args))?;
+            value_to_json(ctx, v)
+        })
+    }
+
+    /// Register global fn `name` forwarding calls to `callback`
+    pub fn register(
+        &self,
+        name: &str,
+        callback: Callback,
+        user_data: *mut c_void,
+    ) -> anyhow::Result<()> {
+        let callback = callback.ok_or(anyhow::anyhow!("NULL callback"))?;
+        self.ctx
+            .with(|ctx| register_callback(&ctx, name, callback, user_data))
+    }
+
+    /// Save error message for [`rqjs_last_error`]
+    fn result<T>(&mut self, result: anyhow::Result<T>) -> Option<T> {
+        match result {
+            Ok(v) => {
+                self.last_error = None;
+                Some(v)
+            }
+            Err(e) => {
+                self.last_error = Some(c_string(format!("{e:#}")));
+                None
+            }
+        }
+    }
+}
+
+fn register_callback<'js>(
+    ctx: &Ctx<'js>,
+    name: &str,
+    callback: extern "C" fn(*mut c_void, *const c_char) -> *const c_char,
+    user_data: *mut c_void,
+) -> anyhow::Result<()> {
+    let f = Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>, args: Rest<Value<'js>>| -> rquickjs::Result<Value<'js>> {
+            let args = args_to_json(ctx.clone(), args.0)
+                .map_err(|e| Exception::throw_type(&ctx, &e.to_string()))?;
+            let args = c_string(args);
+            let out = callback(user_data, args.as_ptr());
+            if out.is_null() {
+                return Err(Exception::throw_message(&ctx, "Host callback failed"));
+            }
+            // SAFETY: non-null result is a NUL terminated string owned by host
+            let out = unsafe { CStr::from_ptr(out) }.to_string_lossy();
+            json_to_value(ctx.clone(), &out).map_err(|e| {
+                Exception::throw_message(&ctx, &format!("Invalid callback result: {e}"))
+            })
+        },
+    )?
+    .with_name(name)?;
+    ctx.globals().set(name, f)?;
+    Ok(())
+}
+
+/// Run `f` catching panics - unwinding out of an `extern "C"` fn aborts the
+/// host process
+fn catch<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let msg = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown".to_string());
+        Err(anyhow::anyhow!("Panic: {msg}"))
+    })
+}
+
+/// Convert to C string (interior NULs can't be represented so are dropped)
+fn c_string(s: String) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+/// Borrow C string as &str
+///
+/// # Safety
+/// `s` must be NULL or a valid NUL terminated string
+unsafe fn to_str<'a>(s: *const c_char) -> anyhow::Result<&'a str> {
+    if s.is_null() {
+        return Err(anyhow::anyhow!("NULL string"));
+    }
+    Ok(unsafe { CStr::from_ptr(s) }.to_str()?)
+}
+
+/// Create context (NULL on failure) - free with [`rqjs_context_free`]
+#[unsafe(no_mangle)]
+pub extern "C" fn rqjs_context_new() -> *mut ContextHandle {
+    match catch(ContextHandle::new) {
+        Ok(handle) => Box::into_raw(Box::new(handle)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Destroy context
+///
+/// # Safety
+/// `handle` must be NULL or returned by [`rqjs_context_new`] (and not
+/// already freed)
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rqjs_context_free(handle: *mut ContextHandle) {
+    if !handle.is_null() {
+        let _ = catch(|| {
+            drop(unsafe { Box::from_raw(handle) });
+            Ok(())
+        });
+    }
+}
+
+/// Evaluate script - returns result as JSON (free with
+/// [`rqjs_string_free`]) or NULL on error (see [`rqjs_last_error`])
+///
+/// # Safety
+/// `handle` must be a live context and `script` a NUL terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rqjs_eval(
+    handle: *mut ContextHandle,
+    script: *const c_char,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_mut() }) else {
+        return ptr::null_mut();
+    };
+    let result = catch(|| unsafe { to_str(script) }.and_then(|script| handle.eval(script)));
+    handle
+        .result(result)
+        .map_or(ptr::null_mut(), |s| c_string(s).into_raw())
+}
+
+/// Call fn at dotted `path` with `args_json` (JSON array, NULL for no
+/// args) - returns result as JSON (free with [`rqjs_string_free`]) or NULL
+/// on error (see [`rqjs_last_error`])
+///
+/// # Safety
+/// `handle` must be a live context, `path` a NUL terminated string and
+/// `args_json` NULL or a NUL terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rqjs_call(
+    handle: *mut ContextHandle,
+    path: *const c_char,
+    args_json: *const c_char,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_mut() }) else {
+        return ptr::null_mut();
+    };
+    let result = catch(|| {
+        let path = unsafe { to_str(path) }?;
+        let args = if args_json.is_null() {
+            "[]"
+        } else {
+            unsafe { to_str(args_json) }?
+        };
+        handle.call(path, args)
+    });
+    handle
+        .result(result)
+        .map_or(ptr::null_mut(), |s| c_string(s).into_raw())
+}
+
+/// Register global fn `name` calling `callback` (see [`Callback`]) - returns
+/// 0 on success or -1 on error, including a NULL callback (see
+/// [`rqjs_last_error`])
+///
+/// # Safety
+/// `handle` must be a live context and `name` a NUL terminated string -
+/// `user_data` is passed to `callback` as is and must outlive the context
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rqjs_register(
+    handle: *mut ContextHandle,
+    name: *const c_char,
+    callback: Callback,
+    user_data: *mut c_void,
+) -> c_int {
+    let Some(handle) = (unsafe { handle.as_mut() }) else {
+        return -1;
+    };
+    let result = catch(|| {
+        unsafe { to_str(name) }.and_then(|name| handle.register(name, callback, user_data))
+    });
+    handle.result(result).map_or(-1, |_| 0)
+}
+
+/// Last error message (NULL if the previous call succeeded) - owned by the
+/// context and valid until the next call
+///
+/// # Safety
+/// `handle` must be NULL or a live context
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rqjs_last_error(handle: *const ContextHandle) -> *const c_char {
+    catch(|| {
+        Ok(unsafe { handle.as_ref() }
+            .and_then(|handle| handle.last_error.as_ref())
+            .map_or(ptr::null(), |e| e.as_ptr()))
+    })
+    .unwrap_or(ptr::null())
+}
+
+/// Free string returned by [`rqjs_eval`]/[`rqjs_call`]
+///
+/// # Safety
+/// `s` must be NULL or returned by this library (and not already freed)
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rqjs_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        let _ = catch(|| {
+            drop(unsafe { CString::from_raw(s) });
+            Ok(())
+        });
+    }
+}
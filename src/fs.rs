@@ -0,0 +1,382 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(all(feature = "tail", not(target_arch = "wasm32")))]
+use std::time::Duration;
+
+use anyhow::anyhow;
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+use rquickjs::ArrayBuffer;
+use rquickjs::{
+    function::{Func, Opt},
+    Ctx, Exception, Object,
+};
+use tokio::sync::mpsc;
+
+use crate::util::channel_iterator;
+
+/// Directories scripts may read from (paths are canonicalised so `..` and
+/// symlinks can't escape a root)
+#[derive(Debug, Clone)]
+pub struct FsAccess {
+    roots: Vec<PathBuf>,
+}
+
+impl FsAccess {
+    pub fn new(roots: &[PathBuf]) -> anyhow::Result<Self> {
+        let roots = roots
+            .iter()
+            .map(|r| {
+                r.canonicalize()
+                    .map_err(|e| anyhow!("Invalid fs root: {} [{e}]", r.display()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { roots })
+    }
+
+    /// Canonical path if `path` is a regular file under one of the roots
+    pub fn check_file(&self, path: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+        let path = path.as_ref().canonicalize()?;
+        if !self.roots.iter().any(|r| path.starts_with(r)) {
+            return Err(anyhow!("Permission denied"));
+        }
+        if !path.metadata()?.is_file() {
+            return Err(anyhow!("Not a file"));
+        }
+        Ok(path)
+    }
+}
+
+/// Lines buffered ahead of the consumer
+const LINE_BUFFER: usize = 256;
+/// `fs.tail` re-checks the file this often even without watcher events
+/// (eg. on network filesystems)
+#[cfg(all(feature = "tail", not(target_arch = "wasm32")))]
+const TAIL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest file `fs.mmap` will map (QuickJS ArrayBuffer length limit)
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+pub const MMAP_MAX_LEN: u64 = i32::MAX as u64;
+
+/// Line, resume offset and whether the line is unterminated, or read error
+type Line = Result<(String, u64, bool), String>;
+
+/// Register `fs` object with `lines(path, {from})` (plus `tail(path,
+/// {from})` and `mmap(path)` with the `tail`/`mmap` features) - access is restricted to the roots in `access`
+pub fn register_fs<'js>(ctx: &Ctx<'js>, access: Arc<FsAccess>) -> anyhow::Result<()> {
+    let fs = Object::new(ctx.clone())?;
+    let a = access.clone();
+    fs.set(
+        "lines",
+        Func::new(move |ctx: Ctx<'js>, path: String, opts: Opt<Object<'js>>| {
+            let from = match &opts.0 {
+                Some(opts) => opts.get::<_, Option<u64>>("from")?.unwrap_or(0),
+                None => 0,
+            };
+            let path = a.check_file(&path).map_err(|e| fs_error(&ctx, &path, e))?;
+            let rx = read_lines(&path, from)
+                .map_err(|e| fs_error(&ctx, &path.display().to_string(), e))?;
+            line_iterator(&ctx, rx)
+        }),
+    )?;
+    #[cfg(all(feature = "tail", not(target_arch = "wasm32")))]
+    {
+        let a = access.clone();
+        fs.set(
+            "tail",
+            Func::new(move |ctx: Ctx<'js>, path: String, opts: Opt<Object<'js>>| {
+                let from = match &opts.0 {
+                    Some(opts) => opts.get::<_, Option<u64>>("from")?,
+                    None => None,
+                };
+                let path = a.check_file(&path).map_err(|e| fs_error(&ctx, &path, e))?;
+                let rx = tail_lines(&path, from)
+                    .map_err(|e| fs_error(&ctx, &path.display().to_string(), e))?;
+                line_iterator(&ctx, rx)
+            }),
+        )?;
+    }
+    #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+    fs.set(
+        "mmap",
+        Func::new(move |ctx: Ctx<'js>, path: String| {
+            let path = access
+                .check_file(&path)
+                .map_err(|e| fs_error(&ctx, &path, e))?;
+            mmap(&ctx, &path).map_err(|e| fs_error(&ctx, &path.display().to_string(), e))
+        }),
+    )?;
+    ctx.globals().set("fs", fs)?;
+    Ok(())
+}
+
+/// fs.lines(path, {from = 0}) - async iterator of `{line, offset, partial}`
+/// read from byte offset `from`, where `offset` is where to resume after
+/// `line` (pass the last offset as `from` on the next run to only see new
+/// lines)
+///
+/// If `from` is past the end of the file (truncated or rotated) reading
+/// restarts from 0. An unterminated last line (possibly still being
+/// written) is yielded with `partial: true` and the offset of its start,
+/// so a resumed read sees it whole once its newline is written.
+pub fn read_lines(path: &Path, from: u64) -> anyhow::Result<mpsc::Receiver<Line>> {
+    let mut file = File::open(path)?;
+    let from = if from > file.metadata()?.len() {
+        0
+    } else {
+        from
+    };
+    file.seek(SeekFrom::Start(from))?;
+    let (tx, rx) = mpsc::channel(LINE_BUFFER);
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(file);
+        let mut offset = from;
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let record = match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let start = offset;
+                    offset += n as u64;
+                    let partial = buf.last() != Some(&b'\n');
+                    let resume = if partial { start } else { offset };
+                    Ok((decode_line(&buf), resume, partial))
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            let failed = record.is_err();
+            if tx.blocking_send(record).is_err() || failed {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// fs.tail(path, {from = end}) - async iterator of `{line, offset,
+/// partial}` for lines appended to `path`, following it across rotation
+///
+/// Woken by a watcher on the parent directory. When `path` is replaced
+/// (new inode) the rest of the old file is read (an unterminated last line
+/// is yielded with `partial: true`) and tailing continues from the start of
+/// the new one; truncation restarts at 0. Offsets are relative to the
+/// current file.
+#[cfg(all(feature = "tail", not(target_arch = "wasm32")))]
+pub fn tail_lines(path: &Path, from: Option<u64>) -> anyhow::Result<mpsc::Receiver<Line>> {
+    use notify::{RecursiveMode, Watcher};
+
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let from = match from {
+        Some(from) if from <= len => from,
+        Some(_) => 0,
+        None => len,
+    };
+    let (wake_tx, wake_rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |_: notify::Result<notify::Event>| {
+        let _ = wake_tx.send(());
+    })?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("No parent directory"))?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    let path = path.to_path_buf();
+    let (tx, rx) = mpsc::channel(LINE_BUFFER);
+    std::thread::spawn(move || {
+        let _watcher = watcher;
+        if let Err(e) = follow(&path, file, from, &wake_rx, &tx) {
+            let _ = tx.blocking_send(Err(e.to_string()));
+        }
+    });
+    Ok(rx)
+}
+
+/// Send complete lines as they are appended until the receiver closes
+#[cfg(all(feature = "tail", not(target_arch = "wasm32")))]
+fn follow(
+    path: &Path,
+    file: File,
+    from: u64,
+    wake: &std::sync::mpsc::Receiver<()>,
+    tx: &mpsc::Sender<Line>,
+) -> anyhow::Result<()> {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let mut id = file_id(&file.metadata()?);
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(from))?;
+    let mut offset = from;
+    // Partial line carried over until its newline is written
+    let mut buf = Vec::new();
+    loop {
+        if !send_lines(&mut reader, &mut buf, &mut offset, tx)? {
+            return Ok(());
+        }
+        match std::fs::metadata(path) {
+            // Rotated - finish old file and switch to the new one
+            Ok(meta) if file_id(&meta) != id => {
+                if let Ok(file) = File::open(path) {
+                    // Drain lines written to the old file since the read
+                    // above (the writer may still have had it open)
+                    if !send_lines(&mut reader, &mut buf, &mut offset, tx)? {
+                        return Ok(());
+                    }
+                    if !buf.is_empty() {
+                        offset += buf.len() as u64;
+                        if tx
+                            .blocking_send(Ok((decode_line(&buf), offset, true)))
+                            .is_err()
+                        {
+                            return Ok(());
+                        }
+                        buf.clear();
+                    }
+                    id = file_id(&file.metadata()?);
+                    reader = BufReader::new(file);
+                    offset = 0;
+                    continue;
+                }
+            }
+            // Truncated in place
+            Ok(meta) if meta.len() < offset => {
+                reader.seek(SeekFrom::Start(0))?;
+                buf.clear();
+                offset = 0;
+                continue;
+            }
+            // Unchanged or removed (wait for it to be recreated)
+            _ => {}
+        }
+        if tx.is_closed() {
+            return Ok(());
+        }
+        if let Err(RecvTimeoutError::Disconnected) = wake.recv_timeout(TAIL_POLL_INTERVAL) {
+            return Err(anyhow!("Watcher stopped"));
+        }
+        // Coalesce bursts of events
+        while wake.try_recv().is_ok() {}
+    }
+}
+
+/// Send complete lines from `reader` until EOF, leaving a partial line in
+/// `buf` - false once the receiver is closed
+#[cfg(all(feature = "tail", not(target_arch = "wasm32")))]
+fn send_lines(
+    reader: &mut BufReader<File>,
+    buf: &mut Vec<u8>,
+    offset: &mut u64,
+    tx: &mpsc::Sender<Line>,
+) -> anyhow::Result<bool> {
+    while reader.read_until(b'\n', buf)? > 0 && buf.last() == Some(&b'\n') {
+        *offset += buf.len() as u64;
+        if tx
+            .blocking_send(Ok((decode_line(buf), *offset, false)))
+            .is_err()
+        {
+            return Ok(false);
+        }
+        buf.clear();
+    }
+    Ok(true)
+}
+
+/// Identity of the file a path refers to (changes when rotated)
+#[cfg(all(feature = "tail", not(target_arch = "wasm32"), unix))]
+fn file_id(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+/// Rotation is only detected by truncation without inode numbers
+#[cfg(all(feature = "tail", not(target_arch = "wasm32"), not(unix)))]
+fn file_id(_meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Line without terminator (invalid UTF-8 is replaced)
+fn decode_line(buf: &[u8]) -> String {
+    let line = buf.strip_suffix(b"\n").unwrap_or(buf);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+/// Async iterator of `{line, offset, partial}` records
+fn line_iterator<'js>(ctx: &Ctx<'js>, rx: mpsc::Receiver<Line>) -> rquickjs::Result<Object<'js>> {
+    channel_iterator(ctx, rx, "FS", |ctx, (line, offset, partial)| {
+        let record = Object::new(ctx.clone())?;
+        record.set("line", line)?;
+        record.set("offset", offset)?;
+        record.set("partial", partial)?;
+        Ok(record.into_value())
+    })
+}
+
+fn fs_error(ctx: &Ctx<'_>, path: &str, e: impl std::fmt::Display) -> rquickjs::Error {
+    Exception::throw_message(ctx, &format!("FS Error: {path} [{e}]"))
+}
+
+/// fs.mmap(path) - ArrayBuffer backed by a memory map of `path` (pages are
+/// read on demand rather than copied into the JS heap)
+///
+/// QuickJS has no read-only ArrayBuffers, so rather than a read-only map
+/// the buffer is a private copy-on-write map: writes through views succeed
+/// but only change the script's copy (touched pages are copied into host
+/// memory) and never reach the file. As with any mmap, the file must not be
+/// truncated while mapped (the host may be killed by SIGBUS).
+///
+/// Files larger than [`MMAP_MAX_LEN`] are rejected (QuickJS ArrayBuffer
+/// lengths are 32 bit).
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+fn mmap<'js>(ctx: &Ctx<'js>, path: &Path) -> anyhow::Result<ArrayBuffer<'js>> {
+    use rquickjs::{qjs, Value};
+    use std::ffi::c_void;
+
+    unsafe extern "C" fn drop_map(
+        _rt: *mut qjs::JSRuntime,
+        opaque: *mut c_void,
+        _ptr: *mut c_void,
+    ) {
+        // SAFETY: `opaque` is the box leaked below, freed once by QuickJS
+        drop(unsafe { Box::from_raw(opaque as *mut memmap2::MmapMut) });
+    }
+
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len > MMAP_MAX_LEN {
+        return Err(anyhow!(
+            "File too large to map ({len} bytes, limit {MMAP_MAX_LEN})"
+        ));
+    }
+    if len == 0 {
+        // Zero length maps are rejected by the OS
+        return Ok(ArrayBuffer::new(ctx.clone(), Vec::<u8>::new())?);
+    }
+    // SAFETY: private mapping (see above) - truncation by another process
+    // is the caller's responsibility
+    let map = unsafe { memmap2::MmapOptions::new().map_copy(&file)? };
+    let len = map.len();
+    let map = Box::into_raw(Box::new(map));
+    // SAFETY: the buffer points into the map, which lives until QuickJS
+    // calls `drop_map` when the ArrayBuffer is collected
+    let value = unsafe {
+        let raw = qjs::JS_NewArrayBuffer(
+            ctx.as_raw().as_ptr(),
+            (*map).as_mut_ptr(),
+            len as _,
+            Some(drop_map),
+            map as *mut c_void,
+            false,
+        );
+        Value::from_raw(ctx.clone(), raw)
+    };
+    match ArrayBuffer::from_value(value) {
+        Some(buffer) => Ok(buffer),
+        None => {
+            // SAFETY: QuickJS did not take ownership on failure
+            drop(unsafe { Box::from_raw(map) });
+            Err(anyhow!("ArrayBuffer allocation failed"))
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Default max history entries
+pub const DEFAULT_MAX_HISTORY: usize = 1000;
+
+/// REPL history shared by all frontends
+///
+/// Stored one entry per line (multi-line entries have `\n` and `\\`
+/// escaped). Adding an entry removes any earlier duplicate.
+///
+/// New entries are appended to the file - it is only rewritten (removing
+/// duplicates and old entries) once it grows to twice the max entries
+#[derive(Debug, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    entries: Vec<String>,
+    max: usize,
+    /// Lines in history file (including duplicates)
+    file_lines: usize,
+}
+
+/// Default history file (`~/.rquickjs_history`)
+pub fn default_history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rquickjs_history"))
+}
+
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Check if all chars of `query` appear in order in `entry`
+fn fuzzy_match(entry: &str, query: &str) -> bool {
+    let mut chars = entry.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+impl History {
+    /// Load history from file (missing file is treated as empty)
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let mut history = Self {
+            path,
+            entries: Vec::new(),
+            max: DEFAULT_MAX_HISTORY,
+            file_lines: 0,
+        };
+        if let Some(path) = &history.path {
+            match std::fs::read_to_string(path) {
+                Ok(s) => s.lines().for_each(|l| {
+                    history.file_lines += 1;
+                    history.push(unescape(l));
+                }),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(history)
+    }
+
+    fn push(&mut self, entry: String) {
+        self.entries.retain(|e| *e != entry);
+        self.entries.push(entry);
+        if self.entries.len() > self.max {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Add entry (de-duplicated) and append to history file
+    pub fn add(&mut self, entry: &str) -> anyhow::Result<()> {
+        if entry.trim().is_empty() {
+            return Ok(());
+        }
+        self.push(entry.to_string());
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.file_lines + 1 >= self.max * 2 {
+            return self.save();
+        }
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(f, "{}", escape(entry))?;
+        self.file_lines += 1;
+        Ok(())
+    }
+
+    /// Rewrite history file (de-duplicated and trimmed to max entries)
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            let mut f = std::fs::File::create(path)?;
+            for e in &self.entries {
+                writeln!(f, "{}", escape(e))?;
+            }
+            self.file_lines = self.entries.len();
+        }
+        Ok(())
+    }
+
+    /// History entries (oldest first)
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Search history (most recent first) by substring or fuzzy match
+    pub fn search(&self, query: &str, fuzzy: bool) -> Vec<(usize, &str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, e)| {
+                if fuzzy {
+                    fuzzy_match(e, query)
+                } else {
+                    e.contains(query)
+                }
+            })
+            .map(|(i, e)| (i, e.as_str()))
+            .collect()
+    }
+}
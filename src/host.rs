@@ -0,0 +1,74 @@
+use rquickjs::{function::Func, Ctx, Exception, Object};
+use std::cmp::Ordering;
+
+/// Crate version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// rquickjs version (from Cargo.lock at build time)
+pub const RQUICKJS_VERSION: &str = env!("RQUICKJS_VERSION");
+/// Git hash (injected by build.rs)
+pub const GIT_HASH: &str = env!("GIT_HASH");
+
+/// Register `host` object
+///
+/// `host.features()` returns `{ name: available }` for the optional
+/// modules in this build/configuration so scripts can degrade gracefully
+///
+/// `host.version` is `{ crate, rquickjs, git }`
+pub fn register_host(ctx: &Ctx<'_>, features: &[(&str, bool)]) -> anyhow::Result<()> {
+    let host = Object::new(ctx.clone())?;
+    let features = features
+        .iter()
+        .map(|(name, enabled)| (name.to_string(), *enabled))
+        .collect::<Vec<_>>();
+    host.set(
+        "features",
+        Func::new(move |ctx| {
+            let obj = Object::new(ctx)?;
+            for (name, enabled) in &features {
+                obj.set(name.as_str(), *enabled)?;
+            }
+            Ok::<_, rquickjs::Error>(obj)
+        }),
+    )?;
+    let version = Object::new(ctx.clone())?;
+    version.set("crate", VERSION)?;
+    version.set("rquickjs", RQUICKJS_VERSION)?;
+    version.set("git", GIT_HASH)?;
+    host.set("version", version)?;
+    ctx.globals().set("host", host)?;
+    Ok(())
+}
+
+/// Register `semver` object (satisfies, compare)
+pub fn register_semver(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let semver = Object::new(ctx.clone())?;
+    semver.set("satisfies", js_satisfies)?;
+    semver.set("compare", js_compare)?;
+    ctx.globals().set("semver", semver)?;
+    Ok(())
+}
+
+fn parse_version(ctx: &Ctx<'_>, v: &str) -> rquickjs::Result<semver::Version> {
+    semver::Version::parse(v.trim_start_matches('v'))
+        .map_err(|e| Exception::throw_message(ctx, &format!("Invalid version: {v} [{e}]")))
+}
+
+/// semver.satisfies("1.2.3", ">=1.2, <2")
+#[rquickjs::function]
+fn satisfies(ctx: Ctx<'_>, version: String, req: String) -> rquickjs::Result<bool> {
+    let version = parse_version(&ctx, &version)?;
+    let req = semver::VersionReq::parse(&req)
+        .map_err(|e| Exception::throw_message(&ctx, &format!("Invalid range: {req} [{e}]")))?;
+    Ok(req.matches(&version))
+}
+
+/// semver.compare(a, b) => -1 | 0 | 1
+#[rquickjs::function]
+fn compare(ctx: Ctx<'_>, a: String, b: String) -> rquickjs::Result<i32> {
+    let (a, b) = (parse_version(&ctx, &a)?, parse_version(&ctx, &b)?);
+    Ok(match a.cmp(&b) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    })
+}
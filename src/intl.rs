@@ -0,0 +1,196 @@
+use icu::calendar::Date;
+use icu::collator::{
+    options::{CollatorOptions, Strength},
+    Collator, CollatorBorrowed,
+};
+use icu::datetime::{fieldsets, DateTimeFormatter};
+use icu::decimal::{input::Decimal, DecimalFormatter};
+use icu::locale::Locale;
+use icu::time::{DateTime, Time};
+use rquickjs::{function::Opt, Coerced, Ctx, Exception, Object};
+
+/// Locale used when options don't specify one
+pub const DEFAULT_LOCALE: &str = "en";
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Register `intl` object with `formatNumber`, `formatDate`, `compare` and
+/// `sort` (backed by ICU4X compiled data - gated by the `intl` feature as
+/// it adds several MB to the binary)
+pub fn register_intl(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let intl = Object::new(ctx.clone())?;
+    intl.set("formatNumber", js_format_number)?;
+    intl.set("formatDate", js_format_date)?;
+    intl.set("compare", js_compare)?;
+    intl.set("sort", js_sort)?;
+    ctx.globals().set("intl", intl)?;
+    Ok(())
+}
+
+/// Optional property from options object
+fn opt<'js, T: rquickjs::FromJs<'js>>(
+    opts: &Opt<Object<'js>>,
+    key: &str,
+) -> rquickjs::Result<Option<T>> {
+    match &opts.0 {
+        Some(opts) => opts.get(key),
+        None => Ok(None),
+    }
+}
+
+fn locale<'js>(ctx: &Ctx<'js>, opts: &Opt<Object<'js>>) -> rquickjs::Result<Locale> {
+    let name = opt::<String>(opts, "locale")?.unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    Locale::try_from_str(&name)
+        .map_err(|e| Exception::throw_range(ctx, &format!("Invalid locale: {name} [{e}]")))
+}
+
+fn intl_error(ctx: &Ctx<'_>, e: impl std::fmt::Display) -> rquickjs::Error {
+    Exception::throw_message(ctx, &format!("Intl Error: {e}"))
+}
+
+/// intl.formatNumber(n, {locale, minimumFractionDigits = 0,
+/// maximumFractionDigits = 3})
+#[rquickjs::function]
+fn format_number<'js>(ctx: Ctx<'js>, n: f64, opts: Opt<Object<'js>>) -> rquickjs::Result<String> {
+    if !n.is_finite() {
+        return Ok(n.to_string());
+    }
+    let max = opt::<u8>(&opts, "maximumFractionDigits")?
+        .unwrap_or(3)
+        .min(20) as usize;
+    let min = (opt::<u8>(&opts, "minimumFractionDigits")?.unwrap_or(0) as usize).min(max);
+    let formatter = DecimalFormatter::try_new((&locale(&ctx, &opts)?).into(), Default::default())
+        .map_err(|e| intl_error(&ctx, e))?;
+    let decimal = fraction_digits(n, min, max)
+        .parse::<Decimal>()
+        .map_err(|e| intl_error(&ctx, e))?;
+    Ok(formatter.format(&decimal).to_string())
+}
+
+/// Decimal string rounded to `max` fraction digits with trailing zeros
+/// trimmed down to `min`
+fn fraction_digits(n: f64, min: usize, max: usize) -> String {
+    let mut s = format!("{n:.max$}");
+    if max > min {
+        let keep = s.len() - (max - min);
+        let trimmed = s[keep..].trim_end_matches('0').len();
+        s.truncate(keep + trimmed);
+        if s.ends_with('.') {
+            s.pop();
+        }
+    }
+    s
+}
+
+/// intl.formatDate(date, {locale, length = "medium", time = false}) -
+/// `date` is a Date or epoch milliseconds (formatted as UTC)
+#[rquickjs::function]
+fn format_date<'js>(
+    ctx: Ctx<'js>,
+    date: Coerced<f64>,
+    opts: Opt<Object<'js>>,
+) -> rquickjs::Result<String> {
+    if !date.0.is_finite() {
+        return Err(Exception::throw_range(&ctx, "Invalid time value"));
+    }
+    let ms = date.0 as i64;
+    let (y, m, d) = civil_from_days(ms.div_euclid(MS_PER_DAY));
+    let time_ms = ms.rem_euclid(MS_PER_DAY);
+    let datetime = DateTime {
+        date: Date::try_new_iso(y, m, d).map_err(|e| intl_error(&ctx, e))?,
+        time: Time::try_new(
+            (time_ms / 3_600_000) as u8,
+            (time_ms / 60_000 % 60) as u8,
+            (time_ms / 1000 % 60) as u8,
+            (time_ms % 1000) as u32 * 1_000_000,
+        )
+        .map_err(|e| intl_error(&ctx, e))?,
+    };
+    let prefs = (&locale(&ctx, &opts)?).into();
+    let length = opt::<String>(&opts, "length")?;
+    let with_time = opt::<bool>(&opts, "time")?.unwrap_or(false);
+    let formatted = match (length.as_deref(), with_time) {
+        (Some("short"), false) => DateTimeFormatter::try_new(prefs, fieldsets::YMD::short())
+            .map(|f| f.format(&datetime).to_string()),
+        (Some("long"), false) => DateTimeFormatter::try_new(prefs, fieldsets::YMD::long())
+            .map(|f| f.format(&datetime).to_string()),
+        (None | Some("medium"), false) => {
+            DateTimeFormatter::try_new(prefs, fieldsets::YMD::medium())
+                .map(|f| f.format(&datetime).to_string())
+        }
+        (Some("short"), true) => DateTimeFormatter::try_new(prefs, fieldsets::YMDT::short())
+            .map(|f| f.format(&datetime).to_string()),
+        (Some("long"), true) => DateTimeFormatter::try_new(prefs, fieldsets::YMDT::long())
+            .map(|f| f.format(&datetime).to_string()),
+        (None | Some("medium"), true) => {
+            DateTimeFormatter::try_new(prefs, fieldsets::YMDT::medium())
+                .map(|f| f.format(&datetime).to_string())
+        }
+        (Some(length), _) => {
+            return Err(Exception::throw_range(
+                &ctx,
+                &format!("Invalid length: {length} (expected short/medium/long)"),
+            ))
+        }
+    };
+    formatted.map_err(|e| intl_error(&ctx, e))
+}
+
+/// Civil (year, month, day) from days since 1970-01-01
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y as i32, m as u8, d as u8)
+}
+
+/// Collator for `{locale, sensitivity = "variant"}` (base/accent/case/variant)
+fn collator<'js>(
+    ctx: &Ctx<'js>,
+    opts: &Opt<Object<'js>>,
+) -> rquickjs::Result<CollatorBorrowed<'static>> {
+    let mut options = CollatorOptions::default();
+    options.strength = match opt::<String>(opts, "sensitivity")?.as_deref() {
+        Some("base") => Some(Strength::Primary),
+        Some("accent") => Some(Strength::Secondary),
+        None | Some("case") | Some("variant") => None,
+        Some(s) => {
+            return Err(Exception::throw_range(
+                ctx,
+                &format!("Invalid sensitivity: {s}"),
+            ))
+        }
+    };
+    Collator::try_new((&locale(ctx, opts)?).into(), options).map_err(|e| intl_error(ctx, e))
+}
+
+/// intl.compare(a, b, {locale, sensitivity}) - locale-aware comparison
+/// (-1/0/1, for use with `Array.prototype.sort`)
+#[rquickjs::function]
+fn compare<'js>(
+    ctx: Ctx<'js>,
+    a: String,
+    b: String,
+    opts: Opt<Object<'js>>,
+) -> rquickjs::Result<i32> {
+    Ok(collator(&ctx, &opts)?.compare(&a, &b) as i32)
+}
+
+/// intl.sort(strings, {locale, sensitivity}) - sorted copy (creates one
+/// collator rather than one per comparison)
+#[rquickjs::function]
+fn sort<'js>(
+    ctx: Ctx<'js>,
+    mut strings: Vec<String>,
+    opts: Opt<Object<'js>>,
+) -> rquickjs::Result<Vec<String>> {
+    let collator = collator(&ctx, &opts)?;
+    strings.sort_by(|a, b| collator.compare(a, b));
+    Ok(strings)
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
+
+use rquickjs::{
+    function::{Func, Opt},
+    Ctx, Exception, Object,
+};
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use tokio::sync::mpsc;
+
+use crate::convert::json_to_value;
+use crate::fs::FsAccess;
+use crate::util::channel_iterator;
+
+/// Parsed records buffered ahead of the consumer (limits memory when JS is
+/// slower than the parser)
+const STREAM_BUFFER: usize = 64;
+const READ_BUFFER: usize = 64 * 1024;
+
+/// Record (JSON text) or parse/read error
+type Record = Result<String, String>;
+
+/// Input layout for [`stream_json`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonStreamFormat {
+    /// Elements of a top-level array
+    Array,
+    /// Newline delimited (or concatenated) JSON values
+    Ndjson,
+    /// `Array` if the first non-whitespace byte is `[`, otherwise `Ndjson`
+    Auto,
+}
+
+/// Register `json` object with `stream(path, {format})` - files are
+/// restricted to the roots in `access` (none without) and stdin (`-`) is
+/// only readable if `stdin` is set
+pub fn register_json<'js>(
+    ctx: &Ctx<'js>,
+    access: Option<Arc<FsAccess>>,
+    stdin: bool,
+) -> anyhow::Result<()> {
+    let json = Object::new(ctx.clone())?;
+    json.set(
+        "stream",
+        Func::new(move |ctx: Ctx<'js>, path: String, opts: Opt<Object<'js>>| {
+            stream(ctx, access.as_deref(), stdin, path, opts)
+        }),
+    )?;
+    ctx.globals().set("json", json)?;
+    Ok(())
+}
+
+/// json.stream(path, {format = "auto"}) - async iterator over the elements
+/// of a top-level array or NDJSON records in `path` (`-` for stdin)
+///
+/// Parsing runs on a separate thread and only [`STREAM_BUFFER`] records
+/// are held at a time, so inputs larger than the JS heap can be processed
+/// with `for await (const record of json.stream(path))`
+fn stream<'js>(
+    ctx: Ctx<'js>,
+    access: Option<&FsAccess>,
+    stdin: bool,
+    path: String,
+    opts: Opt<Object<'js>>,
+) -> rquickjs::Result<Object<'js>> {
+    let format = match &opts.0 {
+        Some(opts) => opts.get::<_, Option<String>>("format")?,
+        None => None,
+    };
+    let format = match format.as_deref() {
+        None | Some("auto") => JsonStreamFormat::Auto,
+        Some("array") => JsonStreamFormat::Array,
+        Some("ndjson") => JsonStreamFormat::Ndjson,
+        Some(f) => {
+            return Err(Exception::throw_range(
+                &ctx,
+                &format!("Invalid format: {f} (expected auto/array/ndjson)"),
+            ))
+        }
+    };
+    let error = |e: anyhow::Error| {
+        Exception::throw_message(&ctx, &format!("JSON Stream Error: {path} [{e}]"))
+    };
+    let input: Box<dyn Read + Send> = if path == "-" {
+        if !stdin {
+            return Err(error(anyhow::anyhow!("Permission denied")));
+        }
+        Box::new(std::io::stdin())
+    } else {
+        let checked = match access {
+            Some(access) => access.check_file(&path).map_err(error)?,
+            None => return Err(error(anyhow::anyhow!("Permission denied"))),
+        };
+        Box::new(std::fs::File::open(checked).map_err(|e| error(e.into()))?)
+    };
+    channel_iterator(
+        &ctx,
+        stream_json(input, format),
+        "JSON Stream",
+        |ctx, json| {
+            json_to_value(ctx.clone(), &json)
+                .map_err(|e| Exception::throw_message(ctx, &format!("JSON Stream Error: {e}")))
+        },
+    )
+}
+
+/// Parse `input` on a background thread, sending each record as JSON text
+///
+/// Parsing stops when the receiver is dropped or closed
+pub fn stream_json(
+    input: Box<dyn Read + Send>,
+    format: JsonStreamFormat,
+) -> mpsc::Receiver<Record> {
+    let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+    std::thread::spawn(move || {
+        if let Err(e) = parse(input, format, &tx) {
+            let _ = tx.blocking_send(Err(e.to_string()));
+        }
+    });
+    rx
+}
+
+fn parse(
+    input: Box<dyn Read + Send>,
+    format: JsonStreamFormat,
+    tx: &mpsc::Sender<Record>,
+) -> anyhow::Result<()> {
+    let mut reader = BufReader::with_capacity(READ_BUFFER, input);
+    let array = match format {
+        JsonStreamFormat::Array => true,
+        JsonStreamFormat::Ndjson => false,
+        JsonStreamFormat::Auto => first_byte(&mut reader)? == Some(b'['),
+    };
+    if array {
+        let mut de = serde_json::Deserializer::from_reader(reader);
+        (&mut de).deserialize_seq(ElementSender(tx))?;
+        de.end()?;
+    } else {
+        for value in serde_json::Deserializer::from_reader(reader).into_iter::<serde_json::Value>()
+        {
+            if tx.blocking_send(Ok(value?.to_string())).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Skip leading whitespace and peek at the next byte
+fn first_byte(reader: &mut impl BufRead) -> std::io::Result<Option<u8>> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(i) => {
+                let b = buf[i];
+                reader.consume(i);
+                return Ok(Some(b));
+            }
+            None => {
+                let n = buf.len();
+                reader.consume(n);
+            }
+        }
+    }
+}
+
+/// Sends array elements as they are parsed (rather than collecting the
+/// array)
+struct ElementSender<'a>(&'a mpsc::Sender<Record>);
+
+impl<'de> Visitor<'de> for ElementSender<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            if self.0.blocking_send(Ok(value.to_string())).is_err() {
+                // Consumer gone - the resulting parse error is not reported
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use rquickjs::{
+    function::{Async, Func, Opt},
+    Ctx, Exception, Object, Value,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::convert::{json_to_value, value_to_json};
+
+/// Storage backend used by the `kv` object
+///
+/// Values are stored as JSON strings so backends only deal with text -
+/// hosts pick a backend per deployment (memory for tests/ephemeral state,
+/// files for durable local state) without changing scripts
+///
+/// Only the memory and file backends are provided - other stores (eg.
+/// sqlite or redis) need a client dependency so are left to the host to
+/// implement against this trait
+pub trait KvBackend: Send + Sync {
+    /// Get value for key (None if missing)
+    fn get(&self, key: &str) -> anyhow::Result<Option<String>>;
+    /// Set value for key
+    fn set(&self, key: &str, value: &str) -> anyhow::Result<()>;
+    /// Delete key - returns false if missing
+    fn delete(&self, key: &str) -> anyhow::Result<bool>;
+    /// Keys starting with prefix (sorted)
+    fn keys(&self, prefix: &str) -> anyhow::Result<Vec<String>>;
+}
+
+/// In-process backend (lost on exit)
+#[derive(Default)]
+pub struct MemoryKvBackend {
+    data: Mutex<BTreeMap<String, String>>,
+}
+
+impl MemoryKvBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn data(&self) -> anyhow::Result<std::sync::MutexGuard<'_, BTreeMap<String, String>>> {
+        self.data
+            .lock()
+            .map_err(|_| anyhow::anyhow!("KV Mutex Error"))
+    }
+}
+
+impl KvBackend for MemoryKvBackend {
+    fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.data()?.get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.data()?.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.data()?.remove(key).is_some())
+    }
+
+    fn keys(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .data()?
+            .range(prefix.to_string()..)
+            .map(|(k, _)| k)
+            .take_while(|k| k.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Local backend storing one file per key (`<dir>/<encoded key>.json`)
+///
+/// Writes go via a temp file + rename so readers (including other
+/// processes) never see partial values
+pub struct FileKvBackend {
+    dir: PathBuf,
+    // Temp file suffix so concurrent writes of a key don't share a file
+    writes: AtomicU64,
+}
+
+impl FileKvBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            writes: AtomicU64::new(0),
+        })
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        if key.is_empty() {
+            return Err(anyhow::anyhow!("Invalid key: empty"));
+        }
+        Ok(self.dir.join(format!("{}.json", encode_key(key))))
+    }
+}
+
+/// Percent-encode key as a portable file name
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => (b as char).to_string(),
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn decode_key(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+impl KvBackend for FileKvBackend {
+    fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        match std::fs::read_to_string(self.path(key)?) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        let tmp = path.with_extension(format!(
+            "tmp.{}.{}",
+            std::process::id(),
+            self.writes.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&tmp, value)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> anyhow::Result<bool> {
+        match std::fs::remove_file(self.path(key)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn keys(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        let mut keys = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                decode_key(name.to_str()?.strip_suffix(".json")?)
+            })
+            .filter(|key| key.starts_with(prefix))
+            .collect::<Vec<_>>();
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// Register `kv` object with async `get(key)`, `set(key, value)`,
+/// `delete(key)` and `keys(prefix = "")` (values must be JSON serialisable)
+pub fn register_kv<'js>(ctx: &Ctx<'js>, backend: Arc<dyn KvBackend>) -> anyhow::Result<()> {
+    let kv = Object::new(ctx.clone())?;
+    let b = backend.clone();
+    kv.set(
+        "get",
+        Func::new(Async(move |ctx: Ctx<'js>, key: String| {
+            let backend = b.clone();
+            async move {
+                match backend.get(&key).map_err(|e| kv_error(&ctx, e))? {
+                    Some(json) => json_to_value(ctx.clone(), &json).map_err(|e| kv_error(&ctx, e)),
+                    None => Ok(Value::new_undefined(ctx.clone())),
+                }
+            }
+        })),
+    )?;
+    let b = backend.clone();
+    kv.set(
+        "set",
+        Func::new(Async(
+            move |ctx: Ctx<'js>, key: String, value: Value<'js>| {
+                let backend = b.clone();
+                let json = value_to_json(ctx.clone(), value);
+                async move {
+                    let json = json.map_err(|e| kv_error(&ctx, e))?;
+                    backend.set(&key, &json).map_err(|e| kv_error(&ctx, e))
+                }
+            },
+        )),
+    )?;
+    let b = backend.clone();
+    kv.set(
+        "delete",
+        Func::new(Async(move |ctx: Ctx<'js>, key: String| {
+            let backend = b.clone();
+            async move { backend.delete(&key).map_err(|e| kv_error(&ctx, e)) }
+        })),
+    )?;
+    kv.set(
+        "keys",
+        Func::new(Async(move |ctx: Ctx<'js>, prefix: Opt<String>| {
+            let backend = backend.clone();
+            async move {
+                backend
+                    .keys(&prefix.0.unwrap_or_default())
+                    .map_err(|e| kv_error(&ctx, e))
+            }
+        })),
+    )?;
+    ctx.globals().set("kv", kv)?;
+    Ok(())
+}
+
+fn kv_error(ctx: &Ctx<'_>, e: anyhow::Error) -> rquickjs::Error {
+    Exception::throw_message(ctx, &format!("KV Error: {e}"))
+}
@@ -0,0 +1,83 @@
+use rquickjs::{
+    object::{Accessor, Property},
+    Ctx, Exception, IntoJs, Value,
+};
+
+/// Install global `name` lazily
+///
+/// A configurable getter stub is placed on `globalThis`; on first access it
+/// is removed and `install` is called to register the real global (eg.
+/// `register_dns`), which is then returned. Assigning to the global before
+/// it is accessed replaces the stub without installing.
+pub fn register_lazy_module<'js, F>(ctx: &Ctx<'js>, name: &str, install: F) -> anyhow::Result<()>
+where
+    F: Fn(&Ctx<'js>) -> anyhow::Result<()> + 'js,
+{
+    let get_name = name.to_string();
+    let set_name = name.to_string();
+    ctx.globals().prop(
+        name,
+        Accessor::new(
+            move |ctx: Ctx<'js>| -> rquickjs::Result<Value<'js>> {
+                ctx.globals().remove(get_name.as_str())?;
+                install(&ctx).map_err(|e| {
+                    Exception::throw_message(&ctx, &format!("Error installing {get_name}: {e}"))
+                })?;
+                ctx.globals().get(get_name.as_str())
+            },
+            move |ctx: Ctx<'js>, v: Value<'js>| -> rquickjs::Result<()> {
+                ctx.globals().prop(
+                    set_name.as_str(),
+                    Property::from(v).writable().configurable().enumerable(),
+                )
+            },
+        )
+        .configurable(),
+    )?;
+    Ok(())
+}
+
+/// Install global `name` whose value is computed by `compute` on read
+///
+/// With `cache` the first computed value replaces the accessor as a plain
+/// data property, otherwise `compute` runs on every read. Assigning to the
+/// global replaces the accessor.
+pub fn register_lazy_global<'js, F, V>(
+    ctx: &Ctx<'js>,
+    name: &str,
+    cache: bool,
+    compute: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(&Ctx<'js>) -> rquickjs::Result<V> + 'js,
+    V: IntoJs<'js>,
+{
+    let get_name = name.to_string();
+    let set_name = name.to_string();
+    ctx.globals().prop(
+        name,
+        Accessor::new(
+            move |ctx: Ctx<'js>| -> rquickjs::Result<Value<'js>> {
+                let v = compute(&ctx)?.into_js(&ctx)?;
+                if cache {
+                    ctx.globals().prop(
+                        get_name.as_str(),
+                        Property::from(v.clone())
+                            .writable()
+                            .configurable()
+                            .enumerable(),
+                    )?;
+                }
+                Ok(v)
+            },
+            move |ctx: Ctx<'js>, v: Value<'js>| -> rquickjs::Result<()> {
+                ctx.globals().prop(
+                    set_name.as_str(),
+                    Property::from(v).writable().configurable().enumerable(),
+                )
+            },
+        )
+        .configurable(),
+    )?;
+    Ok(())
+}
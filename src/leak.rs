@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use rquickjs::{async_with, AsyncContext, AsyncRuntime};
+
+use crate::run::run_script;
+use crate::watchdog::PendingFutures;
+
+/// Default number of leak check iterations
+pub const DEFAULT_LEAK_ITERATIONS: usize = 100;
+
+/// Default allowed object growth for [`LeakReport::leaked`]
+pub const DEFAULT_LEAK_TOLERANCE: i64 = 16;
+
+/// Runtime memory/resource counts after GC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    pub malloc_size: i64,
+    pub obj_count: i64,
+    pub str_count: i64,
+    pub js_func_count: i64,
+    /// Host futures still pending (tracked bridges)
+    pub pending_host_futures: usize,
+}
+
+impl MemorySnapshot {
+    /// Run GC and take snapshot
+    pub async fn take(rt: &AsyncRuntime) -> Self {
+        rt.run_gc().await;
+        let usage = rt.memory_usage().await;
+        Self {
+            malloc_size: usage.malloc_size,
+            obj_count: usage.obj_count,
+            str_count: usage.str_count,
+            js_func_count: usage.js_func_count,
+            pending_host_futures: PendingFutures::global().len(),
+        }
+    }
+}
+
+/// Memory retained by one script/module evaluation - snapshots (after GC)
+/// taken before and after it ran in a shared context, so growth can be
+/// attributed to individual `--script`/`--module` entries
+#[derive(Debug, Clone)]
+pub struct ExecutionStats {
+    pub name: String,
+    pub elapsed: Duration,
+    pub before: MemorySnapshot,
+    pub after: MemorySnapshot,
+}
+
+impl ExecutionStats {
+    /// Bytes allocated (and still reachable) by the evaluation
+    pub fn malloc_delta(&self) -> i64 {
+        self.after.malloc_size - self.before.malloc_size
+    }
+
+    /// Objects retained by the evaluation
+    pub fn obj_delta(&self) -> i64 {
+        self.after.obj_count - self.before.obj_count
+    }
+
+    /// Strings retained by the evaluation
+    pub fn str_delta(&self) -> i64 {
+        self.after.str_count - self.before.str_count
+    }
+}
+
+impl std::fmt::Display for ExecutionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {:+} bytes, {:+} objects, {:+} strings ({:.1}ms)",
+            self.name,
+            self.malloc_delta(),
+            self.obj_delta(),
+            self.str_delta(),
+            self.elapsed.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// Result of [`leak_check`]
+#[derive(Debug, Clone)]
+pub struct LeakReport {
+    pub iterations: usize,
+    pub baseline: MemorySnapshot,
+    pub after: MemorySnapshot,
+}
+
+impl LeakReport {
+    /// Objects retained per iteration
+    pub fn objects_per_iteration(&self) -> f64 {
+        (self.after.obj_count - self.baseline.obj_count) as f64 / self.iterations.max(1) as f64
+    }
+
+    /// Check if memory/resources didn't return to baseline - objects/host
+    /// futures must not grow with iterations (small one-off growth from
+    /// caches etc is allowed by `tolerance` objects)
+    pub fn leaked(&self, tolerance: i64) -> bool {
+        self.after.obj_count - self.baseline.obj_count > tolerance
+            || self.after.js_func_count > self.baseline.js_func_count + tolerance
+            || self.after.pending_host_futures > self.baseline.pending_host_futures
+    }
+}
+
+impl std::fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (b, a) = (&self.baseline, &self.after);
+        writeln!(f, "iterations:   {}", self.iterations)?;
+        writeln!(f, "malloc_size:  {} -> {}", b.malloc_size, a.malloc_size)?;
+        writeln!(f, "obj_count:    {} -> {}", b.obj_count, a.obj_count)?;
+        writeln!(f, "str_count:    {} -> {}", b.str_count, a.str_count)?;
+        writeln!(
+            f,
+            "js_func:      {} -> {}",
+            b.js_func_count, a.js_func_count
+        )?;
+        write!(
+            f,
+            "host_futures: {} -> {}",
+            b.pending_host_futures, a.pending_host_futures
+        )
+    }
+}
+
+/// Run `script` repeatedly (driving the runtime to idle and forcing GC)
+/// and compare memory usage with the baseline after a warm-up run
+pub async fn leak_check(
+    rt: &AsyncRuntime,
+    ctx: &AsyncContext,
+    script: &str,
+    iterations: usize,
+) -> anyhow::Result<LeakReport> {
+    let run = || async {
+        async_with!(ctx => |ctx| {
+            run_script(ctx.clone(), script.to_string()).await?;
+            Ok::<(), anyhow::Error>(())
+        })
+        .await?;
+        rt.idle().await;
+        Ok::<(), anyhow::Error>(())
+    };
+    // Warm up (atoms, shapes, lazy globals etc)
+    run().await?;
+    let baseline = MemorySnapshot::take(rt).await;
+    for _ in 0..iterations {
+        run().await?;
+    }
+    Ok(LeakReport {
+        iterations,
+        baseline,
+        after: MemorySnapshot::take(rt).await,
+    })
+}
@@ -1,2 +1,84 @@
+#[cfg(feature = "async")]
+pub mod abort;
+#[cfg(feature = "async")]
+pub mod breaker;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+#[cfg(feature = "async")]
+pub mod cache;
+pub mod class;
+#[cfg(feature = "async")]
+pub mod combinators;
+pub mod compat;
+#[cfg(feature = "sync")]
+pub mod console;
+#[cfg(feature = "sync")]
+pub mod convert;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+#[cfg(feature = "sync")]
+pub mod dedupe;
+#[cfg(feature = "dns")]
+pub mod dns;
+#[cfg(feature = "sync")]
+pub mod engine;
+#[cfg(feature = "async")]
+pub mod env;
+pub mod error;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod event_loop;
+#[cfg(feature = "sync")]
+pub mod events;
+#[cfg(feature = "async")]
+pub mod executor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "async")]
+pub mod fs;
+pub mod history;
+pub mod host;
+#[cfg(feature = "intl")]
+pub mod intl;
+#[cfg(feature = "async")]
+pub mod json;
+#[cfg(feature = "async")]
+pub mod kv;
+pub mod lazy;
+#[cfg(feature = "async")]
+pub mod leak;
+pub mod loader;
+#[cfg(feature = "async")]
+pub mod lock;
+pub mod metrics;
+#[cfg(feature = "async")]
+pub mod native;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "os")]
+pub mod os;
+#[cfg(all(feature = "plugin", not(target_arch = "wasm32")))]
+pub mod plugin;
+#[cfg(feature = "sync")]
+pub mod policy;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "sync")]
+pub mod query;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod repl;
+#[cfg(feature = "sync")]
+pub mod rules;
 pub mod run;
+pub mod source;
+#[cfg(feature = "sync")]
+pub mod sql;
+#[cfg(feature = "text")]
+pub mod text;
+#[cfg(feature = "async")]
+pub mod timers;
+pub mod trace;
+#[cfg(feature = "async")]
 pub mod util;
+pub mod watchdog;
+#[cfg(feature = "sync")]
+pub mod web;
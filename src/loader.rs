@@ -0,0 +1,522 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "async")]
+use rquickjs::AsyncRuntime;
+use rquickjs::{
+    loader::{BuiltinLoader, BuiltinResolver, Loader, Resolver},
+    Ctx, Module, Runtime,
+};
+use serde::Deserialize;
+
+#[cfg(feature = "async")]
+use crate::native::NativeModuleSet;
+use crate::source::register_source;
+
+/// Default module file extensions (in probe order)
+pub const MODULE_EXTENSIONS: &[&str] = &["js", "mjs"];
+
+/// Resolve module imports to files on disk
+///
+/// Relative imports (`./x`, `../x`) are resolved from the importing module,
+/// bare imports and imports from inline modules (eg. `main.mjs`) from
+/// `root`. If the path doesn't exist each extension is tried in turn
+/// (`./helper` -> `./helper.js`, `./helper.mjs`).
+#[derive(Debug, Clone)]
+pub struct FileResolver {
+    root: PathBuf,
+    extensions: Vec<String>,
+}
+
+impl FileResolver {
+    fn dir(&self, base: &str) -> PathBuf {
+        let base = Path::new(base);
+        let dir = base.parent().unwrap_or(Path::new(""));
+        if base.is_absolute() {
+            dir.to_path_buf()
+        } else {
+            self.root.join(dir)
+        }
+    }
+
+    fn probe(&self, path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+        self.extensions.iter().find_map(|ext| {
+            let mut file = path.as_os_str().to_owned();
+            file.push(format!(".{ext}"));
+            let file = PathBuf::from(file);
+            file.is_file().then_some(file)
+        })
+    }
+}
+
+impl Resolver for FileResolver {
+    fn resolve<'js>(
+        &mut self,
+        _ctx: &Ctx<'js>,
+        base: &str,
+        name: &str,
+    ) -> rquickjs::Result<String> {
+        // Modules loaded over http(s) can't import local files
+        if is_url(base) {
+            return Err(rquickjs::Error::new_resolving(base, name));
+        }
+        let path = if name.starts_with("./") || name.starts_with("../") {
+            self.dir(base).join(name)
+        } else {
+            self.root.join(name)
+        };
+        self.probe(&path)
+            .and_then(|path| path.canonicalize().ok())
+            .map(|path| path.to_string_lossy().into_owned())
+            .ok_or_else(|| rquickjs::Error::new_resolving(base, name))
+    }
+}
+
+/// Import map (`{"imports": {"utils": "./lib/utils.js", "lib/": "./lib/"}}`)
+///
+/// Keys are bare specifiers - keys ending in `/` map any specifier with that
+/// prefix (longest prefix wins). Path values (`./`, `../`, `/`) are relative
+/// to the map file, other values name embedded/native modules.
+#[derive(Debug, Clone, Default)]
+pub struct ImportMap {
+    /// (specifier, target) sorted by descending specifier length
+    imports: Vec<(String, String)>,
+}
+
+#[derive(Deserialize)]
+struct ImportMapJson {
+    #[serde(default)]
+    imports: std::collections::HashMap<String, String>,
+}
+
+fn is_path(s: &str) -> bool {
+    s.starts_with("./") || s.starts_with("../") || s.starts_with('/')
+}
+
+impl ImportMap {
+    /// Parse import map JSON (paths relative to `base`)
+    pub fn from_json(json: &str, base: &Path) -> anyhow::Result<Self> {
+        let map: ImportMapJson =
+            serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Invalid Import Map: {e}"))?;
+        let mut imports = map
+            .imports
+            .into_iter()
+            .map(|(k, v)| {
+                if k.ends_with('/') != v.ends_with('/') {
+                    return Err(anyhow::anyhow!(
+                        "Invalid Import Map: {k} -> {v} (prefix must map to prefix)"
+                    ));
+                }
+                let v = if is_path(&v) {
+                    let mut target = base.join(&v).to_string_lossy().into_owned();
+                    // join drops trailing separator of prefix targets
+                    if v.ends_with('/') && !target.ends_with('/') {
+                        target.push('/');
+                    }
+                    target
+                } else {
+                    v
+                };
+                Ok((k, v))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        imports.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        Ok(Self { imports })
+    }
+
+    /// Read import map file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Import Map: {} [{e}]", path.display()))?;
+        let base = path
+            .canonicalize()?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Self::from_json(&json, &base)
+    }
+
+    /// Map bare specifier (None if not mapped)
+    pub fn map(&self, specifier: &str) -> Option<String> {
+        if is_path(specifier) {
+            return None;
+        }
+        self.imports.iter().find_map(|(k, v)| {
+            if k == specifier {
+                Some(v.clone())
+            } else if k.ends_with('/') {
+                specifier
+                    .strip_prefix(k.as_str())
+                    .map(|rest| format!("{v}{rest}"))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Resolver applying [`ImportMap`] before delegating to `inner`
+#[derive(Debug, Clone)]
+pub struct ImportMapResolver<R> {
+    map: ImportMap,
+    inner: R,
+}
+
+impl<R> ImportMapResolver<R> {
+    pub fn new(map: ImportMap, inner: R) -> Self {
+        Self { map, inner }
+    }
+}
+
+impl<R: Resolver> Resolver for ImportMapResolver<R> {
+    fn resolve<'js>(&mut self, ctx: &Ctx<'js>, base: &str, name: &str) -> rquickjs::Result<String> {
+        match self.map.map(name) {
+            Some(mapped) => self.inner.resolve(ctx, base, &mapped),
+            None => self.inner.resolve(ctx, base, name),
+        }
+    }
+}
+
+/// Default HTTP module cache (`~/.cache/rquickjs/modules`)
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+pub fn default_http_cache() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache/rquickjs/modules"))
+}
+
+fn is_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+/// `url` may be imported (`https://`, or `http://` if `allow_http`)
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+fn allowed_scheme(url: &reqwest::Url, allow_http: bool) -> bool {
+    url.scheme() == "https" || (allow_http && url.scheme() == "http")
+}
+
+/// Resolve `https://` imports (and relative imports from URL modules) -
+/// `http://` only if allowed by the loader
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone, Default)]
+pub struct HttpResolver {
+    enabled: bool,
+    allow_http: bool,
+}
+
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+impl Resolver for HttpResolver {
+    fn resolve<'js>(
+        &mut self,
+        _ctx: &Ctx<'js>,
+        base: &str,
+        name: &str,
+    ) -> rquickjs::Result<String> {
+        let url = if !self.enabled {
+            None
+        } else if is_url(name) {
+            reqwest::Url::parse(name).ok()
+        } else if is_url(base) && is_path(name) {
+            reqwest::Url::parse(base).and_then(|b| b.join(name)).ok()
+        } else {
+            None
+        };
+        url.filter(|url| allowed_scheme(url, self.allow_http))
+            .map(String::from)
+            .ok_or_else(|| rquickjs::Error::new_resolving(base, name))
+    }
+}
+
+/// Load `http(s)://` modules via a content-addressed on-disk cache
+///
+/// Sources are stored as `objects/<sha256>` with `urls/<sha256(url)>`
+/// pointing to the latest content - in offline mode only the cache is used,
+/// otherwise the cache is the fallback if the fetch fails.
+///
+/// Only `https://` imports are allowed unless [`HttpLoader::allow_http`] is
+/// set, and URL modules can't import local files.
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub struct HttpLoader {
+    cache_dir: Option<PathBuf>,
+    offline: bool,
+    allow_http: bool,
+}
+
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+impl HttpLoader {
+    /// Cache modules in `cache_dir`
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: Some(cache_dir.into()),
+            offline: false,
+            allow_http: false,
+        }
+    }
+
+    /// Only load modules from cache
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Also allow insecure `http://` imports
+    pub fn allow_http(mut self, allow: bool) -> Self {
+        self.allow_http = allow;
+        self
+    }
+
+    fn disabled() -> Self {
+        Self {
+            cache_dir: None,
+            offline: false,
+            allow_http: false,
+        }
+    }
+
+    fn hash(data: &[u8]) -> String {
+        use sha2::Digest;
+        sha2::Sha256::digest(data)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn cached(dir: &Path, url: &str) -> anyhow::Result<Vec<u8>> {
+        let hash = std::fs::read_to_string(dir.join("urls").join(Self::hash(url.as_bytes())))?;
+        let source = std::fs::read(dir.join("objects").join(hash.trim()))?;
+        // Detect corrupted/modified cache entries
+        if Self::hash(&source) != hash.trim() {
+            return Err(anyhow::anyhow!("Cache Entry Invalid: {url}"));
+        }
+        Ok(source)
+    }
+
+    fn store(dir: &Path, url: &str, source: &[u8]) -> anyhow::Result<()> {
+        let hash = Self::hash(source);
+        std::fs::create_dir_all(dir.join("objects"))?;
+        std::fs::create_dir_all(dir.join("urls"))?;
+        std::fs::write(dir.join("objects").join(&hash), source)?;
+        std::fs::write(dir.join("urls").join(Self::hash(url.as_bytes())), hash)?;
+        Ok(())
+    }
+
+    /// Fetch `url` - redirects are followed only to allowed schemes so an
+    /// `https://` import can't be downgraded to `http://`
+    fn fetch(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let allow_http = self.allow_http;
+        let policy = reqwest::redirect::Policy::custom(move |attempt| {
+            if allowed_scheme(attempt.url(), allow_http) {
+                reqwest::redirect::Policy::default().redirect(attempt)
+            } else {
+                let error = format!("Insecure Redirect: {}", attempt.url());
+                attempt.error(error)
+            }
+        });
+        // reqwest::blocking can't run on a tokio worker thread
+        std::thread::scope(|s| {
+            s.spawn(|| -> anyhow::Result<Vec<u8>> {
+                let client = reqwest::blocking::Client::builder()
+                    .redirect(policy)
+                    .build()?;
+                let response = client.get(url).send()?.error_for_status()?;
+                Ok(response.bytes()?.to_vec())
+            })
+            .join()
+            .map_err(|_| anyhow::anyhow!("Fetch Thread Panicked"))?
+        })
+    }
+
+    fn source(&self, dir: &Path, url: &str) -> anyhow::Result<Vec<u8>> {
+        if self.offline {
+            return Self::cached(dir, url).map_err(|e| anyhow::anyhow!("Offline: {url} [{e}]"));
+        }
+        match self.fetch(url) {
+            Ok(source) => {
+                Self::store(dir, url, &source)?;
+                Ok(source)
+            }
+            Err(e) => Self::cached(dir, url).map_err(|_| e),
+        }
+    }
+}
+
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+impl Loader for HttpLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, path: &str) -> rquickjs::Result<Module<'js>> {
+        let Some(dir) = self.cache_dir.as_ref().filter(|_| is_url(path)) else {
+            return Err(rquickjs::Error::new_loading(path));
+        };
+        let source = self.source(dir, path).map_err(|e| {
+            rquickjs::Error::new_loading_message(path, format!("Import Error: {e}"))
+        })?;
+        register_source(ctx, path, &String::from_utf8_lossy(&source));
+        Module::declare(ctx.clone(), path, source)
+    }
+}
+
+/// Load module files with one of `extensions` (as rquickjs `ScriptLoader`),
+/// recording each source for error snippets (see [`register_source`])
+#[derive(Debug, Clone)]
+struct SourceLoader {
+    extensions: Vec<String>,
+}
+
+impl Loader for SourceLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, path: &str) -> rquickjs::Result<Module<'js>> {
+        let ext = Path::new(path).extension().and_then(|e| e.to_str());
+        if !ext.is_some_and(|ext| self.extensions.iter().any(|e| e == ext)) {
+            return Err(rquickjs::Error::new_loading(path));
+        }
+        let source = std::fs::read(path)?;
+        register_source(ctx, path, &String::from_utf8_lossy(&source));
+        Module::declare(ctx.clone(), path, source)
+    }
+}
+
+/// Filesystem ES module loader setup
+///
+/// ```ignore
+/// FileLoader::new("./lib")
+///     .with_modules(&modules)
+///     .install_async(&rt)
+///     .await;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileLoader {
+    root: PathBuf,
+    extensions: Vec<String>,
+    builtins: Vec<String>,
+    embedded: Vec<(String, String)>,
+    import_map: ImportMap,
+    #[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+    http: Option<HttpLoader>,
+}
+
+/// Resolver/loader chain installed by [`FileLoader`]
+#[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+type Parts = (
+    ImportMapResolver<(BuiltinResolver, HttpResolver, FileResolver)>,
+    (BuiltinLoader, HttpLoader, SourceLoader),
+);
+#[cfg(not(all(feature = "http_imports", not(target_arch = "wasm32"))))]
+type Parts = (
+    ImportMapResolver<(BuiltinResolver, FileResolver)>,
+    (BuiltinLoader, SourceLoader),
+);
+
+impl FileLoader {
+    /// Load modules from `root` with default extensions
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            extensions: MODULE_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            builtins: Vec::new(),
+            embedded: Vec::new(),
+            import_map: ImportMap::default(),
+            #[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+            http: None,
+        }
+    }
+
+    /// Replace probed file extensions (without leading `.`)
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Allow importing declared (native) module `name` by name
+    pub fn with_builtin(mut self, name: &str) -> Self {
+        self.builtins.push(name.to_string());
+        self
+    }
+
+    /// Allow importing modules in `modules` by name
+    #[cfg(feature = "async")]
+    pub fn with_modules(mut self, modules: &NativeModuleSet) -> Self {
+        self.builtins.extend(modules.names().map(str::to_string));
+        self
+    }
+
+    /// Embedded module source importable by `name`
+    pub fn with_embedded(mut self, name: &str, source: &str) -> Self {
+        self.embedded.push((name.to_string(), source.to_string()));
+        self
+    }
+
+    /// Map bare specifiers (see [`ImportMap`])
+    pub fn with_import_map(mut self, map: ImportMap) -> Self {
+        self.import_map = map;
+        self
+    }
+
+    /// Allow `https://` imports (see [`HttpLoader`])
+    #[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+    pub fn with_http(mut self, http: HttpLoader) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    fn parts(&self) -> Parts {
+        let builtins = self
+            .builtins
+            .iter()
+            .chain(self.embedded.iter().map(|(name, _)| name))
+            .fold(BuiltinResolver::default(), |r, name| r.with_module(name));
+        let files = FileResolver {
+            root: self.root.clone(),
+            extensions: self.extensions.clone(),
+        };
+        // `js` is always loadable (as with `ScriptLoader`)
+        let loader = SourceLoader {
+            extensions: std::iter::once("js".to_string())
+                .chain(self.extensions.iter().cloned())
+                .collect(),
+        };
+        let embedded = self
+            .embedded
+            .iter()
+            .fold(BuiltinLoader::default(), |l, (name, source)| {
+                l.with_module(name, source.as_str())
+            });
+        #[cfg(all(feature = "http_imports", not(target_arch = "wasm32")))]
+        let parts = {
+            let http = HttpResolver {
+                enabled: self.http.is_some(),
+                allow_http: self.http.as_ref().is_some_and(|h| h.allow_http),
+            };
+            (
+                ImportMapResolver::new(self.import_map.clone(), (builtins, http, files)),
+                (
+                    embedded,
+                    self.http.clone().unwrap_or_else(HttpLoader::disabled),
+                    loader,
+                ),
+            )
+        };
+        #[cfg(not(all(feature = "http_imports", not(target_arch = "wasm32"))))]
+        let parts = (
+            ImportMapResolver::new(self.import_map.clone(), (builtins, files)),
+            (embedded, loader),
+        );
+        parts
+    }
+
+    /// Set resolver/loader on runtime
+    pub fn install(&self, rt: &Runtime) {
+        let (resolver, loader) = self.parts();
+        rt.set_loader(resolver, loader);
+    }
+
+    /// Set resolver/loader on async runtime
+    #[cfg(feature = "async")]
+    pub async fn install_async(&self, rt: &AsyncRuntime) {
+        let (resolver, loader) = self.parts();
+        rt.set_loader(resolver, loader).await;
+    }
+}
@@ -0,0 +1,323 @@
+use rquickjs::{
+    function::{Async, Func, Opt},
+    promise::MaybePromise,
+    Ctx, Exception, Function, Object, Value,
+};
+use std::future::{poll_fn, Future};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::compat::{sleep, Instant};
+use crate::timers::delay;
+
+/// Interval between attempts to acquire a held lock
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long `withLock` waits for a held lock by default
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Shortest interval between lease renewals
+const MIN_RENEW_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Next owner id suffix (process wide, so contexts sharing a backend never
+/// reuse an owner id)
+static NEXT_OWNER: AtomicU64 = AtomicU64::new(0);
+
+/// Lock backend used by `lock.withLock`
+///
+/// Locks are held by an owner id for at most `ttl`, after which other
+/// owners may take them over (protects against crashed holders)
+///
+/// Only [`FileLockBackend`] is provided - there is no Redis client in the
+/// dependency tree, so a Redis backend (`SET name owner NX PX ttl`) is left
+/// to the host
+pub trait LockBackend: Send + Sync {
+    /// Try to acquire named lock - returns false if held by another owner
+    fn try_acquire(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool>;
+    /// Extend a held lock to expire `ttl` from now - returns false if the
+    /// lock is no longer held by owner
+    fn renew(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool>;
+    /// Release named lock if still held by owner
+    fn release(&self, name: &str, owner: &str) -> anyhow::Result<()>;
+}
+
+/// Local lock backend using lock files (`<dir>/<name>.lock`)
+///
+/// Lock files contain `<owner> <expiry ms>` and are shared between
+/// processes on the same host (or on a shared filesystem)
+pub struct FileLockBackend {
+    dir: PathBuf,
+}
+
+impl FileLockBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(anyhow::anyhow!("Invalid lock name: {name}"));
+        }
+        Ok(self.dir.join(format!("{name}.lock")))
+    }
+
+    /// Private file next to the lock at `path` (`<name>.lock.<owner>.<kind>`)
+    fn owner_path(path: &Path, owner: &str, kind: &str) -> PathBuf {
+        let suffix = owner.replace([':', '/', '\\'], "-");
+        path.with_extension(format!("lock.{suffix}.{kind}"))
+    }
+
+    fn read(&self, path: &Path) -> Option<(String, u128)> {
+        let s = std::fs::read_to_string(path).ok()?;
+        let (owner, expiry) = s.trim().rsplit_once(' ')?;
+        Some((owner.to_string(), expiry.parse().ok()?))
+    }
+
+    /// Link `tmp` (our lock contents) to the lock at `path`, taking over an
+    /// expired lock
+    fn link(&self, path: &Path, tmp: &Path, owner: &str) -> anyhow::Result<bool> {
+        match std::fs::hard_link(tmp, path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let Some(stale) = self.read(path) else {
+                    return Ok(false);
+                };
+                if stale.1 > now_ms() {
+                    return Ok(false);
+                }
+                // Move the expired lock aside (only one contender can) and
+                // check it is the one we read - otherwise another owner took
+                // it over in between and it is put back
+                let aside = Self::owner_path(path, owner, "stale");
+                if std::fs::rename(path, &aside).is_err() {
+                    return Ok(false);
+                }
+                if self.read(&aside).as_ref() != Some(&stale) {
+                    let _ = std::fs::hard_link(&aside, path);
+                    let _ = std::fs::remove_file(&aside);
+                    return Ok(false);
+                }
+                let _ = std::fs::remove_file(&aside);
+                match std::fs::hard_link(tmp, path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+        // Re-read to confirm we hold the lock
+        Ok(self.read(path).is_some_and(|(o, _)| o == owner))
+    }
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+impl LockBackend for FileLockBackend {
+    fn try_acquire(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool> {
+        let path = self.path(name)?;
+        // Lock contents are written to a private temp file and hard linked
+        // into place (atomic and fails if the lock exists) so contenders never
+        // see a partially written lock
+        let tmp = Self::owner_path(&path, owner, "tmp");
+        std::fs::write(&tmp, format!("{} {}", owner, now_ms() + ttl.as_millis()))?;
+        let acquired = self.link(&path, &tmp, owner);
+        let _ = std::fs::remove_file(&tmp);
+        acquired
+    }
+
+    fn renew(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool> {
+        let path = self.path(name)?;
+        // An unexpired lock can't be taken over, so replacing it (rename is
+        // atomic) after checking it is ours is safe
+        match self.read(&path) {
+            Some((o, expiry)) if o == owner && expiry > now_ms() => {}
+            _ => return Ok(false),
+        }
+        let tmp = Self::owner_path(&path, owner, "tmp");
+        std::fs::write(&tmp, format!("{} {}", owner, now_ms() + ttl.as_millis()))?;
+        if let Err(e) = std::fs::rename(&tmp, &path) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(true)
+    }
+
+    fn release(&self, name: &str, owner: &str) -> anyhow::Result<()> {
+        let path = self.path(name)?;
+        if !self.read(&path).is_some_and(|(o, _)| o == owner) {
+            return Ok(());
+        }
+        // Move the lock aside and check it is still ours before removing it
+        // - it may have expired and been taken over since it was read, in
+        // which case it is put back
+        let aside = Self::owner_path(&path, owner, "release");
+        match std::fs::rename(&path, &aside) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        if !self.read(&aside).is_some_and(|(o, _)| o == owner) {
+            let _ = std::fs::hard_link(&aside, &path);
+        }
+        std::fs::remove_file(&aside)?;
+        Ok(())
+    }
+}
+
+/// Register `lock` object with `withLock(name, ttl_ms, async fn, opts?)`
+///
+/// Waits for the lock (up to `opts.timeoutMs`, default 60s, then throws
+/// "Lock Timeout") and calls `fn` while holding it. The lease is renewed
+/// every `ttl_ms / 3` while `fn` is pending - if a renewal finds the lock
+/// taken over (eg. the process stalled for longer than `ttl_ms`) the call
+/// throws "Lock Lost" without waiting for `fn` to settle
+pub fn register_lock(ctx: &Ctx<'_>, backend: Arc<dyn LockBackend>) -> anyhow::Result<()> {
+    let lock = Object::new(ctx.clone())?;
+    lock.set(
+        "withLock",
+        Func::new(Async(
+            move |ctx, name: String, ttl: u64, f: Function, opts: Opt<Object>| {
+                let backend = backend.clone();
+                let owner = format!(
+                    "{}:{}",
+                    std::process::id(),
+                    NEXT_OWNER.fetch_add(1, Ordering::Relaxed)
+                );
+                async move {
+                    let timeout = match opts.0 {
+                        Some(opts) => opts
+                            .get::<_, Option<f64>>("timeoutMs")?
+                            .map_or(DEFAULT_ACQUIRE_TIMEOUT, |ms| delay(Some(ms))),
+                        None => DEFAULT_ACQUIRE_TIMEOUT,
+                    };
+                    let ttl = Duration::from_millis(ttl);
+                    let throw = |e: anyhow::Error| {
+                        Exception::throw_message(&ctx, &format!("Lock Error: {name} [{e}]"))
+                    };
+                    let started = Instant::now();
+                    while !backend.try_acquire(&name, &owner, ttl).map_err(throw)? {
+                        let waited = started.elapsed();
+                        if waited >= timeout {
+                            return Err(Exception::throw_message(
+                                &ctx,
+                                &format!("Lock Timeout: {name}"),
+                            ));
+                        }
+                        sleep(RETRY_INTERVAL.min(timeout - waited)).await;
+                    }
+                    let mut task = pin!(async {
+                        match f.call::<_, MaybePromise>(()) {
+                            Ok(p) => p.into_future::<Value>().await,
+                            Err(e) => Err(e),
+                        }
+                    });
+                    let mut lease = pin!(renew_lease(&*backend, &name, &owner, ttl));
+                    let result = poll_fn(|cx| match task.as_mut().poll(cx) {
+                        Poll::Ready(result) => Poll::Ready(Ok(result)),
+                        Poll::Pending => lease.as_mut().poll(cx).map(Err),
+                    })
+                    .await;
+                    match result {
+                        Ok(result) => {
+                            backend.release(&name, &owner).map_err(throw)?;
+                            result
+                        }
+                        Err(Some(e)) => Err(throw(e)),
+                        Err(None) => Err(Exception::throw_message(
+                            &ctx,
+                            &format!("Lock Lost: {name}"),
+                        )),
+                    }
+                }
+            },
+        )),
+    )?;
+    ctx.globals().set("lock", lock)?;
+    Ok(())
+}
+
+/// Renew the lease on a held lock every `ttl / 3` - completes (with the
+/// backend error, if any) once the lock has been lost
+async fn renew_lease(
+    backend: &dyn LockBackend,
+    name: &str,
+    owner: &str,
+    ttl: Duration,
+) -> Option<anyhow::Error> {
+    let interval = (ttl / 3).max(MIN_RENEW_INTERVAL);
+    loop {
+        sleep(interval).await;
+        match backend.renew(name, owner, ttl) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(name: &str) -> FileLockBackend {
+        let dir = std::env::temp_dir().join(format!("rquickjs-lock-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        FileLockBackend::new(dir).unwrap()
+    }
+
+    #[test]
+    fn held_locks_exclude_other_owners() {
+        let b = backend("exclude");
+        let ttl = Duration::from_secs(60);
+        assert!(b.try_acquire("job", "a", ttl).unwrap());
+        assert!(!b.try_acquire("job", "b", ttl).unwrap());
+        // Releasing someone else's lock is a no-op
+        b.release("job", "b").unwrap();
+        assert!(!b.try_acquire("job", "b", ttl).unwrap());
+        b.release("job", "a").unwrap();
+        assert!(b.try_acquire("job", "b", ttl).unwrap());
+    }
+
+    #[test]
+    fn expired_locks_are_taken_over() {
+        let b = backend("expire");
+        assert!(b.try_acquire("job", "a", Duration::ZERO).unwrap());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(b.try_acquire("job", "b", Duration::from_secs(60)).unwrap());
+        // The previous owner can neither renew nor release it
+        assert!(!b.renew("job", "a", Duration::from_secs(60)).unwrap());
+        b.release("job", "a").unwrap();
+        assert!(!b.try_acquire("job", "c", Duration::from_secs(60)).unwrap());
+    }
+
+    #[test]
+    fn renewed_locks_stay_held() {
+        let b = backend("renew");
+        assert!(b
+            .try_acquire("job", "a", Duration::from_millis(50))
+            .unwrap());
+        assert!(b.renew("job", "a", Duration::from_secs(60)).unwrap());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!b.try_acquire("job", "b", Duration::from_secs(60)).unwrap());
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let b = backend("names");
+        for name in ["", "../job", ".hidden"] {
+            assert!(b.try_acquire(name, "a", Duration::from_secs(1)).is_err());
+        }
+    }
+}
@@ -1,7 +1,16 @@
+use std::time::{Duration, Instant};
+
 use argh::FromArgs;
 
 use rquickjs::{async_with, AsyncContext, AsyncRuntime};
-use rquickjs_test::run::{call_fn, get_script, repl_rustyline, run_module, run_script};
+use rquickjs_test::error::{report_error, ErrorFormat};
+use rquickjs_test::leak::{leak_check, ExecutionStats, MemorySnapshot, DEFAULT_LEAK_TOLERANCE};
+use rquickjs_test::loader::FileLoader;
+use rquickjs_test::repl::{default_rc_files, ReplOptions};
+use rquickjs_test::run::{
+    call_fn, get_script, install_timeout_handler, run_module, run_script, run_script_with_timeout,
+    PREV,
+};
 use rquickjs_test::util::{json_to_value, register_fns, register_oneshot, value_to_json};
 
 #[derive(FromArgs)]
@@ -22,21 +31,87 @@ struct CliArgs {
     #[argh(option)]
     /// call args
     arg: Vec<String>,
+    #[argh(switch)]
+    /// don't load ~/.rquickjsrc.js or ./.rquickjsrc.js in REPL
+    no_rc: bool,
+    #[argh(option, default = "ErrorFormat::Text")]
+    /// error output format (text|json)
+    errors: ErrorFormat,
+    #[argh(option)]
+    /// run each script N times checking memory returns to baseline (not
+    /// supported with --module)
+    leak_check: Option<usize>,
+    #[argh(option, default = "std::path::PathBuf::from(\".\")")]
+    /// directory imports are resolved from (default .)
+    module_root: std::path::PathBuf,
+    #[argh(option)]
+    /// abort scripts/REPL commands running longer than this (secs)
+    timeout: Option<u64>,
+    #[argh(switch)]
+    /// report memory/objects retained by each script/module (runs GC
+    /// between them)
+    stats: bool,
 }
 
 /// Basic CLI test
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() {
     let args: CliArgs = argh::from_env();
+    let format = args.errors;
+    if let Err(e) = run(args).await {
+        std::process::exit(report_error(&e, format));
+    }
+}
 
+async fn run(args: CliArgs) -> anyhow::Result<()> {
     // Check that we have something to do
     if args.script.is_empty() && args.module.is_empty() && args.call.is_empty() && !args.repl {
         let name = std::env::args().next().unwrap_or("-".into());
         CliArgs::from_args(&[&name], &["--help"]).map_err(|exit| anyhow::anyhow!(exit.output))?;
     }
 
+    let repl_opts = ReplOptions {
+        rc_files: if args.no_rc {
+            vec![]
+        } else {
+            default_rc_files()
+        },
+        timeout: args.timeout.map(Duration::from_secs),
+        ..Default::default()
+    };
+
     let rt = AsyncRuntime::new()?;
+    FileLoader::new(&args.module_root).install_async(&rt).await;
     let ctx = AsyncContext::full(&rt).await?;
+    if args.timeout.is_some() {
+        install_timeout_handler(&rt, &ctx).await?;
+    }
+
+    // Leak check mode - run scripts repeatedly and compare with baseline
+    if let Some(iterations) = args.leak_check {
+        // Modules are cached by name so repeated runs wouldn't re-evaluate
+        if !args.module.is_empty() {
+            return Err(anyhow::anyhow!(
+                "--leak-check only supports --script (modules are evaluated once)"
+            ));
+        }
+        async_with!(ctx => |ctx| {
+            register_fns(&ctx)?;
+            Ok::<(),anyhow::Error>(())
+        })
+        .await?;
+        for script in &args.script {
+            let report = leak_check(&rt, &ctx, &get_script(script)?, iterations).await?;
+            println!("[+] Leak Check: {script}\n{report}");
+            if report.leaked(DEFAULT_LEAK_TOLERANCE) {
+                return Err(anyhow::anyhow!(
+                    "Leak detected: {script} ({:.2} objects/iteration)",
+                    report.objects_per_iteration()
+                ));
+            }
+        }
+        return Ok(());
+    }
 
     let (oneshot_tx, oneshot_rx) = tokio::sync::oneshot::channel::<String>();
 
@@ -52,20 +127,59 @@ async fn main() -> anyhow::Result<()> {
     async_with!(ctx => |ctx| {
         register_fns(&ctx)?;
         register_oneshot(ctx.clone(), oneshot_tx, "resolve")?;
+        Ok::<(),anyhow::Error>(())
+    })
+    .await?;
 
-        // Run modules
-        for module in args.module {
-            run_module(ctx.clone(),get_script(&module)?).await?;
-        }
-
-        // Run scripts
-        for script in args.script {
-            run_script(ctx.clone(),get_script(&script)?).await?;
+    // Run modules then scripts - each result is bound to $prev for the next
+    let mut stats = vec![];
+    for (name, is_module) in args
+        .module
+        .iter()
+        .map(|m| (m, true))
+        .chain(args.script.iter().map(|s| (s, false)))
+    {
+        let before = if args.stats {
+            Some(MemorySnapshot::take(&rt).await)
+        } else {
+            None
+        };
+        let start = Instant::now();
+        async_with!(ctx => |ctx| {
+            let source = get_script(name)?;
+            let prev = match (is_module, repl_opts.timeout) {
+                (true, _) => run_module(ctx.clone(),source).await?,
+                (false, Some(timeout)) => run_script_with_timeout(ctx.clone(),source,timeout).await?,
+                (false, None) => run_script(ctx.clone(),source).await?,
+            };
+            ctx.globals().set(PREV, prev)?;
+            Ok::<(),anyhow::Error>(())
+        })
+        .await?;
+        if let Some(before) = before {
+            stats.push(ExecutionStats {
+                name: name.clone(),
+                elapsed: start.elapsed(),
+                before,
+                after: MemorySnapshot::take(&rt).await,
+            });
         }
+    }
+    for s in &stats {
+        println!("[+] Stats: {s}");
+    }
 
+    async_with!(ctx => |ctx| {
         // Run REPL
         if args.repl {
-            repl_rustyline(ctx.clone()).await?;
+            #[cfg(feature = "repl_rustyline")]
+            rquickjs_test::repl::repl_rustyline(ctx.clone(), &repl_opts).await?;
+            #[cfg(all(feature = "repl_rustyline_async", not(feature = "repl_rustyline")))]
+            rquickjs_test::repl::repl_rustyline_async(ctx.clone(), &repl_opts).await?;
+            #[cfg(not(any(feature = "repl_rustyline", feature = "repl_rustyline_async")))]
+            rquickjs_test::repl::repl(ctx.clone(), &repl_opts).await?;
+            // Leaving the REPL ends the session - don't wait for intervals
+            rquickjs_test::timers::clear_timers(&ctx);
         }
 
         // Call JS
@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rquickjs::{function::Func, Ctx, IntoJs, Object, Value};
+
+use crate::watchdog::PendingFutures;
+
+/// Event loop lag (host event ready -> JS callback running)
+///
+/// Only recorded for timers (`sleep`, `setTimeout`/`setInterval`) - the
+/// deadline is the one point where the time the event became ready is known
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LagStats {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+impl LagStats {
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64)
+        }
+    }
+}
+
+/// Runtime statistics
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuntimeStats {
+    pub lag: LagStats,
+    /// Host futures currently pending (tracked bridges)
+    pub pending_host_futures: usize,
+}
+
+impl<'js> IntoJs<'js> for RuntimeStats {
+    fn into_js(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let lag = Object::new(ctx.clone())?;
+        lag.set("count", self.lag.count)?;
+        lag.set("meanMs", ms(self.lag.mean()))?;
+        lag.set("maxMs", ms(self.lag.max))?;
+        lag.set("lastMs", ms(self.lag.last))?;
+        let obj = Object::new(ctx.clone())?;
+        obj.set("lag", lag)?;
+        obj.set("pendingHostFutures", self.pending_host_futures)?;
+        Ok(obj.into_value())
+    }
+}
+
+static LAG: Mutex<LagStats> = Mutex::new(LagStats {
+    count: 0,
+    total: Duration::ZERO,
+    max: Duration::ZERO,
+    last: Duration::ZERO,
+});
+
+/// Record lag between host event (timer deadline) and JS being resumed
+pub fn record_lag(lag: Duration) {
+    if let Ok(mut stats) = LAG.lock() {
+        stats.count += 1;
+        stats.total += lag;
+        stats.max = stats.max.max(lag);
+        stats.last = lag;
+    }
+}
+
+/// Current runtime statistics
+pub fn runtime_stats() -> RuntimeStats {
+    RuntimeStats {
+        lag: LAG.lock().map(|s| *s).unwrap_or_default(),
+        pending_host_futures: PendingFutures::global().len(),
+    }
+}
+
+/// Register `metrics` object (`metrics.runtimeStats()`)
+pub fn register_metrics(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let metrics = Object::new(ctx.clone())?;
+    metrics.set("runtimeStats", Func::new(runtime_stats))?;
+    ctx.globals().set("metrics", metrics)?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use anyhow::anyhow;
+use rquickjs::{module::ModuleDef, CatchResultExt, Ctx, JsLifetime, Module, Promise};
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+type EvaluateFn = for<'js> fn(Ctx<'js>, &str) -> rquickjs::Result<Promise<'js>>;
+
+/// Names of native modules already evaluated in a context (stored as userdata)
+#[derive(Default, JsLifetime)]
+struct EvaluatedModules(RefCell<HashSet<String>>);
+
+fn evaluate_def<'js, D: ModuleDef>(ctx: Ctx<'js>, name: &str) -> rquickjs::Result<Promise<'js>> {
+    let (_, promise) = Module::evaluate_def::<D, _>(ctx, name)?;
+    Ok(promise)
+}
+
+/// Set of native (`#[rquickjs::module]`) modules evaluated once per context
+///
+/// ```ignore
+/// let modules = NativeModuleSet::new().with::<js_test_mod>("stuff");
+/// modules.evaluate(ctx.clone()).await?;
+/// ```
+#[derive(Clone, Default)]
+pub struct NativeModuleSet {
+    modules: Vec<(String, EvaluateFn)>,
+}
+
+impl NativeModuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add module definition under import name
+    pub fn with<D: ModuleDef>(mut self, name: &str) -> Self {
+        self.modules.push((name.to_string(), evaluate_def::<D>));
+        self
+    }
+
+    /// Module names in set
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.modules.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Declare and evaluate modules not already evaluated in this context
+    pub async fn evaluate(&self, ctx: Ctx<'_>) -> anyhow::Result<()> {
+        if ctx.userdata::<EvaluatedModules>().is_none() {
+            let _ = ctx.store_userdata(EvaluatedModules::default());
+        }
+        for (name, evaluate) in &self.modules {
+            let evaluated = ctx
+                .userdata::<EvaluatedModules>()
+                .is_some_and(|m| m.0.borrow().contains(name));
+            if !evaluated {
+                evaluate(ctx.clone(), name)
+                    .catch(&ctx)
+                    .map_err(|e| anyhow!("JS error [declare {name}]: {}", e))?
+                    .into_future::<()>()
+                    .await
+                    .catch(&ctx)
+                    .map_err(|e| anyhow!("JS error [eval {name}]: {}", e))?;
+                // Only marked once evaluation succeeds so a failed module can
+                // be retried
+                if let Some(m) = ctx.userdata::<EvaluatedModules>() {
+                    m.0.borrow_mut().insert(name.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use rquickjs::{Ctx, Exception, IntoJs, Object, Value};
+use tokio::net::TcpStream;
+use tokio::time::{Duration, Instant};
+
+#[cfg(feature = "icmp")]
+use crate::executor::{DefaultExecutor, Executor};
+
+/// Default check timeout (ms)
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// Health-check result returned to JS as `{ ok, latency, error }`
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub ok: bool,
+    pub latency: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn ok(start: Instant) -> Self {
+        Self {
+            ok: true,
+            latency: Some(start.elapsed().as_secs_f64() * 1000.0),
+            error: None,
+        }
+    }
+
+    fn err(e: impl std::fmt::Display) -> Self {
+        Self {
+            ok: false,
+            latency: None,
+            error: Some(e.to_string()),
+        }
+    }
+}
+
+impl<'js> IntoJs<'js> for CheckResult {
+    fn into_js(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        let obj = Object::new(ctx.clone())?;
+        obj.set("ok", self.ok)?;
+        obj.set("latency", self.latency)?;
+        obj.set("error", self.error)?;
+        Ok(obj.into_value())
+    }
+}
+
+/// Register `net` object (checkPort and, with the `icmp` feature, ping)
+///
+/// These are only gated at build time (`net`/`icmp` cargo features) and by
+/// the [`crate::env::JsEnvBuilder`] toggle - there's no runtime permission
+/// check, so leave `net` off for contexts that shouldn't probe the network
+pub fn register_net(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let net = Object::new(ctx.clone())?;
+    net.set("checkPort", js_check_port)?;
+    #[cfg(feature = "icmp")]
+    net.set("ping", js_ping)?;
+    ctx.globals().set("net", net)?;
+    Ok(())
+}
+
+/// Attempt TCP connection to host:port
+pub async fn tcp_check(host: &str, port: u16, timeout: Duration) -> CheckResult {
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => CheckResult::ok(start),
+        Ok(Err(e)) => CheckResult::err(e),
+        Err(_) => CheckResult::err("Timeout"),
+    }
+}
+
+/// net.checkPort(host, port, timeout_ms)
+#[rquickjs::function]
+async fn check_port<'js>(
+    ctx: Ctx<'js>,
+    host: String,
+    port: u16,
+    timeout: rquickjs::function::Opt<u64>,
+) -> rquickjs::Result<CheckResult> {
+    if host.is_empty() {
+        return Err(Exception::throw_message(&ctx, "Invalid host"));
+    }
+    let timeout = Duration::from_millis(timeout.0.unwrap_or(DEFAULT_TIMEOUT_MS));
+    Ok(tcp_check(&host, port, timeout).await)
+}
+
+/// Send single ICMP echo request using an unprivileged ping socket
+///
+/// Requires the process gid to be within `net.ipv4.ping_group_range` (or CAP_NET_RAW)
+#[cfg(feature = "icmp")]
+pub async fn icmp_ping(host: &str, timeout: Duration) -> CheckResult {
+    let host = host.to_string();
+    DefaultExecutor::spawn_blocking(move || ping_blocking(&host, timeout))
+        .await
+        .unwrap_or_else(CheckResult::err)
+}
+
+#[cfg(feature = "icmp")]
+fn ping_blocking(host: &str, timeout: Duration) -> CheckResult {
+    use socket2::{Domain, Protocol, Socket, Type};
+    use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+    let addr = match (host, 0)
+        .to_socket_addrs()
+        .map(|mut a| a.find(SocketAddr::is_ipv4))
+    {
+        Ok(Some(addr)) => addr,
+        Ok(None) => return CheckResult::err("No IPv4 address"),
+        Err(e) => return CheckResult::err(e),
+    };
+    let socket: UdpSocket = match Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4)) {
+        Ok(s) => s.into(),
+        Err(e) => return CheckResult::err(format!("ICMP socket not permitted [{e}]")),
+    };
+    if let Err(e) = socket.set_read_timeout(Some(timeout)) {
+        return CheckResult::err(e);
+    }
+
+    // Echo request: type 8, code 0, checksum, id (set by kernel), seq 1
+    let mut packet = [8, 0, 0, 0, 0, 0, 0, 1, b'r', b'q', b'j', b's'];
+    let checksum = icmp_checksum(&packet);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+    let start = Instant::now();
+    if let Err(e) = socket.send_to(&packet, addr) {
+        return CheckResult::err(e);
+    }
+    let mut buf = [0_u8; 1024];
+    loop {
+        match socket.recv_from(&mut buf) {
+            // Echo reply
+            Ok((n, _)) if n >= 8 && buf[0] == 0 => return CheckResult::ok(start),
+            Ok(_) => continue,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                return CheckResult::err("Timeout");
+            }
+            Err(e) => return CheckResult::err(e),
+        }
+    }
+}
+
+#[cfg(feature = "icmp")]
+fn icmp_checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum::<u32>();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// net.ping(host, timeout_ms)
+#[cfg(feature = "icmp")]
+#[rquickjs::function]
+async fn ping(
+    host: String,
+    timeout: rquickjs::function::Opt<u64>,
+) -> rquickjs::Result<CheckResult> {
+    let timeout = Duration::from_millis(timeout.0.unwrap_or(DEFAULT_TIMEOUT_MS));
+    Ok(icmp_ping(&host, timeout).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tcp_check() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let result = tcp_check("127.0.0.1", port, Duration::from_secs(5)).await;
+        assert!(result.ok && result.latency.is_some(), "{result:?}");
+        drop(listener);
+        let result = tcp_check("127.0.0.1", port, Duration::from_secs(5)).await;
+        assert!(!result.ok && result.error.is_some(), "{result:?}");
+    }
+
+    /// Linux drops SYNs for a listener whose accept queue is full, so
+    /// connecting to one hangs until the timeout
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_tcp_check_timeout() {
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(1).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut queued = Vec::new();
+        while let Ok(Ok(stream)) = tokio::time::timeout(
+            Duration::from_millis(100),
+            TcpStream::connect(("127.0.0.1", port)),
+        )
+        .await
+        {
+            queued.push(stream);
+            assert!(queued.len() < 16, "accept queue never filled");
+        }
+        let result = tcp_check("127.0.0.1", port, Duration::from_millis(50)).await;
+        assert_eq!(result.error.as_deref(), Some("Timeout"));
+    }
+}
@@ -0,0 +1,137 @@
+use rquickjs::{Ctx, Exception, IntoJs, Object, Value};
+use sysinfo::{CpuRefreshKind, DiskRefreshKind, Disks, MemoryRefreshKind, RefreshKind, System};
+
+use crate::executor::{DefaultExecutor, Executor};
+
+/// Register `os` object (gated by `os` feature as it exposes host details)
+///
+/// There is no per-call permission check here - hosts that need one can
+/// wrap `os.info` with [`crate::policy::install_policy_hook`] (with lazy
+/// installation `os` must be accessed first so the function exists)
+pub fn register_os(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let os = Object::new(ctx.clone())?;
+    os.set("info", js_info)?;
+    ctx.globals().set("os", os)?;
+    Ok(())
+}
+
+/// Host details returned by `os.info()`
+#[derive(Debug, Clone)]
+pub struct HostInfo {
+    pub hostname: Option<String>,
+    pub arch: String,
+    pub os_version: Option<String>,
+    pub kernel_version: Option<String>,
+    pub uptime: u64,
+    /// (brand, frequency MHz)
+    pub cpus: Vec<(String, u64)>,
+    pub loadavg: [f64; 3],
+    /// (total, used, available) bytes
+    pub memory: (u64, u64, u64),
+    /// (name, mount point, total, available) bytes
+    pub disks: Vec<(String, String, u64, u64)>,
+}
+
+impl HostInfo {
+    /// Gather host details - only CPU frequency, RAM and disk space are
+    /// refreshed (blocking, so run off the JS thread)
+    pub fn collect() -> Self {
+        let sys = System::new_with_specifics(
+            RefreshKind::nothing()
+                .with_cpu(CpuRefreshKind::nothing().with_frequency())
+                .with_memory(MemoryRefreshKind::nothing().with_ram()),
+        );
+        let load = System::load_average();
+        let disks =
+            Disks::new_with_refreshed_list_specifics(DiskRefreshKind::nothing().with_storage());
+        Self {
+            hostname: System::host_name(),
+            arch: System::cpu_arch(),
+            os_version: System::long_os_version(),
+            kernel_version: System::kernel_version(),
+            uptime: System::uptime(),
+            cpus: sys
+                .cpus()
+                .iter()
+                .map(|cpu| (cpu.brand().to_string(), cpu.frequency()))
+                .collect(),
+            loadavg: [load.one, load.five, load.fifteen],
+            memory: (
+                sys.total_memory(),
+                sys.used_memory(),
+                sys.available_memory(),
+            ),
+            disks: disks
+                .list()
+                .iter()
+                .map(|disk| {
+                    (
+                        disk.name().to_string_lossy().to_string(),
+                        disk.mount_point().to_string_lossy().to_string(),
+                        disk.total_space(),
+                        disk.available_space(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'js> IntoJs<'js> for HostInfo {
+    fn into_js(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        let info = Object::new(ctx.clone())?;
+        info.set("hostname", self.hostname)?;
+        info.set("platform", std::env::consts::OS)?;
+        info.set("arch", self.arch)?;
+        info.set("osVersion", self.os_version)?;
+        info.set("kernelVersion", self.kernel_version)?;
+        info.set("uptime", self.uptime)?;
+
+        let cpus = self
+            .cpus
+            .into_iter()
+            .map(|(brand, frequency)| -> rquickjs::Result<Object<'js>> {
+                let c = Object::new(ctx.clone())?;
+                c.set("brand", brand)?;
+                c.set("frequency", frequency)?;
+                Ok(c)
+            })
+            .collect::<rquickjs::Result<Vec<_>>>()?;
+        info.set("cpus", cpus)?;
+        info.set("loadavg", self.loadavg.to_vec())?;
+
+        let memory = Object::new(ctx.clone())?;
+        memory.set("total", self.memory.0)?;
+        memory.set("used", self.memory.1)?;
+        memory.set("available", self.memory.2)?;
+        info.set("memory", memory)?;
+
+        let disks = self
+            .disks
+            .into_iter()
+            .map(
+                |(name, mount, total, available)| -> rquickjs::Result<Object<'js>> {
+                    let d = Object::new(ctx.clone())?;
+                    d.set("name", name)?;
+                    d.set("mount", mount)?;
+                    d.set("total", total)?;
+                    d.set("available", available)?;
+                    Ok(d)
+                },
+            )
+            .collect::<rquickjs::Result<Vec<_>>>()?;
+        info.set("disks", disks)?;
+        Ok(info.into_value())
+    }
+}
+
+/// os.info() - hostname, platform, cpus, load average, memory & disk usage
+///
+/// Resolves once gathered on a blocking thread. CPU usage isn't reported
+/// (it needs two samples some time apart) - use `loadavg` instead
+#[rquickjs::function]
+async fn info<'js>(ctx: Ctx<'js>) -> rquickjs::Result<HostInfo> {
+    DefaultExecutor::spawn_blocking(HostInfo::collect)
+        .await
+        .map_err(|e| Exception::throw_message(&ctx, &format!("OS Error: {e}")))
+}
@@ -0,0 +1,196 @@
+use std::ffi::{c_char, CStr};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use rquickjs::{
+    function::{Async, Func, Rest},
+    Ctx, Exception, Object, Value,
+};
+
+use crate::convert::{args_to_json, json_to_value};
+use crate::executor::{DefaultExecutor, Executor};
+use crate::watchdog::PendingFutures;
+
+/// Plugin ABI version - bumped on any incompatible change to [`PluginVTable`]
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Symbol exported by plugins (`extern "C" fn() -> *const PluginVTable`)
+pub const PLUGIN_ENTRY: &str = "rqjs_plugin_entry";
+
+/// Plugin interface (returned by [`PLUGIN_ENTRY`])
+///
+/// Values are passed as JSON so plugins don't depend on the QuickJS build -
+/// `call` may be invoked concurrently from blocking worker threads
+///
+/// See `examples/echo_plugin.rs`
+#[repr(C)]
+pub struct PluginVTable {
+    /// Must be [`PLUGIN_ABI_VERSION`]
+    pub abi_version: u32,
+    /// Global name (NUL terminated)
+    pub name: *const c_char,
+    /// Comma separated function names (NUL terminated)
+    pub functions: *const c_char,
+    /// Call `function` with JSON array `args_json` - returns JSON result or
+    /// NULL with message in `*error` (both released with `free`)
+    pub call: unsafe extern "C" fn(
+        function: *const c_char,
+        args_json: *const c_char,
+        error: *mut *mut c_char,
+    ) -> *mut c_char,
+    /// Free string returned by `call`
+    pub free: unsafe extern "C" fn(s: *mut c_char),
+}
+
+// SAFETY: vtable is immutable static data and `call` is required to be
+// thread safe
+unsafe impl Sync for PluginVTable {}
+
+type PluginEntry = unsafe extern "C" fn() -> *const PluginVTable;
+
+struct PluginInner {
+    name: String,
+    functions: Vec<String>,
+    vtable: *const PluginVTable,
+    // Keeps vtable loaded - must drop last
+    _lib: libloading::Library,
+}
+
+// SAFETY: see PluginVTable
+unsafe impl Send for PluginInner {}
+unsafe impl Sync for PluginInner {}
+
+/// Native plugin loaded from shared library
+///
+/// ```ignore
+/// let plugin = Plugin::load("libfoo.so")?;
+/// register_plugin(&ctx, &plugin)?;  // JS: await foo.query(...)
+/// ```
+#[derive(Clone)]
+pub struct Plugin {
+    inner: Arc<PluginInner>,
+}
+
+impl Plugin {
+    /// Load plugin and check ABI version
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        // SAFETY: library initialisers run on load - plugins are trusted
+        let lib = unsafe { libloading::Library::new(path) }
+            .map_err(|e| anyhow::anyhow!("Error loading plugin {}: {e}", path.display()))?;
+        let vtable = unsafe {
+            let entry = lib
+                .get::<PluginEntry>(PLUGIN_ENTRY.as_bytes())
+                .map_err(|e| anyhow::anyhow!("Invalid plugin {}: {e}", path.display()))?;
+            entry()
+        };
+        // SAFETY: non-null vtable is valid while the library is loaded
+        let table = unsafe { vtable.as_ref() }.ok_or(anyhow::anyhow!(
+            "Invalid plugin {}: no vtable",
+            path.display()
+        ))?;
+        if table.abi_version != PLUGIN_ABI_VERSION {
+            return Err(anyhow::anyhow!(
+                "Plugin {} ABI version {} (expected {PLUGIN_ABI_VERSION})",
+                path.display(),
+                table.abi_version
+            ));
+        }
+        let name = unsafe { c_str(table.name) }
+            .filter(|name| !name.is_empty())
+            .ok_or(anyhow::anyhow!(
+                "Invalid plugin {}: no name",
+                path.display()
+            ))?;
+        let functions = unsafe { c_str(table.functions) }
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Self {
+            inner: Arc::new(PluginInner {
+                name,
+                functions,
+                vtable,
+                _lib: lib,
+            }),
+        })
+    }
+
+    /// Plugin (global) name
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Functions exported by plugin
+    pub fn functions(&self) -> &[String] {
+        &self.inner.functions
+    }
+
+    /// Call function with JSON array args (blocking)
+    pub fn call(&self, function: &str, args_json: &str) -> anyhow::Result<String> {
+        let function = std::ffi::CString::new(function)?;
+        let args = std::ffi::CString::new(args_json)?;
+        // SAFETY: vtable valid while library loaded (held by self)
+        let vtable = unsafe { &*self.inner.vtable };
+        let mut error = ptr::null_mut();
+        let out = unsafe { (vtable.call)(function.as_ptr(), args.as_ptr(), &mut error) };
+        let result = if out.is_null() {
+            let message = unsafe { c_str(error) }.unwrap_or("Unknown error".into());
+            Err(anyhow::anyhow!(
+                "{}.{}: {message}",
+                self.name(),
+                function.to_string_lossy()
+            ))
+        } else {
+            Ok(unsafe { c_str(out) }.unwrap_or_default())
+        };
+        for s in [out, error] {
+            if !s.is_null() {
+                unsafe { (vtable.free)(s) };
+            }
+        }
+        result
+    }
+}
+
+/// Copy C string (None if NULL)
+unsafe fn c_str(s: *const c_char) -> Option<String> {
+    (!s.is_null()).then(|| unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned())
+}
+
+/// Register plugin as global object with an async method per function
+/// (calls run on the blocking thread pool)
+pub fn register_plugin<'js>(ctx: &Ctx<'js>, plugin: &Plugin) -> anyhow::Result<()> {
+    let obj = Object::new(ctx.clone())?;
+    for function in plugin.functions() {
+        let plugin = plugin.clone();
+        let function = function.clone();
+        let bridge = format!("plugin:{}.{function}", plugin.name());
+        obj.set(
+            function.as_str(),
+            Func::new(Async(move |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+                let args = args_to_json(ctx.clone(), args.0);
+                let plugin = plugin.clone();
+                let function = function.clone();
+                let bridge = bridge.clone();
+                async move {
+                    let _pending = PendingFutures::global().track(&bridge);
+                    let args = args.map_err(|e| Exception::throw_type(&ctx, &e.to_string()))?;
+                    let out =
+                        DefaultExecutor::spawn_blocking(move || plugin.call(&function, &args))
+                            .await
+                            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?
+                            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+                    json_to_value(ctx.clone(), &out)
+                        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+                }
+            })),
+        )?;
+    }
+    ctx.globals().set(plugin.name(), obj)?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use anyhow::anyhow;
+use rquickjs::{
+    function::{Args, Func, Rest, This},
+    Ctx, Exception, Function, Object, Value,
+};
+use std::sync::Arc;
+
+use crate::convert::json_to_value;
+use crate::error::POLICY_DENIED_ERROR;
+
+/// Maximum length of each argument in [`PolicyCall::args`]
+const ARG_SUMMARY_LEN: usize = 128;
+
+/// Native call presented to [`PolicyHook::check`]
+#[derive(Debug, Clone)]
+pub struct PolicyCall<'a> {
+    /// Function path as installed (eg. `dns.lookup`)
+    pub function: &'a str,
+    /// Embedder supplied tenant/context id
+    pub context_id: &'a str,
+    /// Arguments as (truncated) JSON
+    pub args: &'a [String],
+}
+
+/// Result of policy check
+#[derive(Debug, Clone)]
+pub enum PolicyDecision {
+    Allow,
+    /// Throw JS exception with reason
+    Deny(String),
+    /// Call with replacement arguments (JSON)
+    ModifyArgs(Vec<String>),
+}
+
+/// Authorization hook invoked before each wrapped native call
+pub trait PolicyHook: Send + Sync {
+    fn check(&self, call: &PolicyCall<'_>) -> PolicyDecision;
+}
+
+fn arg_summary<'js>(ctx: &Ctx<'js>, v: &Value<'js>) -> String {
+    let mut s = if v.is_function() {
+        "<function>".to_string()
+    } else {
+        ctx.json_stringify(v.clone())
+            .ok()
+            .flatten()
+            .and_then(|s| s.to_string().ok())
+            .unwrap_or_else(|| "<undefined>".to_string())
+    };
+    if s.len() > ARG_SUMMARY_LEN {
+        let mut end = ARG_SUMMARY_LEN;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+        s.push_str("...");
+    }
+    s
+}
+
+/// Wrap native functions (by global path, eg. `dns.lookup`) so that `hook`
+/// is consulted before each call
+///
+/// Denied calls throw a `PolicyDeniedError` (reported as
+/// [`crate::error::ErrorKind::Permission`])
+pub fn install_policy_hook(
+    ctx: &Ctx<'_>,
+    hook: Arc<dyn PolicyHook>,
+    context_id: &str,
+    functions: &[&str],
+) -> anyhow::Result<()> {
+    for path in functions {
+        let (parent, name) = match path.rsplit_once('.') {
+            Some((parent, name)) => {
+                let mut obj = ctx.globals();
+                for p in parent.split(".") {
+                    obj = obj
+                        .get::<_, Object>(p)
+                        .map_err(|e| anyhow!("Invalid Path: {p} [{e}]"))?;
+                }
+                (obj, name)
+            }
+            None => (ctx.globals(), *path),
+        };
+        let original = parent
+            .get::<_, Function>(name)
+            .map_err(|e| anyhow!("{path} not a function [{e}]"))?;
+        let hook = hook.clone();
+        let function = path.to_string();
+        let context_id = context_id.to_string();
+        parent.set(
+            name,
+            Func::new(move |ctx, this: This<Value>, args: Rest<Value>| {
+                let summary = args
+                    .iter()
+                    .map(|a| arg_summary(&ctx, a))
+                    .collect::<Vec<_>>();
+                let call = PolicyCall {
+                    function: &function,
+                    context_id: &context_id,
+                    args: &summary,
+                };
+                let args = match hook.check(&call) {
+                    PolicyDecision::Allow => args.0,
+                    PolicyDecision::Deny(reason) => {
+                        let ex = Exception::from_message(
+                            ctx.clone(),
+                            &format!("Policy Denied: {function} [{reason}]"),
+                        )?;
+                        ex.set("name", POLICY_DENIED_ERROR)?;
+                        return Err(ctx.throw(ex.into_value()));
+                    }
+                    PolicyDecision::ModifyArgs(json) => json
+                        .iter()
+                        .map(|a| {
+                            json_to_value(ctx.clone(), a).map_err(|e| {
+                                Exception::throw_message(&ctx, &format!("Policy Error: {e}"))
+                            })
+                        })
+                        .collect::<rquickjs::Result<Vec<_>>>()?,
+                };
+                let mut arg = Args::new(ctx.clone(), args.len());
+                arg.this(this.0)?;
+                arg.push_args(args)?;
+                original.call_arg::<Value>(arg)
+            }),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorKind, JsError};
+    use rquickjs::{CatchResultExt, CaughtError, Context, Runtime};
+
+    struct DenyAll;
+
+    impl PolicyHook for DenyAll {
+        fn check(&self, call: &PolicyCall<'_>) -> PolicyDecision {
+            PolicyDecision::Deny(format!("{} not allowed", call.function))
+        }
+    }
+
+    #[test]
+    fn test_denied_calls_are_permission_errors() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            ctx.eval::<(), _>("globalThis.host = { run() {} }").unwrap();
+            install_policy_hook(&ctx, Arc::new(DenyAll), "test", &["host.run"]).unwrap();
+            let kind = |src: &str| match ctx.eval::<(), _>(src).catch(&ctx) {
+                Err(CaughtError::Exception(ex)) => JsError::from_exception(&ex).kind,
+                _ => panic!("{src} did not throw"),
+            };
+            assert_eq!(kind("host.run()"), ErrorKind::Permission);
+            // Only the host sets the marker - the message alone isn't enough
+            assert_eq!(
+                kind("throw new Error('Policy Denied: host.run')"),
+                ErrorKind::Exception
+            );
+        });
+    }
+}
@@ -0,0 +1,362 @@
+use std::sync::Arc;
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use pyo3_async_runtimes::tokio::future_into_py;
+use rquickjs::{
+    async_with, function::Rest, AsyncContext, AsyncRuntime, CatchResultExt, Ctx, Exception,
+    Function, Value,
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{oneshot, Mutex};
+
+use crate::env::JsEnvBuilder;
+use crate::error::JsError;
+use crate::run::{call_fn, run_script};
+use crate::util::{
+    args_to_json, json_to_value, register_rx_channel, register_tx_channel, value_to_json,
+};
+
+/// Request handled by the JS thread (results are JSON)
+enum Command {
+    Eval {
+        source: String,
+        reply: oneshot::Sender<anyhow::Result<String>>,
+    },
+    Call {
+        path: String,
+        args: String,
+        reply: oneshot::Sender<anyhow::Result<String>>,
+    },
+    Register {
+        name: String,
+        callback: Py<PyAny>,
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+    Channel {
+        send: String,
+        recv: String,
+        to_js: UnboundedReceiver<String>,
+        from_js: UnboundedSender<String>,
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+}
+
+/// Script environment driven from Python
+///
+/// Values cross the boundary as JSON. The JS runtime runs on its own thread
+/// (so timers and host futures progress between calls) - blocking methods
+/// release the GIL while waiting and the `*_async` variants / `Channel.recv`
+/// return asyncio awaitables:
+///
+/// ```python
+/// env = JsEnv()
+/// ch = env.channel()
+/// env.eval("(async () => { for (;;) send((await recv()).toUpperCase()) })()")
+/// ch.send("hello")
+/// print(await ch.recv())
+/// ```
+#[pyclass(name = "JsEnv")]
+pub struct PyJsEnv {
+    /// JS thread exits when this is dropped
+    commands: UnboundedSender<Command>,
+}
+
+#[pymethods]
+impl PyJsEnv {
+    /// Create environment with basic helpers (see [`JsEnvBuilder::minimal`])
+    #[new]
+    fn new(py: Python<'_>) -> PyResult<Self> {
+        let commands = py.allow_threads(spawn_js_thread).map_err(py_err)?;
+        Ok(Self { commands })
+    }
+
+    /// Evaluate script and return result
+    fn eval(&self, py: Python<'_>, source: String) -> PyResult<Py<PyAny>> {
+        let reply = self.send(|reply| Command::Eval { source, reply })?;
+        let json = py.allow_threads(|| wait(reply))?;
+        from_json(py, &json)
+    }
+
+    /// As `eval` returning an awaitable
+    fn eval_async<'py>(&self, py: Python<'py>, source: String) -> PyResult<Bound<'py, PyAny>> {
+        let reply = self.send(|reply| Command::Eval { source, reply })?;
+        future_into_py(py, async move {
+            let json = wait_async(reply).await?;
+            Python::with_gil(|py| from_json(py, &json))
+        })
+    }
+
+    /// Call fn at dotted `path` (returned promises are awaited)
+    #[pyo3(signature = (path, *args))]
+    fn call(&self, py: Python<'_>, path: String, args: &Bound<'_, PyTuple>) -> PyResult<Py<PyAny>> {
+        let args = to_json(py, args.as_any())?;
+        let reply = self.send(|reply| Command::Call { path, args, reply })?;
+        let json = py.allow_threads(|| wait(reply))?;
+        from_json(py, &json)
+    }
+
+    /// As `call` returning an awaitable
+    #[pyo3(signature = (path, *args))]
+    fn call_async<'py>(
+        &self,
+        py: Python<'py>,
+        path: String,
+        args: &Bound<'py, PyTuple>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let args = to_json(py, args.as_any())?;
+        let reply = self.send(|reply| Command::Call { path, args, reply })?;
+        future_into_py(py, async move {
+            let json = wait_async(reply).await?;
+            Python::with_gil(|py| from_json(py, &json))
+        })
+    }
+
+    /// Register global JS fn `name` calling Python `callback`
+    fn register_callback(&self, py: Python<'_>, name: String, callback: Py<PyAny>) -> PyResult<()> {
+        let reply = self.send(|reply| Command::Register {
+            name,
+            callback,
+            reply,
+        })?;
+        py.allow_threads(|| wait(reply))
+    }
+
+    /// Bridge string messages between Python and JS - JS sends with
+    /// `send(msg)` and receives with `await recv()`
+    #[pyo3(signature = (send = "send", recv = "recv"))]
+    fn channel(&self, py: Python<'_>, send: &str, recv: &str) -> PyResult<Channel> {
+        let (to_js_tx, to_js) = unbounded_channel::<String>();
+        let (from_js, from_js_rx) = unbounded_channel::<String>();
+        let reply = self.send(|reply| Command::Channel {
+            send: send.to_string(),
+            recv: recv.to_string(),
+            to_js,
+            from_js,
+            reply,
+        })?;
+        py.allow_threads(|| wait(reply))?;
+        Ok(Channel {
+            tx: to_js_tx,
+            rx: Arc::new(Mutex::new(from_js_rx)),
+        })
+    }
+}
+
+impl PyJsEnv {
+    fn send<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<anyhow::Result<T>>) -> Command,
+    ) -> PyResult<oneshot::Receiver<anyhow::Result<T>>> {
+        let (reply, rx) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| PyRuntimeError::new_err("JS thread stopped"))?;
+        Ok(rx)
+    }
+}
+
+/// Block until the JS thread replies (call with the GIL released)
+fn wait<T>(reply: oneshot::Receiver<anyhow::Result<T>>) -> PyResult<T> {
+    reply
+        .blocking_recv()
+        .map_err(|_| PyRuntimeError::new_err("JS thread stopped"))?
+        .map_err(py_err)
+}
+
+async fn wait_async<T>(reply: oneshot::Receiver<anyhow::Result<T>>) -> PyResult<T> {
+    reply
+        .await
+        .map_err(|_| PyRuntimeError::new_err("JS thread stopped"))?
+        .map_err(py_err)
+}
+
+/// Start the JS thread - runs commands and drives jobs/host futures between
+/// them until the command sender is dropped
+fn spawn_js_thread() -> anyhow::Result<UnboundedSender<Command>> {
+    let (tx, mut rx) = unbounded_channel::<Command>();
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<anyhow::Result<()>>();
+    std::thread::Builder::new()
+        .name("rquickjs".into())
+        .spawn(move || {
+            let tokio = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(tokio) => tokio,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.into()));
+                    return;
+                }
+            };
+            tokio.block_on(async move {
+                let (rt, ctx) = match new_env().await {
+                    Ok(env) => env,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+                loop {
+                    let command = tokio::select! {
+                        command = rx.recv() => command,
+                        // Nothing left to drive - wait for the next command
+                        () = rt.idle() => rx.recv().await,
+                    };
+                    let Some(command) = command else {
+                        break;
+                    };
+                    run_command(&ctx, command).await;
+                }
+            });
+        })?;
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow::anyhow!("JS thread stopped"))??;
+    Ok(tx)
+}
+
+async fn new_env() -> anyhow::Result<(AsyncRuntime, AsyncContext)> {
+    let rt = AsyncRuntime::new()?;
+    let ctx = AsyncContext::full(&rt).await?;
+    async_with!(ctx => |ctx| {
+        JsEnvBuilder::minimal().apply(ctx.clone()).await
+    })
+    .await?;
+    Ok((rt, ctx))
+}
+
+async fn run_command(ctx: &AsyncContext, command: Command) {
+    match command {
+        Command::Eval { source, reply } => {
+            let result = async_with!(ctx => |ctx| {
+                let v = run_script(ctx.clone(), source).await?;
+                value_to_json(ctx, v)
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+        Command::Call { path, args, reply } => {
+            let result = async_with!(ctx => |ctx| {
+                let args = json_to_value(ctx.clone(), &args)?
+                    .into_array()
+                    .ok_or(anyhow::anyhow!("Args must be an array"))?
+                    .iter::<Value>()
+                    .collect::<rquickjs::Result<Vec<_>>>()?;
+                let v = call_fn(ctx.clone(), &path, Rest(args)).await?;
+                let v = match v.into_promise() {
+                    Some(promise) => promise
+                        .into_future::<Value>()
+                        .await
+                        .catch(&ctx)
+                        .map_err(|e| anyhow::Error::new(JsError::from_caught(&e)))?,
+                    None => v,
+                };
+                value_to_json(ctx, v)
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+        Command::Register {
+            name,
+            callback,
+            reply,
+        } => {
+            let result = async_with!(ctx => |ctx| {
+                register_py_callback(&ctx, &name, callback)
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+        Command::Channel {
+            send,
+            recv,
+            to_js,
+            from_js,
+            reply,
+        } => {
+            let result = async_with!(ctx => |ctx| {
+                register_tx_channel(ctx.clone(), from_js, &send)?;
+                register_rx_channel(ctx.clone(), to_js, &recv)?;
+                Ok::<(), anyhow::Error>(())
+            })
+            .await;
+            let _ = reply.send(result);
+        }
+    }
+}
+
+/// String message channel created by `JsEnv.channel()`
+#[pyclass]
+pub struct Channel {
+    tx: UnboundedSender<String>,
+    rx: Arc<Mutex<UnboundedReceiver<String>>>,
+}
+
+#[pymethods]
+impl Channel {
+    /// Send message to JS
+    fn send(&self, msg: String) -> PyResult<()> {
+        self.tx
+            .send(msg)
+            .map_err(|_| PyRuntimeError::new_err("Channel closed"))
+    }
+
+    /// Awaitable resolving to the next message from JS (None once closed)
+    fn recv<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let rx = self.rx.clone();
+        future_into_py(py, async move { Ok(rx.lock().await.recv().await) })
+    }
+
+    /// Next message from JS (None if nothing pending)
+    fn try_recv(&self) -> Option<String> {
+        self.rx.try_lock().ok()?.try_recv().ok()
+    }
+}
+
+fn register_py_callback<'js>(
+    ctx: &Ctx<'js>,
+    name: &str,
+    callback: Py<PyAny>,
+) -> anyhow::Result<()> {
+    let f = Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>, args: Rest<Value<'js>>| -> rquickjs::Result<Value<'js>> {
+            let args = args_to_json(ctx.clone(), args.0)
+                .map_err(|e| Exception::throw_type(&ctx, &e.to_string()))?;
+            let out = Python::with_gil(|py| {
+                let args: Vec<Py<PyAny>> = from_json(py, &args)?.extract(py)?;
+                let out = callback.call1(py, PyTuple::new(py, args)?)?;
+                to_json(py, out.bind(py))
+            })
+            .map_err(|e| Exception::throw_message(&ctx, &format!("Python Error: {e}")))?;
+            json_to_value(ctx.clone(), &out)
+                .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+        },
+    )?
+    .with_name(name)?;
+    ctx.globals().set(name, f)?;
+    Ok(())
+}
+
+fn from_json(py: Python<'_>, json: &str) -> PyResult<Py<PyAny>> {
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn to_json(py: Python<'_>, obj: &Bound<'_, PyAny>) -> PyResult<String> {
+    py.import("json")?.call_method1("dumps", (obj,))?.extract()
+}
+
+fn py_err(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{e:#}"))
+}
+
+/// Python module (`import rquickjs_test`)
+#[pymodule]
+fn rquickjs_test(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyJsEnv>()?;
+    m.add_class::<Channel>()?;
+    Ok(())
+}
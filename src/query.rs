@@ -0,0 +1,713 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rquickjs::{Array, Ctx, Exception, JsLifetime, Type, Value};
+
+use crate::convert::value_to_json;
+
+/// Max parsed expressions cached per context by the `query` global
+const QUERY_CACHE_CAPACITY: usize = 256;
+
+/// Parsed `query` expressions (context userdata) - cleared when full
+#[derive(Default, JsLifetime)]
+struct QueryCache(RefCell<HashMap<String, Rc<Query>>>);
+
+/// JMESPath-style document query
+///
+/// Supports a practical subset of JMESPath:
+///
+/// | syntax            | meaning                                        |
+/// |-------------------|------------------------------------------------|
+/// | `a.b`, `"a-b"`    | field access                                   |
+/// | `[0]`, `[-1]`     | array index (negative from end)                |
+/// | `[*]`, `.*`       | array/object value projection                  |
+/// | `[]`              | flatten projection                             |
+/// | `[?cond]`         | filter projection                              |
+/// | `@`               | current element                                |
+///
+/// Filter conditions compare paths and literals (`3`, `'str'`,
+/// `` `json` ``, `true`/`false`/`null`) with `== != < <= > >=`, combined
+/// with `&& || !` and parentheses (numbers compare by value so `1 == 1.0`).
+/// Segments after a projection are applied
+/// to each element and null results are dropped:
+///
+/// ```ignore
+/// let q = Query::parse("sensors[?temp > 30].id")?;
+/// let hot = q.apply(&doc);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query {
+    path: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Field(String),
+    Index(i64),
+    Wildcard,
+    Values,
+    Flatten,
+    Filter(Cond),
+}
+
+#[derive(Debug, Clone)]
+enum Cond {
+    Or(Box<Cond>, Box<Cond>),
+    And(Box<Cond>, Box<Cond>),
+    Not(Box<Cond>),
+    Cmp(Operand, CmpOp, Operand),
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Path(Vec<Segment>),
+    Literal(serde_json::Value),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    /// Parse query expression
+    pub fn parse(expr: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser {
+            chars: expr.chars().collect(),
+            pos: 0,
+        };
+        let path = parser.path()?;
+        parser.skip_ws();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(Self { path })
+    }
+
+    /// Evaluate query against document (missing fields return null)
+    pub fn apply(&self, doc: &serde_json::Value) -> serde_json::Value {
+        eval(&self.path, doc)
+    }
+}
+
+/// Parse and evaluate `expr` against `doc`
+pub fn query(doc: &serde_json::Value, expr: &str) -> anyhow::Result<serde_json::Value> {
+    Ok(Query::parse(expr)?.apply(doc))
+}
+
+/// Register `query(obj, expr)` global
+pub fn register_query(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    ctx.globals().set("query", js_query_value)?;
+    Ok(())
+}
+
+/// query(obj, expr) - throws SyntaxError for invalid expressions
+///
+/// `obj` is walked directly (selected values are returned as is, not
+/// copied) and parsed expressions are cached per context
+#[rquickjs::function]
+fn query_value<'js>(ctx: Ctx<'js>, obj: Value<'js>, expr: String) -> rquickjs::Result<Value<'js>> {
+    let q = cached_query(&ctx, &expr)?;
+    eval_js(&q.path, obj)
+}
+
+fn cached_query(ctx: &Ctx<'_>, expr: &str) -> rquickjs::Result<Rc<Query>> {
+    if ctx.userdata::<QueryCache>().is_none() {
+        let _ = ctx.store_userdata(QueryCache::default());
+    }
+    let cache = ctx.userdata::<QueryCache>();
+    if let Some(q) = cache.as_ref().and_then(|c| c.0.borrow().get(expr).cloned()) {
+        return Ok(q);
+    }
+    let q = Rc::new(Query::parse(expr).map_err(|e| Exception::throw_syntax(ctx, &e.to_string()))?);
+    if let Some(cache) = cache {
+        let mut cache = cache.0.borrow_mut();
+        if cache.len() >= QUERY_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(expr.to_string(), q.clone());
+    }
+    Ok(q)
+}
+
+/// As [`eval`] for a JS value (missing fields are null)
+fn eval_js<'js>(path: &[Segment], v: Value<'js>) -> rquickjs::Result<Value<'js>> {
+    let Some((segment, rest)) = path.split_first() else {
+        return Ok(v);
+    };
+    let ctx = v.ctx().clone();
+    // Plain objects only (arrays/functions are also objects)
+    let object = v.as_object().filter(|_| v.type_of() == Type::Object);
+    match (segment, object, v.as_array()) {
+        (Segment::Field(name), Some(obj), _) => match obj.get::<_, Value>(name.as_str())? {
+            field if field.is_undefined() => Ok(Value::new_null(ctx)),
+            field => eval_js(rest, field),
+        },
+        (Segment::Index(i), _, Some(items)) => {
+            let i = if *i < 0 { items.len() as i64 + i } else { *i };
+            match usize::try_from(i).ok().filter(|i| *i < items.len()) {
+                Some(i) => eval_js(rest, items.get(i)?),
+                None => Ok(Value::new_null(ctx)),
+            }
+        }
+        (Segment::Wildcard, _, Some(items)) => project_js(&ctx, items.iter(), rest),
+        (Segment::Values, Some(obj), _) => project_js(&ctx, obj.values(), rest),
+        (Segment::Flatten, _, Some(items)) => {
+            let mut flat = Vec::new();
+            for v in items.iter::<Value>() {
+                let v = v?;
+                match v.as_array() {
+                    Some(inner) => {
+                        flat.extend(inner.iter::<Value>().collect::<Result<Vec<_>, _>>()?)
+                    }
+                    None => flat.push(v),
+                }
+            }
+            project_js(&ctx, flat.into_iter().map(Ok), rest)
+        }
+        (Segment::Filter(cond), _, Some(items)) => {
+            let mut matched = Vec::new();
+            for v in items.iter::<Value>() {
+                let v = v?;
+                if cond.test_js(&v)? {
+                    matched.push(v);
+                }
+            }
+            project_js(&ctx, matched.into_iter().map(Ok), rest)
+        }
+        _ => Ok(Value::new_null(ctx)),
+    }
+}
+
+/// As [`project`] for JS values
+fn project_js<'js>(
+    ctx: &Ctx<'js>,
+    items: impl Iterator<Item = rquickjs::Result<Value<'js>>>,
+    rest: &[Segment],
+) -> rquickjs::Result<Value<'js>> {
+    let out = Array::new(ctx.clone())?;
+    let mut n = 0;
+    for v in items {
+        let v = eval_js(rest, v?)?;
+        if !v.is_null() && !v.is_undefined() {
+            out.set(n, v)?;
+            n += 1;
+        }
+    }
+    Ok(out.into_value())
+}
+
+fn eval(path: &[Segment], v: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    let Some((segment, rest)) = path.split_first() else {
+        return v.clone();
+    };
+    match (segment, v) {
+        (Segment::Field(name), Json::Object(obj)) => match obj.get(name) {
+            Some(v) => eval(rest, v),
+            None => Json::Null,
+        },
+        (Segment::Index(i), Json::Array(items)) => {
+            let i = if *i < 0 { items.len() as i64 + i } else { *i };
+            match usize::try_from(i).ok().and_then(|i| items.get(i)) {
+                Some(v) => eval(rest, v),
+                None => Json::Null,
+            }
+        }
+        (Segment::Wildcard, Json::Array(items)) => project(items.iter(), rest),
+        (Segment::Values, Json::Object(obj)) => project(obj.values(), rest),
+        (Segment::Flatten, Json::Array(items)) => project(
+            items.iter().flat_map(|v| match v {
+                Json::Array(inner) => inner.iter().collect(),
+                v => vec![v],
+            }),
+            rest,
+        ),
+        (Segment::Filter(cond), Json::Array(items)) => {
+            project(items.iter().filter(|v| cond.test(v)), rest)
+        }
+        _ => Json::Null,
+    }
+}
+
+/// Apply remaining path to each item (dropping nulls)
+fn project<'a>(
+    items: impl Iterator<Item = &'a serde_json::Value>,
+    rest: &[Segment],
+) -> serde_json::Value {
+    serde_json::Value::Array(
+        items
+            .map(|v| eval(rest, v))
+            .filter(|v| !v.is_null())
+            .collect(),
+    )
+}
+
+/// JMESPath truthiness (null, false, empty string/array/object are false)
+fn truthy(v: &serde_json::Value) -> bool {
+    use serde_json::Value as Json;
+    match v {
+        Json::Null => false,
+        Json::Bool(b) => *b,
+        Json::String(s) => !s.is_empty(),
+        Json::Array(a) => !a.is_empty(),
+        Json::Object(o) => !o.is_empty(),
+        Json::Number(_) => true,
+    }
+}
+
+/// As [`truthy`] for JS values
+fn truthy_js(v: &Value<'_>) -> rquickjs::Result<bool> {
+    Ok(match v.type_of() {
+        Type::Null | Type::Undefined => false,
+        Type::Bool => v.as_bool().unwrap_or(false),
+        Type::String => v
+            .as_string()
+            .is_some_and(|s| !s.to_string().unwrap_or_default().is_empty()),
+        Type::Array => v.as_array().is_some_and(|a| !a.is_empty()),
+        Type::Object => v
+            .as_object()
+            .is_some_and(|o| o.keys::<String>().next().is_some()),
+        _ => true,
+    })
+}
+
+/// JSON equality with numbers compared by value (`1 == 1.0`)
+fn json_eq(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    use serde_json::Value as Json;
+    match (a, b) {
+        (Json::Number(a), Json::Number(b)) => a.as_f64() == b.as_f64(),
+        (Json::Array(a), Json::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+        }
+        (Json::Object(a), Json::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, a)| b.get(k).is_some_and(|b| json_eq(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn compare(a: &serde_json::Value, op: CmpOp, b: &serde_json::Value) -> bool {
+    match op {
+        CmpOp::Eq => json_eq(a, b),
+        CmpOp::Ne => !json_eq(a, b),
+        op => {
+            let ord = match (a, b) {
+                (serde_json::Value::Number(a), serde_json::Value::Number(b)) => {
+                    a.as_f64().partial_cmp(&b.as_f64())
+                }
+                (serde_json::Value::String(a), serde_json::Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            };
+            ord.is_some_and(|ord| match op {
+                CmpOp::Lt => ord == Ordering::Less,
+                CmpOp::Le => ord != Ordering::Greater,
+                CmpOp::Gt => ord == Ordering::Greater,
+                _ => ord != Ordering::Less,
+            })
+        }
+    }
+}
+
+impl Cond {
+    fn test(&self, v: &serde_json::Value) -> bool {
+        match self {
+            Cond::Or(a, b) => a.test(v) || b.test(v),
+            Cond::And(a, b) => a.test(v) && b.test(v),
+            Cond::Not(c) => !c.test(v),
+            Cond::Truthy(a) => truthy(&a.eval(v)),
+            Cond::Cmp(a, op, b) => compare(&a.eval(v), *op, &b.eval(v)),
+        }
+    }
+
+    fn test_js(&self, v: &Value<'_>) -> rquickjs::Result<bool> {
+        Ok(match self {
+            Cond::Or(a, b) => a.test_js(v)? || b.test_js(v)?,
+            Cond::And(a, b) => a.test_js(v)? && b.test_js(v)?,
+            Cond::Not(c) => !c.test_js(v)?,
+            Cond::Truthy(Operand::Path(path)) => truthy_js(&eval_js(path, v.clone())?)?,
+            Cond::Truthy(Operand::Literal(lit)) => truthy(lit),
+            Cond::Cmp(a, op, b) => compare(&a.eval_js(v)?, *op, &b.eval_js(v)?),
+        })
+    }
+}
+
+impl Operand {
+    fn eval(&self, v: &serde_json::Value) -> serde_json::Value {
+        match self {
+            Operand::Path(path) => eval(path, v),
+            Operand::Literal(lit) => lit.clone(),
+        }
+    }
+
+    /// Operand as JSON (values JSON can't represent are null)
+    fn eval_js(&self, v: &Value<'_>) -> rquickjs::Result<serde_json::Value> {
+        match self {
+            Operand::Path(path) => {
+                let v = eval_js(path, v.clone())?;
+                Ok(value_to_json(v.ctx().clone(), v)
+                    .ok()
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default())
+            }
+            Operand::Literal(lit) => Ok(lit.clone()),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, msg: &str) -> anyhow::Error {
+        anyhow::anyhow!("Invalid query at {}: {msg}", self.pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume `s` (after whitespace) if present
+    fn eat(&mut self, s: &str) -> bool {
+        self.skip_ws();
+        let n = s.chars().count();
+        if self.chars[self.pos..].iter().take(n).copied().eq(s.chars()) {
+            self.pos += n;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> anyhow::Result<()> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{s}'")))
+        }
+    }
+
+    /// `[@ | field] (.field | .* | [...])*`
+    fn path(&mut self) -> anyhow::Result<Vec<Segment>> {
+        let mut path = Vec::new();
+        self.skip_ws();
+        if self.eat("*") {
+            path.push(Segment::Values);
+        } else if self.at_field() {
+            path.push(Segment::Field(self.field()?));
+        } else {
+            // Optional `@` (current element)
+            self.eat("@");
+        }
+        loop {
+            if self.eat("[") {
+                path.push(self.bracket()?);
+            } else if self.eat(".") {
+                if self.eat("*") {
+                    path.push(Segment::Values);
+                } else {
+                    path.push(Segment::Field(self.field()?));
+                }
+            } else {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn at_field(&mut self) -> bool {
+        self.skip_ws();
+        self.peek()
+            .is_some_and(|c| c == '"' || c == '_' || c.is_alphabetic())
+    }
+
+    /// Identifier or "quoted" field name
+    fn field(&mut self) -> anyhow::Result<String> {
+        self.skip_ws();
+        match self.peek() {
+            Some('"') => self.delimited('"'),
+            Some(c) if c == '_' || c.is_alphabetic() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c == '_' || c.is_alphanumeric()) {
+                    self.pos += 1;
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+            _ => Err(self.error("expected field name")),
+        }
+    }
+
+    /// Segment after '['
+    fn bracket(&mut self) -> anyhow::Result<Segment> {
+        let segment = if self.eat("]") {
+            return Ok(Segment::Flatten);
+        } else if self.eat("*") {
+            Segment::Wildcard
+        } else if self.eat("?") {
+            Segment::Filter(self.or()?)
+        } else {
+            let start = self.pos;
+            if self.peek() == Some('-') {
+                self.pos += 1;
+            }
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+            let index = self.chars[start..self.pos].iter().collect::<String>();
+            Segment::Index(index.parse().map_err(|_| self.error("expected index"))?)
+        };
+        self.expect("]")?;
+        Ok(segment)
+    }
+
+    fn or(&mut self) -> anyhow::Result<Cond> {
+        let mut cond = self.and()?;
+        while self.eat("||") {
+            cond = Cond::Or(Box::new(cond), Box::new(self.and()?));
+        }
+        Ok(cond)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Cond> {
+        let mut cond = self.unary()?;
+        while self.eat("&&") {
+            cond = Cond::And(Box::new(cond), Box::new(self.unary()?));
+        }
+        Ok(cond)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<Cond> {
+        if self.eat("!") {
+            return Ok(Cond::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let cond = self.or()?;
+            self.expect(")")?;
+            return Ok(cond);
+        }
+        let lhs = self.operand()?;
+        // Two char operators first
+        for (s, op) in [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+        ] {
+            if self.eat(s) {
+                return Ok(Cond::Cmp(lhs, op, self.operand()?));
+            }
+        }
+        Ok(Cond::Truthy(lhs))
+    }
+
+    fn operand(&mut self) -> anyhow::Result<Operand> {
+        self.skip_ws();
+        let literal = match self.peek() {
+            Some('\'') => serde_json::Value::String(self.delimited('\'')?),
+            Some('`') => {
+                let json = self.delimited('`')?;
+                serde_json::from_str(&json).map_err(|_| self.error("invalid JSON literal"))?
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                self.pos += 1;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+'))
+                {
+                    self.pos += 1;
+                }
+                let n = self.chars[start..self.pos].iter().collect::<String>();
+                serde_json::from_str(&n).map_err(|_| self.error("invalid number"))?
+            }
+            _ => {
+                for (s, v) in [
+                    ("true", serde_json::Value::Bool(true)),
+                    ("false", serde_json::Value::Bool(false)),
+                    ("null", serde_json::Value::Null),
+                ] {
+                    let end = self.pos + s.len();
+                    let keyword = self
+                        .chars
+                        .get(self.pos..end)
+                        .is_some_and(|k| k.iter().copied().eq(s.chars()))
+                        && !self
+                            .chars
+                            .get(end)
+                            .is_some_and(|c| *c == '_' || c.is_alphanumeric());
+                    if keyword {
+                        self.pos = end;
+                        return Ok(Operand::Literal(v));
+                    }
+                }
+                let start = self.pos;
+                let path = self.path()?;
+                if self.pos == start {
+                    return Err(self.error("expected operand"));
+                }
+                return Ok(Operand::Path(path));
+            }
+        };
+        Ok(Operand::Literal(literal))
+    }
+
+    /// Contents of `delim` quoted string (backslash escapes the delimiter)
+    fn delimited(&mut self, delim: char) -> anyhow::Result<String> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error(&format!("unterminated {delim}"))),
+                Some(c) if c == delim => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some('\\') if self.chars.get(self.pos + 1) == Some(&delim) => {
+                    s.push(delim);
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rquickjs::{Context, Runtime};
+    use serde_json::json;
+
+    fn doc() -> serde_json::Value {
+        json!({
+            "site": "a",
+            "sensors": [
+                { "id": "s1", "temp": 35, "tags": ["x", "y"] },
+                { "id": "s2", "temp": 20.5, "tags": [] },
+                { "id": "s3", "temp": 1.0, "tags": ["z"] },
+            ],
+            "limits": { "hi": 30, "lo": 10 },
+        })
+    }
+
+    #[test]
+    fn test_paths() {
+        let doc = doc();
+        assert_eq!(query(&doc, "site").unwrap(), json!("a"));
+        assert_eq!(query(&doc, "sensors[-1].id").unwrap(), json!("s3"));
+        assert_eq!(query(&doc, "sensors[5]").unwrap(), json!(null));
+        assert_eq!(query(&doc, "missing.field").unwrap(), json!(null));
+        assert_eq!(
+            query(&doc, "sensors[*].id").unwrap(),
+            json!(["s1", "s2", "s3"])
+        );
+        assert_eq!(
+            query(&doc, "sensors[*].tags[]").unwrap(),
+            json!(["x", "y", "z"])
+        );
+        assert_eq!(query(&doc, "limits.*").unwrap(), json!([30, 10]));
+    }
+
+    #[test]
+    fn test_filters() {
+        let doc = doc();
+        assert_eq!(
+            query(&doc, "sensors[?temp > `30`].id").unwrap(),
+            json!(["s1"])
+        );
+        assert_eq!(
+            query(&doc, "sensors[?tags && id != 's1'].id").unwrap(),
+            json!(["s3"])
+        );
+        assert_eq!(
+            query(&doc, "sensors[?!(temp < 25) || id == 's2'].id").unwrap(),
+            json!(["s1", "s2"])
+        );
+    }
+
+    #[test]
+    fn test_numeric_equality() {
+        let doc = doc();
+        assert_eq!(
+            query(&doc, "sensors[?temp == `1`].id").unwrap(),
+            json!(["s3"])
+        );
+        assert_eq!(
+            query(&doc, "sensors[?temp == 35.0].id").unwrap(),
+            json!(["s1"])
+        );
+        assert_eq!(
+            query(&json!([{ "a": [1.0] }]), "[?a == `[1]`]").unwrap(),
+            json!([{ "a": [1.0] }])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expr in ["a.", "a[", "a[?b ==]", "a[?'x]", "a b"] {
+            assert!(Query::parse(expr).is_err(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_js_query() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            register_query(&ctx).unwrap();
+            ctx.globals()
+                .set(
+                    "doc",
+                    crate::convert::json_to_value(ctx.clone(), &doc().to_string()).unwrap(),
+                )
+                .unwrap();
+            let eval = |src: &str| {
+                let v = ctx.eval::<Value, _>(src).unwrap();
+                serde_json::from_str::<serde_json::Value>(&value_to_json(ctx.clone(), v).unwrap())
+                    .unwrap()
+            };
+            assert_eq!(
+                eval("query(doc, 'sensors[?temp >= `20`].id')"),
+                json!(["s1", "s2"])
+            );
+            assert_eq!(
+                eval("query(doc, 'sensors[?temp == `1`].id')"),
+                json!(["s3"])
+            );
+            assert_eq!(eval("query(doc, 'nope.nope')"), json!(null));
+            // Values are returned as is (not copies)
+            assert_eq!(eval("query(doc, 'limits') === doc.limits"), json!(true));
+            // Non-JSON values are walked too
+            assert_eq!(eval("query({ f: { g: 1 }, h() {} }, 'f.g')"), json!(1));
+            assert_eq!(
+                eval("try { query(doc, 'a[') } catch (e) { e.name }"),
+                json!("SyntaxError")
+            );
+            // Parsed once
+            eval("query(doc, 'site'); query(doc, 'site')");
+            assert_eq!(ctx.userdata::<QueryCache>().unwrap().0.borrow().len(), 5);
+        });
+    }
+}
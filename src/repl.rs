@@ -0,0 +1,740 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rquickjs::{Ctx, Value};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::convert::{inspect, stdout_color, truncate_output, DEFAULT_INSPECT_DEPTH};
+use crate::error::ScriptExit;
+use crate::history::{default_history_file, History};
+use crate::run::{run_script, run_script_with_timeout};
+
+/// REPL
+const PROMPT: &str = ">>> ";
+const MULTILINE_PROMPT: &str = "... ";
+
+/// Max interval between lines treated as a single paste (repl_rustyline_async)
+#[cfg(feature = "repl_rustyline_async")]
+const PASTE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Default max bytes of a result printed before paging
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 4096;
+
+/// REPL dot-commands
+const HELP: &str = "\
+.help             Show commands
+.load <file>      Evaluate file
+.save <file>      Save session transcript
+.clear            Remove globals defined in session
+.edit [name]      Edit input (or function source) in $EDITOR
+.history [query]  Search history
+.more             Show more of last result
+.exit             Exit REPL";
+
+/// Startup script loaded from home and current directory
+pub const RC_FILE: &str = ".rquickjsrc.js";
+
+/// Default rc files (`~/.rquickjsrc.js` then `./.rquickjsrc.js`)
+pub fn default_rc_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Some(home) = std::env::var_os("HOME") {
+        files.push(PathBuf::from(home).join(RC_FILE));
+    }
+    let local = PathBuf::from(RC_FILE);
+    // Skip local rc if it is the same file (run from $HOME)
+    match (
+        files.first().map(|f| f.canonicalize()),
+        local.canonicalize(),
+    ) {
+        (Some(Ok(home)), Ok(local)) if home == local => {}
+        _ => files.push(local),
+    }
+    files
+}
+
+/// REPL options
+#[derive(Debug, Clone)]
+pub struct ReplOptions {
+    /// Max bytes of result output shown at once (remainder via `.more`)
+    pub max_output_bytes: Option<usize>,
+    /// History file shared by all frontends (None = in-memory only)
+    pub history_file: Option<PathBuf>,
+    /// Use fuzzy (subsequence) matching for `.history` search
+    pub fuzzy_history: bool,
+    /// Scripts evaluated at startup (missing files are skipped)
+    pub rc_files: Vec<PathBuf>,
+    /// Abort commands running longer than this (requires
+    /// [`install_timeout_handler`](crate::run::install_timeout_handler))
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ReplOptions {
+    fn default() -> Self {
+        Self {
+            max_output_bytes: Some(DEFAULT_MAX_OUTPUT_BYTES),
+            history_file: default_history_file(),
+            fuzzy_history: false,
+            rc_files: default_rc_files(),
+            timeout: None,
+        }
+    }
+}
+
+/// State shared between REPL input and evaluation
+struct ReplSession {
+    pager: OutputPager,
+    history: Arc<Mutex<History>>,
+    fuzzy_history: bool,
+    /// Commands evaluated this session (for `.save`)
+    transcript: Vec<String>,
+    /// Globals present at startup (kept by `.clear`)
+    initial_globals: HashSet<String>,
+    timeout: Option<Duration>,
+}
+
+impl ReplSession {
+    async fn new(ctx: Ctx<'_>, opts: &ReplOptions) -> anyhow::Result<Self> {
+        load_rc(ctx.clone(), &opts.rc_files).await;
+        Ok(Self {
+            pager: OutputPager::new(opts.max_output_bytes),
+            history: Arc::new(Mutex::new(History::load(opts.history_file.clone())?)),
+            fuzzy_history: opts.fuzzy_history,
+            transcript: Vec::new(),
+            initial_globals: global_names(&ctx)?,
+            timeout: opts.timeout,
+        })
+    }
+
+    fn history_entries(&self) -> Vec<String> {
+        self.history
+            .lock()
+            .map(|h| h.entries().to_vec())
+            .unwrap_or_default()
+    }
+
+    fn add_history(&self, cmd: &str) {
+        add_history(&self.history, cmd);
+    }
+
+    fn search_history(&self, query: &str) {
+        if let Ok(history) = self.history.lock() {
+            for (i, entry) in history.search(query, self.fuzzy_history) {
+                println!("{i:>5}  {}", entry.replace('\n', "\n       "));
+            }
+        }
+    }
+}
+
+fn global_names(ctx: &Ctx<'_>) -> rquickjs::Result<HashSet<String>> {
+    ctx.globals()
+        .own_keys::<String>(rquickjs::object::Filter::new().string())
+        .collect()
+}
+
+/// Remove globals defined since startup
+///
+/// Only `globalThis` properties are removed - top-level `let`/`const`/`class`
+/// bindings can't be deleted from a running context
+fn clear_globals(ctx: &Ctx<'_>, keep: &HashSet<String>) -> anyhow::Result<usize> {
+    let mut n = 0;
+    for name in global_names(ctx)?.difference(keep) {
+        ctx.globals().remove(name.as_str())?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Evaluate rc files - errors are reported but don't stop the REPL
+async fn load_rc(ctx: Ctx<'_>, files: &[PathBuf]) {
+    for file in files.iter().filter(|f| f.is_file()) {
+        let result = match std::fs::read_to_string(file) {
+            Ok(script) => run_script(ctx.clone(), script).await.map(|_| ()),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            eprintln!("[-] RC Error: {} [{e}]", file.display());
+        }
+    }
+}
+
+fn add_history(history: &Mutex<History>, cmd: &str) {
+    if let Ok(mut history) = history.lock() {
+        if let Err(e) = history.add(cmd) {
+            eprintln!("[-] History Error: {e}");
+        }
+    }
+}
+
+/// Pages large results - first page is printed and the rest held for `.more`
+#[derive(Debug, Default)]
+struct OutputPager {
+    max: Option<usize>,
+    pending: String,
+}
+
+impl OutputPager {
+    fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            pending: String::new(),
+        }
+    }
+
+    fn show(&mut self, output: String) {
+        self.pending = output;
+        self.more();
+    }
+
+    fn more(&mut self) {
+        if self.pending.is_empty() {
+            println!("[-] No more output");
+            return;
+        }
+        let page = match self.max {
+            Some(max) if self.pending.len() > max => {
+                let (page, _) = truncate_output(&self.pending, max);
+                let rest = self.pending.split_off(page.len());
+                std::mem::replace(&mut self.pending, rest)
+            }
+            _ => std::mem::take(&mut self.pending),
+        };
+        println!("{page}");
+        if !self.pending.is_empty() {
+            println!(
+                "... <{} more bytes - .more to continue>",
+                self.pending.len()
+            );
+        }
+    }
+}
+
+/// Evaluate REPL command (or dot-command) and print result
+async fn eval_cmd(
+    ctx: Ctx<'_>,
+    cmd: String,
+    session: &mut ReplSession,
+) -> anyhow::Result<ControlFlow<()>> {
+    let (line, buffer) = cmd.split_once('\n').unwrap_or((&cmd, ""));
+    let mut args = line.split_whitespace();
+    let cmd = match args.next() {
+        Some(".help") => {
+            println!("{HELP}");
+            return Ok(ControlFlow::Continue(()));
+        }
+        Some(".exit") => return Ok(ControlFlow::Break(())),
+        Some(".more") => {
+            session.pager.more();
+            return Ok(ControlFlow::Continue(()));
+        }
+        Some(".history") => {
+            session.search_history(&args.collect::<Vec<_>>().join(" "));
+            return Ok(ControlFlow::Continue(()));
+        }
+        Some(".clear") => {
+            match clear_globals(&ctx, &session.initial_globals) {
+                Ok(n) => println!("[+] Cleared {n} globals"),
+                Err(e) => eprintln!("[-] Clear Error: {e}"),
+            }
+            return Ok(ControlFlow::Continue(()));
+        }
+        Some(".save") => {
+            match args.next() {
+                Some(path) => match std::fs::write(path, session.transcript.join("\n") + "\n") {
+                    Ok(_) => println!("[+] Saved {} commands to {path}", session.transcript.len()),
+                    Err(e) => eprintln!("[-] Save Error: {path} [{e}]"),
+                },
+                None => eprintln!("[-] Usage: .save <file>"),
+            }
+            return Ok(ControlFlow::Continue(()));
+        }
+        Some(".load") => match args
+            .next()
+            .map(|path| (path, std::fs::read_to_string(path)))
+        {
+            Some((_, Ok(script))) => script,
+            Some((path, Err(e))) => {
+                eprintln!("[-] Load Error: {path} [{e}]");
+                return Ok(ControlFlow::Continue(()));
+            }
+            None => {
+                eprintln!("[-] Usage: .load <file>");
+                return Ok(ControlFlow::Continue(()));
+            }
+        },
+        Some(".edit") => match edit(ctx.clone(), args.next(), buffer) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("[-] Edit Error: {e}");
+                return Ok(ControlFlow::Continue(()));
+            }
+        },
+        Some(c) if is_dot_command(c) => {
+            eprintln!("[-] Unknown command: {c} (.help for commands)");
+            return Ok(ControlFlow::Continue(()));
+        }
+        _ => cmd,
+    };
+    session.transcript.push(cmd.clone());
+    let result = match session.timeout {
+        Some(timeout) => run_script_with_timeout(ctx.clone(), cmd, timeout).await,
+        None => run_script(ctx.clone(), cmd).await,
+    };
+    match result {
+        Ok(v) => {
+            if !v.is_undefined() {
+                ctx.globals().set("_", v.clone())?;
+                session.pager.show(format_result(ctx.clone(), v));
+            }
+        }
+        // exit() ends the REPL with its exit code
+        Err(e) if e.is::<ScriptExit>() => return Err(e),
+        Err(e) => eprintln!("{e}"),
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+fn is_dot_command(s: &str) -> bool {
+    s.strip_prefix('.')
+        .is_some_and(|c| !c.is_empty() && c.chars().all(char::is_alphabetic))
+}
+
+/// Open $VISUAL/$EDITOR with buffer (or source of function `name`) and
+/// return saved contents
+fn edit(ctx: Ctx<'_>, name: Option<&str>, buffer: &str) -> anyhow::Result<String> {
+    let source = match name {
+        Some(name) => {
+            let f = ctx
+                .eval::<rquickjs::Function, _>(name)
+                .map_err(|_| anyhow::anyhow!("{name} not a function"))?;
+            let source = f
+                .get::<_, rquickjs::Function>("toString")?
+                .call::<_, String>((rquickjs::function::This(f.clone()),))?;
+            // Assign expressions (arrow fns etc) back to name when re-evaluated
+            if ["function", "async function", "class"]
+                .iter()
+                .any(|p| source.starts_with(p))
+            {
+                source
+            } else {
+                format!("{name} = {source}")
+            }
+        }
+        None => buffer.to_string(),
+    };
+    let path = std::env::temp_dir().join(format!("rquickjs-edit-{}.js", std::process::id()));
+    std::fs::write(&path, source)?;
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let status = std::process::Command::new(&editor).arg(&path).status()?;
+    let script = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    if !status.success() {
+        return Err(anyhow::anyhow!("{editor} exited with {status}"));
+    }
+    Ok(script?)
+}
+
+/// Add line to input buffer - `.edit` is moved to the front so that any
+/// pending multi-line input is passed to the editor
+fn push_line(lines: &mut Vec<String>, line: String) {
+    if line.trim_start().starts_with(".edit") {
+        lines.insert(0, line);
+    } else {
+        lines.push(line);
+    }
+}
+
+/// Check if input is complete (balanced or a REPL command)
+fn input_complete(input: &str) -> bool {
+    input.trim_start().starts_with(".edit") || !needs_more_input(input)
+}
+
+fn format_result<'js>(ctx: Ctx<'js>, v: Value<'js>) -> String {
+    inspect(&ctx, &v, DEFAULT_INSPECT_DEPTH, stdout_color())
+}
+
+/// Basic REPL (no line editing)
+pub async fn repl(ctx: Ctx<'_>, opts: &ReplOptions) -> anyhow::Result<()> {
+    let stdin = tokio::io::stdin();
+    let mut reader = BufReader::new(stdin);
+    let mut session = ReplSession::new(ctx.clone(), opts).await?;
+    loop {
+        let script = read_multiline_input(&mut reader).await?;
+        if !script.is_empty() {
+            session.add_history(&script);
+            if eval_cmd(ctx.clone(), script, &mut session)
+                .await?
+                .is_break()
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// REPL using rustyline (line editing runs on blocking thread)
+#[cfg(feature = "repl_rustyline")]
+pub async fn repl_rustyline(ctx: Ctx<'_>, opts: &ReplOptions) -> anyhow::Result<()> {
+    use rustyline::{error::ReadlineError, Config, DefaultEditor};
+
+    let mut session = ReplSession::new(ctx.clone(), opts).await?;
+    let entries = session.history_entries();
+    let history = session.history.clone();
+
+    let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::channel::<String>(16);
+    let (reply_tx, mut reply_rx) = tokio::sync::mpsc::channel::<()>(16);
+
+    // Spawn blocking task
+    let input_handle = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        // Bracketed paste (enabled by default) inserts pasted blocks
+        // (including newlines) into the line buffer so they are evaluated
+        // once rather than per line
+        let config = Config::builder().history_ignore_dups(true)?.build();
+        let mut rl = DefaultEditor::with_config(config)?;
+        // Ctrl-R reverse-i-search uses rustyline history seeded from shared history
+        for entry in entries {
+            rl.add_history_entry(entry)?;
+        }
+        let mut lines = Vec::new();
+        let mut prompt = PROMPT;
+        loop {
+            match rl.readline(prompt) {
+                Ok(line) => {
+                    push_line(&mut lines, line.to_string());
+                    let cmd = lines.join("\n");
+                    // Check if we need more input (unmatched braces/parens)
+                    if !input_complete(&cmd) {
+                        prompt = MULTILINE_PROMPT;
+                    } else {
+                        if !cmd.is_empty() {
+                            rl.add_history_entry(cmd.as_str())?;
+                            add_history(&history, &cmd);
+                        }
+                        if cmd_tx.blocking_send(cmd).is_err() {
+                            // Channel closed
+                            break;
+                        }
+                        // Wait for reply
+                        if reply_rx.blocking_recv().is_none() {
+                            // Channel closed
+                            break;
+                        }
+                        lines.clear();
+                        prompt = PROMPT;
+                    };
+                }
+                Err(ReadlineError::Interrupted) => {
+                    eprintln!("<CTRL-C>");
+                    break;
+                }
+                Err(ReadlineError::Eof) => {
+                    eprintln!("<CTRL-D>");
+                    break;
+                }
+                Err(e) => {
+                    eprintln!("[-] Readline Error: {:?}", e);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    });
+
+    // Get input cmd
+    while let Some(cmd) = cmd_rx.recv().await {
+        if eval_cmd(ctx.clone(), cmd, &mut session).await?.is_break() {
+            break;
+        }
+        reply_tx.send(()).await?;
+    }
+    // Closing reply channel stops input thread
+    drop(reply_tx);
+
+    let _ = input_handle.await?;
+    Ok(())
+}
+
+/// REPL using rustyline-async
+///
+/// rustyline-async has no bracketed paste support so pasted blocks are
+/// detected by lines arriving within [`PASTE_INTERVAL`] of each other.
+/// It also has no Ctrl-R search - use `.history <query>` instead
+#[cfg(feature = "repl_rustyline_async")]
+pub async fn repl_rustyline_async(ctx: Ctx<'_>, opts: &ReplOptions) -> anyhow::Result<()> {
+    use rustyline_async::{Readline, ReadlineEvent};
+
+    let (mut rl, _stdout) = Readline::new(PROMPT.into())?;
+    let mut session = ReplSession::new(ctx.clone(), opts).await?;
+    for entry in session.history_entries() {
+        rl.add_history_entry(entry);
+    }
+    let mut lines = Vec::new();
+    loop {
+        let event = if lines.is_empty() {
+            rl.readline().await?
+        } else {
+            // Buffer lines until input is complete and no further pasted lines arrive
+            match tokio::time::timeout(PASTE_INTERVAL, rl.readline()).await {
+                Ok(event) => event?,
+                Err(_) if input_complete(&lines.join("\n")) => {
+                    let cmd = lines.join("\n");
+                    lines.clear();
+                    rl.update_prompt(PROMPT)?;
+                    if !cmd.trim().is_empty() {
+                        rl.add_history_entry(cmd.clone());
+                        session.add_history(&cmd);
+                        if eval_cmd(ctx.clone(), cmd, &mut session).await?.is_break() {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                Err(_) => rl.readline().await?,
+            }
+        };
+        match event {
+            ReadlineEvent::Line(line) => {
+                push_line(&mut lines, line);
+                if !input_complete(&lines.join("\n")) {
+                    rl.update_prompt(MULTILINE_PROMPT)?;
+                }
+            }
+            ReadlineEvent::Interrupted => {
+                eprintln!("<CTRL-C>");
+                break;
+            }
+            ReadlineEvent::Eof => {
+                eprintln!("<CTRL-D>");
+                break;
+            }
+        }
+    }
+    rl.flush()?;
+    Ok(())
+}
+
+async fn read_multiline_input(reader: &mut BufReader<tokio::io::Stdin>) -> anyhow::Result<String> {
+    let mut lines = Vec::new();
+    let mut buffer = String::new();
+
+    loop {
+        let prompt = if lines.is_empty() {
+            PROMPT
+        } else {
+            MULTILINE_PROMPT
+        };
+        print!("{}", prompt);
+        std::io::stdout().flush()?;
+
+        buffer.clear();
+        reader.read_line(&mut buffer).await?;
+        let line = buffer.trim_end();
+
+        push_line(&mut lines, line.to_string());
+
+        let full_input = lines.join("\n");
+        // Check if we need more input (unmatched braces/parens)
+        if input_complete(&full_input) {
+            return Ok(full_input);
+        }
+    }
+}
+
+/// Keywords after which `/` starts a regex literal rather than division
+const REGEX_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "do",
+    "else",
+    "yield",
+    "await",
+];
+
+/// Keywords whose parenthesised header is followed by a statement (so `/`
+/// after the closing `)` starts a regex)
+const HEADER_KEYWORDS: &[&str] = &["if", "while", "for", "with"];
+
+/// Check if input is incomplete (unclosed brackets, template literal or
+/// block comment)
+///
+/// Uses a minimal JS lexer so brackets inside strings, template literals,
+/// regex literals and comments are ignored. Syntax errors (unbalanced
+/// close, unterminated string/regex) return false so the error is reported
+/// by the engine.
+pub fn needs_more_input(input: &str) -> bool {
+    // Open brackets - '`' marks template literal text, '$' a `${` substitution
+    // and 'h' the `(` of an `if`/`while`/`for` header
+    let mut stack: Vec<char> = Vec::new();
+    let mut chars = input.chars().peekable();
+    // Whether a `/` here starts a regex (ie. not after a value)
+    let mut regex_allowed = true;
+    // Whether the previous token was a header keyword
+    let mut header_keyword = false;
+
+    loop {
+        if stack.last() == Some(&'`') {
+            match chars.next() {
+                None => return true,
+                Some('\\') => {
+                    chars.next();
+                }
+                Some('`') => {
+                    stack.pop();
+                    regex_allowed = false;
+                }
+                Some('$') if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    stack.push('$');
+                    regex_allowed = true;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let Some(c) = chars.next() else {
+            break;
+        };
+        let header = std::mem::take(&mut header_keyword);
+        match c {
+            c if c.is_whitespace() => header_keyword = header,
+            '/' if chars.peek() == Some(&'/') => {
+                // Line comment
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                // Block comment
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        None => return true,
+                        Some('/') if prev == '*' => break,
+                        Some(c) => prev = c,
+                    }
+                }
+            }
+            '/' if regex_allowed => {
+                // Regex literal ('/' is allowed unescaped in a [class])
+                let mut class = false;
+                loop {
+                    match chars.next() {
+                        None | Some('\n') => return false,
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some('[') => class = true,
+                        Some(']') => class = false,
+                        Some('/') if !class => break,
+                        _ => {}
+                    }
+                }
+                regex_allowed = false;
+            }
+            '\'' | '"' => {
+                loop {
+                    match chars.next() {
+                        None | Some('\n') => return false,
+                        // Line continuation
+                        Some('\\') if chars.peek().is_none() => return true,
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some(q) if q == c => break,
+                        _ => {}
+                    }
+                }
+                regex_allowed = false;
+            }
+            '`' => stack.push('`'),
+            '(' if header => {
+                stack.push('h');
+                regex_allowed = true;
+            }
+            '(' | '[' | '{' => {
+                stack.push(c);
+                regex_allowed = true;
+            }
+            ')' => match stack.pop() {
+                Some('(') => regex_allowed = false,
+                // End of header - a statement (possibly a regex) follows
+                Some('h') => regex_allowed = true,
+                _ => return false,
+            },
+            ']' => {
+                if stack.pop() != Some('[') {
+                    return false;
+                }
+                regex_allowed = false;
+            }
+            '}' => match stack.pop() {
+                Some('{') => regex_allowed = true,
+                // End of substitution - back in template literal
+                Some('$') => {}
+                _ => return false,
+            },
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_' || c == '$')
+                {
+                    word.push(c);
+                }
+                regex_allowed = REGEX_KEYWORDS.contains(&word.as_str());
+                header_keyword = HEADER_KEYWORDS.contains(&word.as_str());
+            }
+            '+' | '-' if chars.peek() == Some(&c) => {
+                // Postfix ++/-- (prefix followed by `/` is not valid anyway)
+                chars.next();
+                regex_allowed = false;
+            }
+            _ => regex_allowed = true,
+        }
+    }
+    !stack.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::needs_more_input;
+
+    #[test]
+    fn regex_after_header() {
+        assert!(!needs_more_input("if (x) /[(]/.test(s)"));
+        assert!(!needs_more_input("while (i < n) /{/.exec(s)"));
+        assert!(!needs_more_input("for (const s of a) /\\(/.test(s)"));
+        assert!(needs_more_input("if (x) /[}]/.test(s) && {"));
+    }
+
+    #[test]
+    fn division_after_call() {
+        assert!(!needs_more_input("f(x) / 2"));
+        assert!(!needs_more_input("(a + b) / (c + d)"));
+        assert!(needs_more_input("f(a) / g("));
+    }
+
+    #[test]
+    fn brackets_in_literals() {
+        assert!(!needs_more_input("'{' + \"(\" + `[${1}`"));
+        assert!(!needs_more_input("x = /[/]/ // {"));
+        assert!(needs_more_input("/* { */ function f() {"));
+        assert!(needs_more_input("`${"));
+    }
+
+    #[test]
+    fn unbalanced_close_is_complete() {
+        assert!(!needs_more_input("f())"));
+        assert!(!needs_more_input("}"));
+    }
+}
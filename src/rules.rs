@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use rquickjs::{CatchResultExt, Context, Function, Persistent, Runtime, Value};
+
+use crate::compat::Instant;
+use crate::convert::{json_to_value, value_to_json};
+use crate::error::{error_kind, ErrorKind, JsError};
+use crate::run::ScriptDeadline;
+
+/// Default limit on the synchronous run time of a single rule
+pub const DEFAULT_RULE_TIMEOUT: Duration = Duration::from_secs(1);
+
+struct Rule {
+    name: String,
+    f: Persistent<Function<'static>>,
+    // Own context so rules can't see each other's globals
+    ctx: Context,
+}
+
+/// Result of evaluating one rule
+#[derive(Debug)]
+pub struct RuleResult {
+    pub name: String,
+    /// Rule value (errors are isolated to the failing rule)
+    pub value: anyhow::Result<serde_json::Value>,
+}
+
+impl RuleResult {
+    /// Rule evaluated to a truthy value
+    pub fn matched(&self) -> bool {
+        match &self.value {
+            Ok(serde_json::Value::Null) | Err(_) => false,
+            Ok(serde_json::Value::Bool(b)) => *b,
+            Ok(serde_json::Value::Number(n)) => n.as_f64().is_some_and(|n| n != 0.0),
+            Ok(serde_json::Value::String(s)) => !s.is_empty(),
+            Ok(_) => true,
+        }
+    }
+}
+
+/// Named JS expressions evaluated against a JSON document
+///
+/// Expressions are compiled once - document fields are in scope as
+/// variables (and the whole document as `doc`):
+///
+/// ```ignore
+/// let rules = RuleSet::compile(vec![
+///     ("hot", "temp > 30"),
+///     ("topic", "`${site}/${doc.sensor.id}`"),
+/// ])?;
+/// for r in rules.evaluate(&event)? {
+///     println!("{}: {:?}", r.name, r.value);
+/// }
+/// ```
+///
+/// Each rule has its own context with a frozen global object and gets its
+/// own copy of the document, so rules can't affect each other (or later
+/// evaluations). Rules running longer than the timeout
+/// ([`DEFAULT_RULE_TIMEOUT`] unless set with [`RuleSet::with_timeout`])
+/// fail with [`ErrorKind::Timeout`].
+pub struct RuleSet {
+    // Persistent fns must be dropped before the runtime
+    rules: Vec<Rule>,
+    deadline: ScriptDeadline,
+    timeout: Duration,
+    _rt: Runtime,
+}
+
+impl RuleSet {
+    /// Compile `(name, expression)` rules - fails on the first rule with a
+    /// syntax error
+    pub fn compile<N, E>(rules: Vec<(N, E)>) -> anyhow::Result<Self>
+    where
+        N: Into<String>,
+        E: AsRef<str>,
+    {
+        let rt = Runtime::new()?;
+        let deadline = ScriptDeadline::default();
+        rt.set_interrupt_handler(Some(deadline.interrupt_handler()));
+        let rules = rules
+            .into_iter()
+            .map(|(name, expr)| {
+                let name = name.into();
+                let ctx = Context::full(&rt)?;
+                // Newline so a trailing line comment can't swallow the closing brackets
+                let source = format!(
+                    "(function (doc) {{ with (Object(doc)) {{ return ({}\n); }} }})",
+                    expr.as_ref()
+                );
+                let f = ctx.with(|ctx| {
+                    let f = ctx.eval::<Function, _>(source).catch(&ctx).map_err(|e| {
+                        anyhow::Error::new(JsError::from_caught(&e)).context(format!("Rule {name}"))
+                    })?;
+                    // Assignments to globals are then ignored (rules are sloppy mode)
+                    ctx.eval::<(), _>("Object.freeze(globalThis)")?;
+                    Ok::<_, anyhow::Error>(Persistent::save(&ctx, f))
+                })?;
+                Ok(Rule { name, f, ctx })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            deadline,
+            timeout: DEFAULT_RULE_TIMEOUT,
+            _rt: rt,
+        })
+    }
+
+    /// Set limit on the synchronous run time of each rule
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Rule names (in evaluation order)
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.name.as_str())
+    }
+
+    /// Evaluate all rules against `doc` (converted to JS for each rule)
+    pub fn evaluate(&self, doc: &serde_json::Value) -> anyhow::Result<Vec<RuleResult>> {
+        let doc = doc.to_string();
+        Ok(self
+            .rules
+            .iter()
+            .map(|rule| RuleResult {
+                name: rule.name.clone(),
+                value: self.evaluate_rule(rule, &doc),
+            })
+            .collect())
+    }
+
+    fn evaluate_rule(&self, rule: &Rule, doc: &str) -> anyhow::Result<serde_json::Value> {
+        rule.ctx.with(|ctx| {
+            let doc = json_to_value(ctx.clone(), doc)?;
+            self.deadline.set(Some(Instant::now() + self.timeout));
+            let value = rule
+                .f
+                .clone()
+                .restore(&ctx)
+                .and_then(|f| f.call::<_, Value>((doc,)))
+                .catch(&ctx);
+            self.deadline.set(None);
+            let value = value.map_err(|e| anyhow::Error::new(JsError::from_caught(&e)));
+            let value = match value {
+                Err(e) if error_kind(&e) == ErrorKind::Timeout => {
+                    return Err(e.context(format!("Rule timed out after {:?}", self.timeout)));
+                }
+                value => value?,
+            };
+            Ok(serde_json::from_str(&value_to_json(ctx.clone(), value)?)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fields_in_scope() {
+        let rules = RuleSet::compile(vec![("hot", "temp > 30"), ("id", "doc.sensor.id")]).unwrap();
+        let out = rules
+            .evaluate(&json!({ "temp": 35, "sensor": { "id": "a" } }))
+            .unwrap();
+        assert!(out[0].matched());
+        assert_eq!(out[1].value.as_ref().unwrap(), &json!("a"));
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        assert!(RuleSet::compile(vec![("bad", "temp >")]).is_err());
+    }
+
+    #[test]
+    fn test_errors_isolated() {
+        let rules = RuleSet::compile(vec![("throws", "missing.field"), ("ok", "1")]).unwrap();
+        let out = rules.evaluate(&json!({})).unwrap();
+        assert!(out[0].value.is_err());
+        assert_eq!(out[1].value.as_ref().unwrap(), &json!(1));
+    }
+
+    #[test]
+    fn test_globals_isolated() {
+        let rules = RuleSet::compile(vec![
+            ("set", "(globalThis.leak = 1, leaked = 2, 0)"),
+            ("get", "typeof leak + typeof leaked"),
+        ])
+        .unwrap();
+        for _ in 0..2 {
+            let out = rules.evaluate(&json!({})).unwrap();
+            assert_eq!(out[1].value.as_ref().unwrap(), &json!("undefinedundefined"));
+        }
+        // Frozen globals also keep evaluations of the same rule independent
+        let rules =
+            RuleSet::compile(vec![("count", "(n = (typeof n == 'number' ? n : 0) + 1)")]).unwrap();
+        for _ in 0..2 {
+            let out = rules.evaluate(&json!({})).unwrap();
+            assert_eq!(out[0].value.as_ref().unwrap(), &json!(1));
+        }
+    }
+
+    #[test]
+    fn test_doc_not_shared() {
+        let rules = RuleSet::compile(vec![
+            ("mutate", "(doc.a.b = 2, delete doc.c, a.b)"),
+            ("read", "[a.b, c]"),
+        ])
+        .unwrap();
+        let out = rules.evaluate(&json!({ "a": { "b": 1 }, "c": 3 })).unwrap();
+        assert_eq!(out[0].value.as_ref().unwrap(), &json!(2));
+        assert_eq!(out[1].value.as_ref().unwrap(), &json!([1, 3]));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let rules = RuleSet::compile(vec![("spin", "(() => { for (;;) {} })()"), ("ok", "1")])
+            .unwrap()
+            .with_timeout(Duration::from_millis(50));
+        let out = rules.evaluate(&json!({})).unwrap();
+        let e = out[0].value.as_ref().unwrap_err();
+        assert_eq!(error_kind(e), ErrorKind::Timeout);
+        assert_eq!(out[1].value.as_ref().unwrap(), &json!(1));
+    }
+}
@@ -1,18 +1,41 @@
-use std::io::{Read, Write};
+use std::cell::Cell;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use std::time::Duration;
 
-use anyhow::anyhow;
-use rquickjs::{prelude::IntoArgs, CatchResultExt, Ctx, Module, Value};
-use tokio::io::{AsyncBufReadExt, BufReader};
+use rquickjs::{
+    function::Opt, prelude::IntoArgs, runtime::InterruptHandler, Ctx, Exception, JsLifetime, Value,
+};
+#[cfg(feature = "sync")]
+use rquickjs::{
+    module::{Declared, WriteOptions},
+    CatchResultExt, CaughtError, Module,
+};
+#[cfg(feature = "async")]
+use rquickjs::{AsyncContext, AsyncRuntime};
 
-use crate::util::print_v;
+use crate::compat::Instant;
+#[cfg(feature = "async")]
+use crate::error::{error_kind, ErrorKind};
+#[cfg(feature = "sync")]
+use crate::error::{JsError, ScriptExit};
+#[cfg(feature = "sync")]
+use crate::source::{eval_script, register_source};
 
 /// Expand script arg to handle literal script, @file or stdin (-)
+///
+/// With the `bundle` feature `@file.enc` is decrypted using the bundle key
 pub fn get_script(script: &str) -> anyhow::Result<String> {
     Ok(if script == "-" {
         let mut s = String::new();
         std::io::stdin().read_to_string(&mut s)?;
         s
     } else if script.starts_with("@") {
+        #[cfg(feature = "bundle")]
+        if script.ends_with(crate::bundle::BUNDLE_EXT) {
+            return crate::bundle::read_bundle(&script[1..]);
+        }
         std::fs::read_to_string(&script[1..])?
     } else {
         script.to_string()
@@ -20,143 +43,277 @@ pub fn get_script(script: &str) -> anyhow::Result<String> {
 }
 
 /// Run as script
+#[cfg(feature = "async")]
 pub async fn run_script<'js>(ctx: Ctx<'js>, script: String) -> anyhow::Result<Value<'js>> {
-    match ctx.eval::<rquickjs::Value, _>(script) {
+    match eval_script::<rquickjs::Value>(&ctx, script) {
         Ok(v) => Ok(v),
-        Err(e) => {
-            if let Ok(ex) = rquickjs::Exception::from_value(ctx.catch()) {
-                Err(anyhow!(
-                    "{}\n{}",
-                    ex.message().unwrap_or("-".into()),
-                    ex.stack().unwrap_or("-".into())
-                ))
-            } else {
-                Err(anyhow!("JS Error: {e}"))
-            }
+        Err(e) => Err(script_error(&ctx, e)),
+    }
+}
+
+/// Deadline checked by the runtime interrupt handler (stored as context
+/// userdata by [`install_timeout_handler`])
+#[derive(Clone, Default, JsLifetime)]
+pub struct ScriptDeadline(Arc<Mutex<Option<Instant>>>);
+
+impl ScriptDeadline {
+    /// Set/clear deadline
+    pub fn set(&self, deadline: Option<Instant>) {
+        if let Ok(mut d) = self.0.lock() {
+            *d = deadline;
+        }
+    }
+
+    /// Interrupt handler aborting execution once the deadline has passed
+    pub fn interrupt_handler(&self) -> InterruptHandler {
+        let deadline = self.0.clone();
+        Box::new(move || {
+            deadline
+                .lock()
+                .ok()
+                .and_then(|d| *d)
+                .is_some_and(|d| Instant::now() >= d)
+        })
+    }
+}
+
+/// Install interrupt handler used by [`run_script_with_timeout`]
+///
+/// Replaces any existing interrupt handler on the runtime
+#[cfg(feature = "async")]
+pub async fn install_timeout_handler(rt: &AsyncRuntime, ctx: &AsyncContext) -> anyhow::Result<()> {
+    let deadline = ScriptDeadline::default();
+    rt.set_interrupt_handler(Some(deadline.interrupt_handler()))
+        .await;
+    ctx.with(|ctx| {
+        ctx.store_userdata(deadline)
+            .map(|_| ())
+            .map_err(|_| anyhow::anyhow!("Failed to store script deadline"))
+    })
+    .await
+}
+
+/// Run as script aborting (with [`ErrorKind::Timeout`]) if synchronous
+/// execution doesn't complete within `timeout`
+///
+/// Requires [`install_timeout_handler`] - note only the initial evaluation
+/// is limited, not promise jobs/host futures started by the script
+#[cfg(feature = "async")]
+pub async fn run_script_with_timeout<'js>(
+    ctx: Ctx<'js>,
+    script: String,
+    timeout: Duration,
+) -> anyhow::Result<Value<'js>> {
+    let deadline = ctx
+        .userdata::<ScriptDeadline>()
+        .map(|d| (*d).clone())
+        .ok_or(anyhow::anyhow!(
+            "Timeout handler not installed (see install_timeout_handler)"
+        ))?;
+    deadline.set(Some(Instant::now() + timeout));
+    let result = run_script(ctx, script).await;
+    deadline.set(None);
+    result.map_err(|e| match error_kind(&e) {
+        ErrorKind::Timeout => e.context(format!("Script timed out after {timeout:?}")),
+        _ => e,
+    })
+}
+
+/// Exit code set by `exit()` (context userdata, stored up front by
+/// [`register_exit_code`] and set in place)
+#[derive(Default, JsLifetime)]
+struct ExitCode(Cell<Option<i32>>);
+
+/// Register `exit(code = 0)` global - stops the script with an uncatchable
+/// error returned by [`run_script`]/[`run_module`] as [`ScriptExit`]
+pub fn register_exit(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    register_exit_code(ctx)?;
+    ctx.globals().set("exit", js_exit)?;
+    Ok(())
+}
+
+/// Store the exit code userdata used by `exit()` (no-op if already stored)
+pub(crate) fn register_exit_code(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    if ctx.userdata::<ExitCode>().is_none() {
+        ctx.store_userdata(ExitCode::default())
+            .map_err(|_| anyhow::anyhow!("Failed to store exit code"))?;
+    }
+    Ok(())
+}
+
+#[rquickjs::function]
+pub(crate) fn exit<'js>(ctx: Ctx<'js>, code: Opt<i32>) -> rquickjs::Result<()> {
+    let code = code.0.unwrap_or(0);
+    match ctx.userdata::<ExitCode>() {
+        Some(exit) => exit.0.set(Some(code)),
+        None => {
+            ctx.store_userdata(ExitCode(Cell::new(Some(code))))
+                .map_err(|_| Exception::throw_internal(&ctx, "Failed to store exit code"))?;
         }
     }
+    #[cfg(feature = "async")]
+    crate::timers::clear_timers(&ctx);
+    let ex = Exception::from_message(ctx.clone(), "exit")?;
+    // SAFETY: ctx and exception value are live
+    unsafe { rquickjs::qjs::JS_SetUncatchableError(ctx.as_raw().as_ptr(), ex.as_raw()) };
+    Err(ctx.throw(ex.into_value()))
+}
+
+/// `exit()` has been called (exit code not yet taken)
+pub(crate) fn exit_requested(ctx: &Ctx<'_>) -> bool {
+    ctx.userdata::<ExitCode>()
+        .is_some_and(|exit| exit.0.get().is_some())
+}
+
+/// Take exit code set by `exit()` (eg. from a timer callback after
+/// [`run_script`] returned)
+pub fn take_exit_code(ctx: &Ctx<'_>) -> Option<i32> {
+    ctx.userdata::<ExitCode>().and_then(|exit| exit.0.take())
+}
+
+/// Convert error from `eval` (`exit()` is returned as [`ScriptExit`])
+#[cfg(feature = "sync")]
+pub(crate) fn script_error(ctx: &Ctx<'_>, e: rquickjs::Error) -> anyhow::Error {
+    let caught = ctx.catch();
+    if let Some(code) = take_exit_code(ctx) {
+        ScriptExit(code).into()
+    } else if let Ok(ex) = Exception::from_value(caught) {
+        JsError::from_exception(&ex).into()
+    } else {
+        anyhow::anyhow!("JS Error: {e}")
+    }
+}
+
+/// Convert caught error (`exit()` is returned as [`ScriptExit`])
+#[cfg(feature = "sync")]
+fn caught_error(ctx: &Ctx<'_>, e: CaughtError<'_>, stage: &str) -> anyhow::Error {
+    match take_exit_code(ctx) {
+        Some(code) => ScriptExit(code).into(),
+        None => anyhow::Error::new(JsError::from_caught(&e)).context(format!("JS error [{stage}]")),
+    }
 }
 
-/// Run as module
-pub async fn run_module(ctx: Ctx<'_>, module: String) -> anyhow::Result<()> {
+/// Global bound to the result of the previous CLI script/module
+pub const PREV: &str = "$prev";
+
+/// Run as module (returns default export or undefined)
+#[cfg(feature = "async")]
+pub async fn run_module<'js>(ctx: Ctx<'js>, module: String) -> anyhow::Result<Value<'js>> {
     // Declare module
+    register_source(&ctx, "main.mjs", &module);
     let module = Module::declare(ctx.clone(), "main.mjs", module)
         .catch(&ctx)
-        .map_err(|e| anyhow!("JS error [declare]: {}", e))?;
+        .map_err(|e| anyhow::Error::new(JsError::from_caught(&e)).context("JS error [declare]"))?;
+    eval_module(ctx, module).await
+}
 
+/// Run module compiled with [`compile_module`]
+///
+/// # Safety
+///
+/// `bytes` must be trusted - see [`load_compiled_module`]
+#[cfg(feature = "async")]
+pub async unsafe fn run_compiled_module<'js>(
+    ctx: Ctx<'js>,
+    bytes: &[u8],
+) -> anyhow::Result<Value<'js>> {
+    // SAFETY: caller guarantees bytes are trusted
+    let module = unsafe { load_compiled_module(ctx.clone(), bytes)? };
+    eval_module(ctx, module).await
+}
+
+#[cfg(feature = "async")]
+async fn eval_module<'js>(
+    ctx: Ctx<'js>,
+    module: Module<'js, Declared>,
+) -> anyhow::Result<Value<'js>> {
     // Evaluate module
-    let (_module, promise) = module
+    let (module, promise) = module
         .eval()
         .catch(&ctx)
-        .map_err(|e| anyhow!("JS error [eval]: {}", e))?;
+        .map_err(|e| caught_error(&ctx, e, "eval"))?;
 
     // Complete promise as future
     promise
         .into_future::<()>()
         .await
         .catch(&ctx)
-        .map_err(|e| anyhow!("JS error [await]: {}", e))?;
+        .map_err(|e| caught_error(&ctx, e, "await"))?;
 
-    Ok(())
+    Ok(module.get("default").unwrap_or(Value::new_undefined(ctx)))
 }
 
-/// Basic REPL (no line editing)
-pub async fn repl(ctx: Ctx<'_>) -> anyhow::Result<()> {
-    let stdin = tokio::io::stdin();
-    let mut reader = BufReader::new(stdin);
-    loop {
-        let script = read_multiline_input(&mut reader).await?;
-        if !script.is_empty() {
-            match run_script(ctx.clone(), script).await {
-                Ok(v) => {
-                    if !v.is_undefined() {
-                        ctx.globals().set("_", v.clone())?;
-                        let _ = print_v(ctx.clone(), v);
-                    }
-                }
-                Err(e) => eprintln!("{e}"),
-            }
-        }
-    }
+/// Header of [`compile_module`] output (followed by the rquickjs version the
+/// bytecode was written by and a NUL)
+#[cfg(feature = "sync")]
+pub const BYTECODE_MAGIC: &[u8] = b"RQJSC\0";
+
+/// Extension used for compiled modules
+pub const BYTECODE_EXT: &str = ".qjsc";
+
+/// Compile module source to QuickJS bytecode
+///
+/// `name` is the module name used to resolve relative imports when the
+/// compiled module is loaded (eg. the source path)
+#[cfg(feature = "sync")]
+pub fn compile_module(ctx: &Ctx<'_>, name: &str, source: String) -> anyhow::Result<Vec<u8>> {
+    let module = Module::declare(ctx.clone(), name, source)
+        .catch(ctx)
+        .map_err(|e| anyhow::Error::new(JsError::from_caught(&e)).context("JS error [compile]"))?;
+    let bytecode = module.write(WriteOptions::default())?;
+    let mut out = BYTECODE_MAGIC.to_vec();
+    out.extend_from_slice(crate::host::RQUICKJS_VERSION.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&bytecode);
+    Ok(out)
 }
 
-/// REPL
-const PROMPT: &str = ">>> ";
-const MULTILINE_PROMPT: &str = "... ";
-
-pub async fn repl_rl(ctx: Ctx<'_>) -> anyhow::Result<()> {
-    use rustyline::{error::ReadlineError, DefaultEditor};
-
-    let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::channel::<String>(16);
-    let (reply_tx, mut reply_rx) = tokio::sync::mpsc::channel::<()>(16);
-
-    // Spawn blocking task
-    let input_handle = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-        let mut rl = DefaultEditor::new()?;
-        let mut lines = Vec::new();
-        let mut prompt = PROMPT;
-        loop {
-            match rl.readline(prompt) {
-                Ok(line) => {
-                    lines.push(line.to_string());
-                    let cmd = lines.join("\n");
-                    // Check if we need more input (unmatched braces/parens)
-                    if needs_more_input(&cmd) {
-                        prompt = MULTILINE_PROMPT;
-                    } else {
-                        if !cmd.is_empty() {
-                            rl.add_history_entry(cmd.as_str())?;
-                        }
-                        if cmd_tx.blocking_send(cmd).is_err() {
-                            // Channel closed
-                            break;
-                        }
-                        // Wait for reply
-                        if reply_rx.blocking_recv().is_none() {
-                            // Channel closed
-                            break;
-                        }
-                        lines.clear();
-                        prompt = PROMPT;
-                    };
-                }
-                Err(ReadlineError::Interrupted) => {
-                    eprintln!("<CTRL-C>");
-                    break;
-                }
-                Err(ReadlineError::Eof) => {
-                    eprintln!("<CTRL-D>");
-                    break;
-                }
-                Err(e) => {
-                    eprintln!("[-] Readline Error: {:?}", e);
-                    break;
-                }
-            }
-        }
-        Ok(())
-    });
-
-    // Get input cmd
-    while let Some(cmd) = cmd_rx.recv().await {
-        match run_script(ctx.clone(), cmd).await {
-            Ok(v) => {
-                if !v.is_undefined() {
-                    ctx.globals().set("_", v.clone())?;
-                    let _ = print_v(ctx.clone(), v);
-                }
-            }
-            Err(e) => eprintln!("[-] Command Channel: {e}"),
-        }
-        reply_tx.send(()).await?;
+/// Load module compiled with [`compile_module`] (not evaluated)
+///
+/// Bytecode is only compatible with the QuickJS build that wrote it so the
+/// header is checked before handing it to the engine
+///
+/// # Safety
+///
+/// QuickJS doesn't validate bytecode - corrupted or crafted input can cause
+/// memory unsafety. `bytes` must be output of [`compile_module`] from a
+/// trusted source (the header only guards against version mismatches).
+#[cfg(feature = "sync")]
+pub unsafe fn load_compiled_module<'js>(
+    ctx: Ctx<'js>,
+    bytes: &[u8],
+) -> anyhow::Result<Module<'js, Declared>> {
+    let rest = bytes
+        .strip_prefix(BYTECODE_MAGIC)
+        .ok_or(anyhow::anyhow!("Invalid bytecode (bad header)"))?;
+    let end = rest
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(anyhow::anyhow!("Invalid bytecode (bad header)"))?;
+    let version = String::from_utf8_lossy(&rest[..end]);
+    if version != crate::host::RQUICKJS_VERSION {
+        return Err(anyhow::anyhow!(
+            "Bytecode written by rquickjs {version} (running {})",
+            crate::host::RQUICKJS_VERSION
+        ));
     }
-
-    let _ = input_handle.await?;
-    Ok(())
+    // SAFETY: caller guarantees bytes were written by compile_module (and
+    // the header shows it was with this engine version)
+    unsafe { Module::load(ctx.clone(), &rest[end + 1..]) }
+        .catch(&ctx)
+        .map_err(|e| anyhow::Error::new(JsError::from_caught(&e)).context("JS error [load]"))
 }
 
 /// Call JS fn
 pub async fn call_fn<'js, A>(ctx: Ctx<'js>, path: &str, args: A) -> anyhow::Result<Value<'js>>
+where
+    A: IntoArgs<'js>,
+{
+    call_path(ctx, path, args)
+}
+
+/// Call fn at dotted `path` from globals
+fn call_path<'js, A>(ctx: Ctx<'js>, path: &str, args: A) -> anyhow::Result<Value<'js>>
 where
     A: IntoArgs<'js>,
 {
@@ -172,56 +329,82 @@ where
         .call::<A, rquickjs::Value>(args)?)
 }
 
-async fn read_multiline_input(reader: &mut BufReader<tokio::io::Stdin>) -> anyhow::Result<String> {
-    let mut lines = Vec::new();
-    let mut buffer = String::new();
+/// Execute pending jobs (promise reactions) until the queue is empty
+#[cfg(feature = "sync")]
+fn pump_jobs(ctx: &Ctx<'_>) {
+    while ctx.execute_pending_job() {}
+}
 
-    loop {
-        let prompt = if lines.is_empty() { ">>> " } else { "... " };
-        print!("{}", prompt);
-        std::io::stdout().flush()?;
+/// Error for promise still waiting after the job queue is drained (ie. on a
+/// host future which needs the async runtime)
+#[cfg(feature = "sync")]
+fn would_block(stage: &str) -> anyhow::Error {
+    anyhow::anyhow!("JS error [{stage}]: promise pending after draining jobs (use async API)")
+}
 
-        buffer.clear();
-        reader.read_line(&mut buffer).await?;
-        let line = buffer.trim_end();
+/// Run as script without an async runtime (pending jobs are executed
+/// before returning)
+#[cfg(feature = "sync")]
+pub fn run_script_blocking<'js>(ctx: Ctx<'js>, script: String) -> anyhow::Result<Value<'js>> {
+    let v = match eval_script::<rquickjs::Value>(&ctx, script) {
+        Ok(v) => v,
+        Err(e) => return Err(script_error(&ctx, e)),
+    };
+    pump_jobs(&ctx);
+    Ok(v)
+}
 
-        lines.push(line.to_string());
+/// Run as module without an async runtime (returns default export or
+/// undefined)
+///
+/// Fails if top-level await is waiting on a host future
+#[cfg(feature = "sync")]
+pub fn run_module_blocking<'js>(ctx: Ctx<'js>, module: String) -> anyhow::Result<Value<'js>> {
+    register_source(&ctx, "main.mjs", &module);
+    let module = Module::declare(ctx.clone(), "main.mjs", module)
+        .catch(&ctx)
+        .map_err(|e| anyhow::Error::new(JsError::from_caught(&e)).context("JS error [declare]"))?;
 
-        let full_input = lines.join("\n");
-        // Check if we need more input (unmatched braces/parens)
-        if !needs_more_input(&full_input) {
-            return Ok(full_input);
-        }
+    let (module, promise) = module
+        .eval()
+        .catch(&ctx)
+        .map_err(|e| caught_error(&ctx, e, "eval"))?;
+
+    // Promise::finish executes pending jobs until settled
+    match promise.finish::<()>() {
+        Err(rquickjs::Error::WouldBlock) => return Err(would_block("await")),
+        r => r.catch(&ctx).map_err(|e| caught_error(&ctx, e, "await"))?,
     }
+    pump_jobs(&ctx);
+
+    Ok(module.get("default").unwrap_or(Value::new_undefined(ctx)))
 }
 
-fn needs_more_input(input: &str) -> bool {
-    let mut balance = 0i32;
-    let mut chars = input.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        match ch {
-            '{' | '(' | '[' => balance += 1,
-            '}' | ')' | ']' => {
-                balance -= 1;
-                if balance < 0 {
-                    return false;
-                } // Syntax error
-            }
-            '"' | '\'' => {
-                // Skip string literals
-                let quote = ch;
-                while let Some(c) = chars.next() {
-                    if c == '\\' {
-                        // Skip escaped chars
-                        chars.next();
-                    } else if c == quote {
-                        break;
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-    balance > 0
+/// Call JS fn without an async runtime - if the function returns a promise
+/// it is resolved by executing pending jobs
+#[cfg(feature = "sync")]
+pub fn call_fn_blocking<'js, A>(ctx: Ctx<'js>, path: &str, args: A) -> anyhow::Result<Value<'js>>
+where
+    A: IntoArgs<'js>,
+{
+    let v = call_path(ctx.clone(), path, args)?;
+    resolve_blocking(&ctx, v, "call")
+}
+
+/// Resolve value if it is a promise by executing pending jobs
+#[cfg(feature = "sync")]
+pub(crate) fn resolve_blocking<'js>(
+    ctx: &Ctx<'js>,
+    v: Value<'js>,
+    stage: &str,
+) -> anyhow::Result<Value<'js>> {
+    let result = match v.as_promise() {
+        Some(promise) => match promise.finish::<Value>() {
+            Err(rquickjs::Error::WouldBlock) => return Err(would_block(stage)),
+            r => r.catch(ctx).map_err(|e| caught_error(ctx, e, stage))?,
+        },
+        None => v,
+    };
+    pump_jobs(ctx);
+    Ok(result)
 }
@@ -0,0 +1,177 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rquickjs::{context::EvalOptions, Ctx, FromJs, JsLifetime};
+
+/// Sources kept per context - the least recently used source is dropped
+/// beyond this (its errors are shown without a snippet)
+pub const EVAL_SOURCES_CAPACITY: usize = 64;
+
+/// Sources evaluated in a context by filename (context userdata) - used to
+/// show the offending line in error output
+#[derive(Default, JsLifetime)]
+pub struct EvalSources {
+    /// Source and last use (for LRU ordering)
+    sources: RefCell<HashMap<String, (Rc<str>, u64)>>,
+    scripts: Cell<usize>,
+    /// Access counter used for LRU ordering
+    tick: Cell<u64>,
+}
+
+impl EvalSources {
+    fn tick(&self) -> u64 {
+        self.tick.set(self.tick.get() + 1);
+        self.tick.get()
+    }
+
+    fn insert(&self, name: &str, source: &str) {
+        let mut sources = self.sources.borrow_mut();
+        while sources.len() >= EVAL_SOURCES_CAPACITY && !sources.contains_key(name) {
+            let Some(lru) = sources
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            sources.remove(&lru);
+        }
+        sources.insert(name.to_string(), (source.into(), self.tick()));
+    }
+
+    /// Source registered under `name` (marks it as recently used)
+    fn get(&self, name: &str) -> Option<Rc<str>> {
+        let tick = self.tick();
+        let mut sources = self.sources.borrow_mut();
+        let (source, used) = sources.get_mut(name)?;
+        *used = tick;
+        Some(source.clone())
+    }
+}
+
+/// Record `source` under `name` (eval filename or module name) - at most
+/// [`EVAL_SOURCES_CAPACITY`] sources are kept per context
+pub fn register_source(ctx: &Ctx<'_>, name: &str, source: &str) {
+    if ctx.userdata::<EvalSources>().is_none() {
+        let _ = ctx.store_userdata(EvalSources::default());
+    }
+    if let Some(sources) = ctx.userdata::<EvalSources>() {
+        sources.insert(name, source);
+    }
+}
+
+/// Evaluate script under a unique filename (`<script:N>`) registered with
+/// its source (otherwise as `ctx.eval`)
+pub fn eval_script<'js, V: FromJs<'js>>(ctx: &Ctx<'js>, script: String) -> rquickjs::Result<V> {
+    if ctx.userdata::<EvalSources>().is_none() {
+        let _ = ctx.store_userdata(EvalSources::default());
+    }
+    let n = match ctx.userdata::<EvalSources>() {
+        Some(sources) => {
+            sources.scripts.set(sources.scripts.get() + 1);
+            sources.scripts.get()
+        }
+        None => 0,
+    };
+    let name = format!("<script:{n}>");
+    register_source(ctx, &name, &script);
+    ctx.eval_with_options(
+        script,
+        EvalOptions {
+            filename: Some(name),
+            ..Default::default()
+        },
+    )
+}
+
+/// Source line for the innermost frame of `stack` with a caret under the
+/// column, eg.
+///
+/// ```text
+///   --> <script:1>:2:9
+///    |
+///  2 | let x = y.z;
+///    |         ^
+/// ```
+///
+/// Only sources registered with [`register_source`] (evaluated scripts and
+/// modules read by [`crate::loader::FileLoader`]) are shown - the stack is
+/// script controlled, so names in it are never read from disk
+pub fn source_snippet(ctx: &Ctx<'_>, stack: &str) -> Option<String> {
+    let (name, line, col) = stack.lines().find_map(frame_location)?;
+    let source = ctx.userdata::<EvalSources>()?.get(name)?;
+    let text = source.lines().nth(line.checked_sub(1)?)?;
+    let number = line.to_string();
+    let pad = " ".repeat(number.len());
+    // Keep tabs so the caret lines up with the source line
+    let indent = text
+        .chars()
+        .take(col.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    Some(format!(
+        "{pad}--> {name}:{line}:{col}\n{pad} |\n{number} | {text}\n{pad} | {indent}^"
+    ))
+}
+
+/// `(file, line, column)` from a stack frame (`at fn (file:line:col)` or
+/// `at file:line:col`)
+fn frame_location(frame: &str) -> Option<(&str, usize, usize)> {
+    let location = frame.trim().strip_prefix("at ")?;
+    let location = match location.rfind('(') {
+        Some(i) => location[i + 1..].strip_suffix(')')?,
+        None => location,
+    };
+    let mut parts = location.rsplitn(3, ':');
+    let col = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    Some((parts.next()?, line, col))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rquickjs::{Context, Runtime};
+
+    #[test]
+    fn snippet_from_registered_source() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            register_source(&ctx, "a.js", "let x = 1;\nlet y = x.z.w;");
+            let snippet = source_snippet(&ctx, "Error\n    at <eval> (a.js:2:11)").unwrap();
+            assert!(snippet.contains("2 | let y = x.z.w;"), "{snippet}");
+            assert!(snippet.ends_with("|           ^"), "{snippet}");
+        });
+    }
+
+    #[test]
+    fn unregistered_paths_not_read() {
+        let path = std::env::temp_dir().join(format!("rquickjs-source-{}", std::process::id()));
+        std::fs::write(&path, "secret").unwrap();
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            let stack = format!("    at {}:1:1", path.display());
+            assert_eq!(source_snippet(&ctx, &stack), None);
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_source_dropped() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            for i in 0..EVAL_SOURCES_CAPACITY {
+                register_source(&ctx, &format!("{i}.js"), "x");
+            }
+            assert!(source_snippet(&ctx, "at 0.js:1:1").is_some());
+            register_source(&ctx, "new.js", "x");
+            assert!(source_snippet(&ctx, "at 0.js:1:1").is_some());
+            assert!(source_snippet(&ctx, "at 1.js:1:1").is_none());
+            assert!(source_snippet(&ctx, "at new.js:1:1").is_some());
+        });
+    }
+}
@@ -0,0 +1,168 @@
+use rquickjs::{
+    class::{Trace, Tracer},
+    function::Rest,
+    ArrayBuffer, Class, Ctx, Exception, IntoJs, JsLifetime, TypedArray, Value,
+};
+
+use crate::class::define_class;
+
+/// SQL parameter bound by the host (SQLite storage classes)
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlParam {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl<'js> Trace<'js> for SqlParam {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+impl<'js> IntoJs<'js> for SqlParam {
+    fn into_js(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        match self {
+            SqlParam::Null => Ok(Value::new_null(ctx.clone())),
+            SqlParam::Integer(i) => i.into_js(ctx),
+            SqlParam::Real(f) => f.into_js(ctx),
+            SqlParam::Text(s) => s.into_js(ctx),
+            SqlParam::Blob(b) => ArrayBuffer::new(ctx.clone(), b)?.into_js(ctx),
+        }
+    }
+}
+
+/// Parameterised query created by the `sql` template tag
+///
+/// Interpolated values are never spliced into the SQL text - each becomes
+/// a `?` placeholder with the value in [`SqlQuery::params`]. Arrays expand
+/// to a placeholder list (for `IN (...)`) and nested `sql` fragments are
+/// inlined with their params:
+///
+/// ```ignore
+/// const q = sql`select * from t where id in (${ids}) and ${filter}`;
+/// q.text    // "select * from t where id in (?, ?) and x = ?"
+/// q.params  // [1, 2, 3]
+/// ```
+#[derive(Debug, Clone, Trace, JsLifetime)]
+#[rquickjs::class]
+pub struct SqlQuery {
+    /// SQL text with `?` placeholders
+    #[qjs(get)]
+    text: String,
+    params: Vec<SqlParam>,
+}
+
+#[rquickjs::methods]
+impl SqlQuery {
+    /// Bound parameter values (in placeholder order)
+    #[qjs(get, rename = "params")]
+    pub fn js_params(&self) -> Vec<SqlParam> {
+        self.params.clone()
+    }
+}
+
+impl SqlQuery {
+    /// SQL text with `?` placeholders
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Parameters in placeholder order
+    pub fn params(&self) -> &[SqlParam] {
+        &self.params
+    }
+}
+
+/// Register `sql` template tag and `SqlQuery` class
+pub fn register_sql(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    define_class::<SqlQuery>(ctx)?;
+    ctx.globals().set("sql", js_sql)?;
+    Ok(())
+}
+
+/// sql`...` template tag
+#[rquickjs::function]
+fn sql<'js>(
+    ctx: Ctx<'js>,
+    strings: Vec<String>,
+    values: Rest<Value<'js>>,
+) -> rquickjs::Result<Class<'js, SqlQuery>> {
+    if strings.len() != values.len() + 1 {
+        return Err(Exception::throw_type(
+            &ctx,
+            "sql must be used as a template tag",
+        ));
+    }
+    let mut query = SqlQuery {
+        text: String::new(),
+        params: Vec::new(),
+    };
+    for (i, s) in strings.iter().enumerate() {
+        query.text.push_str(s);
+        if let Some(v) = values.get(i) {
+            bind(&ctx, &mut query, v)?;
+        }
+    }
+    Class::instance(ctx, query)
+}
+
+/// Append placeholder(s) for interpolated value
+fn bind<'js>(ctx: &Ctx<'js>, query: &mut SqlQuery, v: &Value<'js>) -> rquickjs::Result<()> {
+    if let Some(fragment) = v.as_object().and_then(Class::<SqlQuery>::from_object) {
+        let fragment = fragment.borrow();
+        query.text.push_str(&fragment.text);
+        query.params.extend(fragment.params.iter().cloned());
+    } else if let Some(items) = v.as_array() {
+        // Empty list -> `IN (NULL)` which matches nothing
+        if items.is_empty() {
+            query.text.push_str("NULL");
+        }
+        for (i, item) in items.iter::<Value>().enumerate() {
+            if i > 0 {
+                query.text.push_str(", ");
+            }
+            query.text.push('?');
+            query.params.push(param(ctx, &item?)?);
+        }
+    } else {
+        query.text.push('?');
+        query.params.push(param(ctx, v)?);
+    }
+    Ok(())
+}
+
+/// Convert JS value to bound parameter
+fn param<'js>(ctx: &Ctx<'js>, v: &Value<'js>) -> rquickjs::Result<SqlParam> {
+    if v.is_null() || v.is_undefined() {
+        return Ok(SqlParam::Null);
+    }
+    if let Some(b) = v.as_bool() {
+        return Ok(SqlParam::Integer(b.into()));
+    }
+    if let Some(i) = v.as_int() {
+        return Ok(SqlParam::Integer(i.into()));
+    }
+    if let Some(f) = v.as_float() {
+        return Ok(SqlParam::Real(f));
+    }
+    if let Some(s) = v.as_string() {
+        return Ok(SqlParam::Text(s.to_string()?));
+    }
+    if let Some(b) = v.clone().into_big_int() {
+        return Ok(SqlParam::Integer(b.to_i64()?));
+    }
+    let bytes = match ArrayBuffer::from_value(v.clone()) {
+        Some(buf) => buf.as_bytes().map(<[u8]>::to_vec),
+        None => TypedArray::<u8>::from_value(v.clone())
+            .ok()
+            .and_then(|a| a.as_bytes().map(<[u8]>::to_vec)),
+    };
+    if let Some(bytes) = bytes {
+        return Ok(SqlParam::Blob(bytes));
+    }
+    Err(Exception::throw_type(
+        ctx,
+        &format!("Unsupported sql parameter type: {}", v.type_name()),
+    ))
+}
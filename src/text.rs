@@ -0,0 +1,57 @@
+use rquickjs::{
+    function::{Opt, This},
+    Ctx, Exception, Function, Object,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Unicode normalization forms accepted by `text.normalize`
+const NORMALIZATION_FORMS: [&str; 4] = ["NFC", "NFD", "NFKC", "NFKD"];
+
+/// Register `text` object with `graphemes(s)`, `width(s)` and
+/// `normalize(s, form)`
+pub fn register_text(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let text = Object::new(ctx.clone())?;
+    text.set("graphemes", js_graphemes)?;
+    text.set("width", js_width)?;
+    text.set("normalize", js_normalize)?;
+    ctx.globals().set("text", text)?;
+    Ok(())
+}
+
+/// Terminal display width (wide chars count as 2, combining marks as 0)
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// text.graphemes(s) - user-perceived characters (extended grapheme
+/// clusters, so emoji sequences and combining marks stay together)
+#[rquickjs::function]
+fn graphemes(s: String) -> Vec<String> {
+    s.graphemes(true).map(str::to_string).collect()
+}
+
+/// text.width(s) - terminal display width
+#[rquickjs::function]
+fn width(s: String) -> usize {
+    display_width(&s)
+}
+
+/// text.normalize(s, form = "NFC") - `String.prototype.normalize`, throwing
+/// a `RangeError` for any other form
+#[rquickjs::function]
+fn normalize<'js>(ctx: Ctx<'js>, s: String, form: Opt<String>) -> rquickjs::Result<String> {
+    let form = form.0.unwrap_or_else(|| "NFC".to_string());
+    if !NORMALIZATION_FORMS.contains(&form.as_str()) {
+        return Err(Exception::throw_range(
+            &ctx,
+            &format!("Invalid normalization form: {form}"),
+        ));
+    }
+    let normalize = ctx
+        .globals()
+        .get::<_, Object>("String")?
+        .get::<_, Object>("prototype")?
+        .get::<_, Function>("normalize")?;
+    normalize.call((This(s), form))
+}
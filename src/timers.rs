@@ -0,0 +1,174 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::task::Poll;
+use std::time::Duration;
+
+use rquickjs::{
+    function::{Args, Opt, Rest, This},
+    CatchResultExt, CaughtError, Ctx, Exception, Function, JsLifetime, Promise, Value,
+};
+use tokio::sync::oneshot;
+
+use crate::error::JsError;
+use crate::run::exit_requested;
+use crate::util::sleep_lag;
+
+/// Minimum `setInterval` period (avoids spinning on 0ms intervals)
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Pending timers by id (context userdata) - dropping the sender cancels
+/// the timer
+#[derive(Default, JsLifetime)]
+struct TimerRegistry {
+    next_id: Cell<u32>,
+    pending: RefCell<HashMap<u32, oneshot::Sender<()>>>,
+}
+
+/// Register `setTimeout`/`setInterval` (millisecond delays, returning
+/// numeric ids), `clearTimeout`/`clearInterval` and `queueMicrotask`
+///
+/// Timers run as futures on the context runtime so `rt.idle()` waits for
+/// pending timers - see [`clear_timers`] to drain them on shutdown.
+/// Microtasks are queued as promise jobs so they always run before the
+/// next timer callback (see [`crate::event_loop::drain_jobs`])
+pub fn register_timers(ctx: &Ctx<'_>) -> anyhow::Result<()> {
+    let _ = ctx.store_userdata(TimerRegistry::default());
+    ctx.globals().set("setTimeout", js_set_timeout)?;
+    ctx.globals().set("setInterval", js_set_interval)?;
+    ctx.globals().set("clearTimeout", js_clear_timer)?;
+    ctx.globals().set("clearInterval", js_clear_timer)?;
+    ctx.globals().set("queueMicrotask", js_queue_microtask)?;
+    Ok(())
+}
+
+/// Cancel all pending timers (returns number cancelled)
+pub fn clear_timers(ctx: &Ctx<'_>) -> usize {
+    ctx.userdata::<TimerRegistry>()
+        .map(|registry| registry.pending.borrow_mut().drain().count())
+        .unwrap_or_default()
+}
+
+/// setTimeout(fn, ms = 0, ...args)
+#[rquickjs::function]
+fn set_timeout<'js>(
+    ctx: Ctx<'js>,
+    f: Function<'js>,
+    ms: Opt<f64>,
+    args: Rest<Value<'js>>,
+) -> rquickjs::Result<u32> {
+    start_timer(ctx, f, delay(ms.0), None, args.0)
+}
+
+/// setInterval(fn, ms = 0, ...args)
+#[rquickjs::function]
+fn set_interval<'js>(
+    ctx: Ctx<'js>,
+    f: Function<'js>,
+    ms: Opt<f64>,
+    args: Rest<Value<'js>>,
+) -> rquickjs::Result<u32> {
+    let period = delay(ms.0).max(MIN_INTERVAL);
+    start_timer(ctx, f, period, Some(period), args.0)
+}
+
+/// clearTimeout(id)/clearInterval(id) - unknown ids are ignored
+#[rquickjs::function]
+fn clear_timer<'js>(ctx: Ctx<'js>, id: Opt<Value<'js>>) {
+    let id = id.0.and_then(|id| id.as_number());
+    if let (Some(id), Some(registry)) = (id, ctx.userdata::<TimerRegistry>()) {
+        registry.pending.borrow_mut().remove(&(id as u32));
+    }
+}
+
+/// queueMicrotask(fn) - exceptions are reported rather than becoming
+/// unhandled rejections
+#[rquickjs::function]
+fn queue_microtask<'js>(ctx: Ctx<'js>, f: Function<'js>) -> rquickjs::Result<()> {
+    let task = Function::new(ctx.clone(), move |ctx: Ctx<'js>| {
+        match f.call::<_, ()>(()) {
+            Ok(()) => Ok(()),
+            // Let exit() propagate to the job runner
+            Err(e) if exit_requested(&ctx) => Err(e),
+            Err(e) => {
+                let caught = CaughtError::from_error(&ctx, e);
+                eprintln!("Uncaught {}", JsError::from_caught(&caught));
+                Ok(())
+            }
+        }
+    })?;
+    let (promise, resolve, _) = Promise::new(&ctx)?;
+    promise
+        .then()?
+        .call::<_, Value>((This(promise.clone()), task))?;
+    resolve.call::<_, ()>(())
+}
+
+/// Delay from JS milliseconds (negative/NaN = 0, clamped to u32::MAX ms
+/// so `Infinity` and huge values don't panic)
+pub(crate) fn delay(ms: Option<f64>) -> Duration {
+    let ms = ms.filter(|ms| *ms > 0.0).unwrap_or(0.0);
+    Duration::from_secs_f64(ms.min(u32::MAX as f64) / 1000.0)
+}
+
+fn start_timer<'js>(
+    ctx: Ctx<'js>,
+    f: Function<'js>,
+    delay: Duration,
+    interval: Option<Duration>,
+    args: Vec<Value<'js>>,
+) -> rquickjs::Result<u32> {
+    let (tx, mut cancel) = oneshot::channel();
+    let id = {
+        let registry = ctx
+            .userdata::<TimerRegistry>()
+            .ok_or_else(|| Exception::throw_message(&ctx, "Timers not registered"))?;
+        let id = registry.next_id.get().wrapping_add(1).max(1);
+        registry.next_id.set(id);
+        registry.pending.borrow_mut().insert(id, tx);
+        id
+    };
+    let timer_ctx = ctx.clone();
+    ctx.spawn(async move {
+        let ctx = timer_ctx;
+        let mut wait = delay;
+        while sleep_or_cancel(wait, &mut cancel).await {
+            if let Err(e) = call(&ctx, &f, &args).catch(&ctx) {
+                // exit() from a callback stops all timers
+                if exit_requested(&ctx) {
+                    clear_timers(&ctx);
+                    return;
+                }
+                eprintln!("Uncaught {}", JsError::from_caught(&e));
+            }
+            match interval {
+                Some(period) => wait = period,
+                None => break,
+            }
+        }
+        if let Some(registry) = ctx.userdata::<TimerRegistry>() {
+            registry.pending.borrow_mut().remove(&id);
+        }
+    });
+    Ok(id)
+}
+
+/// Sleep for `d` - returns false if cancelled first
+async fn sleep_or_cancel(d: Duration, cancel: &mut oneshot::Receiver<()>) -> bool {
+    let mut sleep = pin!(sleep_lag(d));
+    poll_fn(|cx| {
+        if Pin::new(&mut *cancel).poll(cx).is_ready() {
+            Poll::Ready(false)
+        } else {
+            sleep.as_mut().poll(cx).map(|_| true)
+        }
+    })
+    .await
+}
+
+fn call<'js>(ctx: &Ctx<'js>, f: &Function<'js>, args: &[Value<'js>]) -> rquickjs::Result<()> {
+    let mut call_args = Args::new(ctx.clone(), args.len());
+    call_args.push_args(args.iter())?;
+    f.call_arg(call_args)
+}
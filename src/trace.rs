@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+#[cfg(feature = "async")]
+use rquickjs::{promise::PromiseHookType, AsyncRuntime};
+use serde::Serialize;
+
+use crate::compat::Instant;
+
+/// Trace event category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceCategory {
+    /// QuickJS job (microtask) execution
+    Job,
+    /// Promise resolved (JS promise hook or host oneshot)
+    Resolve,
+    /// Host future (bridge) lifetime from creation to completion
+    Host,
+}
+
+impl TraceCategory {
+    fn name(&self) -> &'static str {
+        match self {
+            TraceCategory::Job => "job",
+            TraceCategory::Resolve => "resolve",
+            TraceCategory::Host => "host",
+        }
+    }
+
+    /// Separate track per category in the trace viewer
+    fn tid(&self) -> u32 {
+        match self {
+            TraceCategory::Job => 1,
+            TraceCategory::Resolve => 2,
+            TraceCategory::Host => 3,
+        }
+    }
+}
+
+/// chrome://tracing (Trace Event Format) event
+#[derive(Debug, Clone, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    /// Start (us since trace enabled)
+    ts: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u128>,
+    pid: u32,
+    tid: u32,
+}
+
+#[derive(Debug)]
+struct Tracer {
+    start: Instant,
+    events: Vec<TraceEvent>,
+}
+
+fn tracer() -> &'static Mutex<Option<Tracer>> {
+    static TRACER: OnceLock<Mutex<Option<Tracer>>> = OnceLock::new();
+    TRACER.get_or_init(|| Mutex::new(None))
+}
+
+/// Start recording trace events (clears any previous events)
+pub fn enable() {
+    if let Ok(mut t) = tracer().lock() {
+        *t = Some(Tracer {
+            start: Instant::now(),
+            events: Vec::new(),
+        });
+    }
+}
+
+/// Check if tracing is enabled
+pub fn enabled() -> bool {
+    tracer().lock().map(|t| t.is_some()).unwrap_or(false)
+}
+
+fn record(cat: TraceCategory, name: &str, start: Instant, end: Option<Instant>) {
+    if let Ok(mut t) = tracer().lock() {
+        if let Some(t) = t.as_mut() {
+            let ts = start.saturating_duration_since(t.start).as_micros();
+            t.events.push(TraceEvent {
+                name: name.to_string(),
+                cat: cat.name(),
+                ph: if end.is_some() { "X" } else { "i" },
+                ts,
+                dur: end.map(|end| end.saturating_duration_since(start).as_micros()),
+                pid: std::process::id(),
+                tid: cat.tid(),
+            });
+        }
+    }
+}
+
+/// Record instant event
+pub fn instant(cat: TraceCategory, name: &str) {
+    record(cat, name, Instant::now(), None);
+}
+
+/// Records complete event (start to drop) if tracing is enabled
+#[derive(Debug)]
+pub struct TraceSpan {
+    cat: TraceCategory,
+    name: String,
+    start: Instant,
+}
+
+impl Drop for TraceSpan {
+    fn drop(&mut self) {
+        record(self.cat, &self.name, self.start, Some(Instant::now()));
+    }
+}
+
+/// Start span (None if tracing is disabled)
+pub fn span(cat: TraceCategory, name: &str) -> Option<TraceSpan> {
+    enabled().then(|| TraceSpan {
+        cat,
+        name: name.to_string(),
+        start: Instant::now(),
+    })
+}
+
+/// Record JS promise resolutions (via QuickJS promise hook)
+#[cfg(feature = "async")]
+pub async fn install_promise_hook(rt: &AsyncRuntime) {
+    rt.set_promise_hook(Some(Box::new(|_ctx, hook, _promise, _parent| {
+        if hook == PromiseHookType::Resolve {
+            instant(TraceCategory::Resolve, "promise");
+        }
+    })))
+    .await;
+}
+
+/// Write recorded events as chrome://tracing JSON
+pub fn write_trace(path: &Path) -> anyhow::Result<()> {
+    let events = tracer()
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Trace Mutex Error"))?
+        .as_ref()
+        .map(|t| t.events.clone())
+        .unwrap_or_default();
+    let f = std::fs::File::create(path)?;
+    serde_json::to_writer(f, &serde_json::json!({ "traceEvents": events }))?;
+    Ok(())
+}
@@ -1,12 +1,27 @@
 use rquickjs::{
-    function::Rest,
-    function::{Async, Func},
-    Ctx, Exception, Function, Object, Value,
+    function::{Async, Func, Opt, This},
+    Class, Ctx, Exception, Object, Symbol, Value,
 };
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use tokio::sync::oneshot;
-use tokio::time::Duration;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{broadcast, oneshot, watch};
+
+use crate::abort::{abortable, AbortSignal};
+use crate::compat::{self, Instant};
+use crate::console::register_console;
+// Conversion helpers (re-exported for existing users of `util`)
+pub use crate::convert::{
+    args_to_json, inspect, json_to_value, limit_output, stdout_color, truncate_output,
+    value_to_json, DEFAULT_INSPECT_DEPTH,
+};
+use crate::metrics::record_lag;
+use crate::run::register_exit;
+use crate::timers::register_timers;
+use crate::trace::{self, TraceCategory};
+use crate::watchdog::PendingFutures;
 
 /// Register TX channel
 pub fn register_oneshot<'js, T>(
@@ -18,12 +33,16 @@ where
     T: rquickjs::IntoJs<'js> + rquickjs::FromJs<'js> + Clone + Send + 'static,
 {
     let tx = Arc::new(Mutex::new(Some(tx)));
+    let name = f.to_string();
     ctx.globals().set(
         f,
         Func::new(move |ctx, msg: T| match tx.lock() {
             Ok(mut guard) => match guard.take() {
                 Some(tx) => match tx.send(msg) {
-                    Ok(_) => Ok::<(), rquickjs::Error>(()),
+                    Ok(_) => {
+                        trace::instant(TraceCategory::Resolve, &name);
+                        Ok::<(), rquickjs::Error>(())
+                    }
                     Err(_) => Err::<(), rquickjs::Error>(Exception::throw_message(
                         &ctx,
                         "TX Channel Closed",
@@ -81,29 +100,269 @@ where
     T: rquickjs::IntoJs<'js> + rquickjs::FromJs<'js> + Clone + Send + 'static,
 {
     let rx = Arc::new(Mutex::new(rx));
+    let bridge = format!("rx:{f}");
     ctx.globals().set(
         f,
-        Func::new(Async(move |ctx| {
-            // Pass closure to JS engine
+        Func::new(Async(
+            move |ctx, signal: Opt<Class<'js, AbortSignal<'js>>>| {
+                // Pass closure to JS engine
+                let rx = rx.clone();
+                let bridge = bridge.clone();
+                async move {
+                    // Tracked so the watchdog can report a recv() that never completes
+                    let _pending = PendingFutures::global().track(&bridge);
+                    // Returns future when called (dropped if signal is aborted)
+                    if let Some(msg) = {
+                        let mut rx = rx
+                            .lock()
+                            .map_err(|_e| Exception::throw_message(&ctx, "Mutex Error"))?;
+                        abortable(&ctx, signal.0.as_ref(), rx.recv()).await?
+                    } {
+                        Ok::<T, rquickjs::Error>(msg)
+                    } else {
+                        Err::<T, rquickjs::Error>(Exception::throw_message(
+                            &ctx,
+                            "RX Channel Closed",
+                        ))
+                    }
+                }
+            },
+        )),
+    )?;
+    Ok(())
+}
+
+/// Register broadcast channel as object `f` with `subscribe()` - each
+/// subscriber gets its own `recv(signal?)` and sees every message sent
+/// after it subscribed
+///
+/// A subscriber that falls more than the channel capacity behind has
+/// `recv()` throw a `LaggedError` (with `skipped` set to the number of
+/// dropped messages) and then continues from the oldest retained message
+///
+/// Only a weak handle to `tx` is kept, so once the host drops its senders
+/// `recv()` throws "Broadcast Channel Closed" (after any buffered
+/// messages) and `subscribe()` throws
+pub fn register_broadcast_channel<'js, T>(
+    ctx: Ctx<'js>,
+    tx: broadcast::Sender<T>,
+    f: &str,
+) -> anyhow::Result<()>
+where
+    T: rquickjs::IntoJs<'js> + Clone + Send + 'static,
+{
+    let bridge = format!("broadcast:{f}");
+    let tx = tx.downgrade();
+    let channel = Object::new(ctx.clone())?;
+    channel.set(
+        "subscribe",
+        Func::new(move |ctx: Ctx<'js>| {
+            let rx = match tx.upgrade() {
+                Some(tx) => tx.subscribe(),
+                None => return Err(Exception::throw_message(&ctx, "Broadcast Channel Closed")),
+            };
+            let rx = Rc::new(tokio::sync::Mutex::new(rx));
+            let bridge = bridge.clone();
+            let subscriber = Object::new(ctx.clone())?;
+            subscriber.set(
+                "recv",
+                Func::new(Async(
+                    move |ctx: Ctx<'js>, signal: Opt<Class<'js, AbortSignal<'js>>>| {
+                        let rx = rx.clone();
+                        let bridge = bridge.clone();
+                        async move {
+                            let _pending = PendingFutures::global().track(&bridge);
+                            let mut rx = rx.lock().await;
+                            match abortable(&ctx, signal.0.as_ref(), rx.recv()).await? {
+                                Ok(msg) => Ok::<T, rquickjs::Error>(msg),
+                                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                    let ex = Exception::from_message(
+                                        ctx.clone(),
+                                        &format!("Broadcast Subscriber Lagged: {skipped} skipped"),
+                                    )?;
+                                    ex.set("name", "LaggedError")?;
+                                    ex.set("skipped", skipped)?;
+                                    Err(ctx.throw(ex.into_value()))
+                                }
+                                Err(broadcast::error::RecvError::Closed) => {
+                                    Err(Exception::throw_message(&ctx, "Broadcast Channel Closed"))
+                                }
+                            }
+                        }
+                    },
+                )),
+            )?;
+            Ok::<_, rquickjs::Error>(subscriber)
+        }),
+    )?;
+    ctx.globals().set(f, channel)?;
+    Ok(())
+}
+
+/// Register watch channel as object `f` with `getState()` (current value)
+/// and `stateChanged(signal?)` (resolves with the next value) - for state
+/// shared from Rust such as config or status
+///
+/// Both mark the current value as seen, so `stateChanged()` resolves with
+/// the first value newer than the last one returned by either. Updates in
+/// between are coalesced (only the latest value is seen) and concurrent
+/// `stateChanged()` calls all resolve on the same update.
+pub fn register_watch_channel<'js, T>(
+    ctx: Ctx<'js>,
+    rx: watch::Receiver<T>,
+    f: &str,
+) -> anyhow::Result<()>
+where
+    T: rquickjs::IntoJs<'js> + Clone + Send + Sync + 'static,
+{
+    // Tracks the last value seen by the script
+    let seen = Rc::new(RefCell::new(rx));
+    let bridge = format!("watch:{f}");
+    let channel = Object::new(ctx.clone())?;
+    let state = seen.clone();
+    channel.set(
+        "getState",
+        Func::new(move || state.borrow_mut().borrow_and_update().clone()),
+    )?;
+    channel.set(
+        "stateChanged",
+        Func::new(Async(
+            move |ctx: Ctx<'js>, signal: Opt<Class<'js, AbortSignal<'js>>>| {
+                // Each call waits on its own receiver (so calls don't queue
+                // behind each other) from the last seen value
+                let mut rx = seen.borrow().clone();
+                let seen = seen.clone();
+                let bridge = bridge.clone();
+                async move {
+                    let _pending = PendingFutures::global().track(&bridge);
+                    match abortable(&ctx, signal.0.as_ref(), rx.changed()).await? {
+                        Ok(()) => {
+                            Ok::<T, rquickjs::Error>(seen.borrow_mut().borrow_and_update().clone())
+                        }
+                        Err(_) => Err(Exception::throw_message(&ctx, "Watch Channel Closed")),
+                    }
+                }
+            },
+        )),
+    )?;
+    ctx.globals().set(f, channel)?;
+    Ok(())
+}
+
+/// Register RX channel as an async iterable object, so scripts can use
+/// `for await (const msg of f) { ... }` (the loop ends when the channel
+/// closes rather than throwing as [`register_rx_channel`])
+pub fn register_rx_iterator<'js, T>(
+    ctx: Ctx<'js>,
+    rx: UnboundedReceiver<T>,
+    f: &str,
+) -> anyhow::Result<()>
+where
+    T: rquickjs::IntoJs<'js> + Send + 'static,
+{
+    let rx = Arc::new(tokio::sync::Mutex::new(rx));
+    let bridge = format!("rx:{f}");
+    let iter = Object::new(ctx.clone())?;
+    let r = rx.clone();
+    iter.set(
+        "next",
+        Func::new(Async(move |ctx: Ctx<'js>| {
+            let rx = r.clone();
+            let bridge = bridge.clone();
+            async move {
+                let _pending = PendingFutures::global().track(&bridge);
+                let msg = rx.lock().await.recv().await;
+                let result = Object::new(ctx.clone())?;
+                result.set("done", msg.is_none())?;
+                result.set("value", msg)?;
+                Ok::<_, rquickjs::Error>(result)
+            }
+        })),
+    )?;
+    // Called on `break` out of `for await` - closes the channel
+    iter.set(
+        "return",
+        Func::new(Async(move |ctx: Ctx<'js>| {
             let rx = rx.clone();
             async move {
-                // Returns future when called
-                if let Some(msg) = {
-                    rx.lock()
-                        .map_err(|_e| Exception::throw_message(&ctx, "Mutex Error"))?
-                        .recv()
-                        .await
-                } {
-                    Ok::<T, rquickjs::Error>(msg)
-                } else {
-                    Err::<T, rquickjs::Error>(Exception::throw_message(&ctx, "RX Channel Closed"))
-                }
+                rx.lock().await.close();
+                let result = Object::new(ctx.clone())?;
+                result.set("done", true)?;
+                Ok::<_, rquickjs::Error>(result)
             }
         })),
     )?;
+    iter.set(
+        Symbol::async_iterator(ctx.clone()),
+        Func::new(|this: This<Object<'js>>| this.0),
+    )?;
+    ctx.globals().set(f, iter)?;
     Ok(())
 }
 
+/// Async iterable object over records produced by a host thread/task -
+/// `convert` maps each record to JS and `Err` throws `"{label} Error: .."`
+/// (`return()`, eg. `break` out of `for await`, closes the channel so the
+/// producer can stop)
+pub fn channel_iterator<'js, T, F>(
+    ctx: &Ctx<'js>,
+    rx: mpsc::Receiver<Result<T, String>>,
+    label: &'static str,
+    convert: F,
+) -> rquickjs::Result<Object<'js>>
+where
+    T: 'static,
+    F: Fn(&Ctx<'js>, T) -> rquickjs::Result<Value<'js>> + 'js,
+{
+    let rx = Rc::new(tokio::sync::Mutex::new(rx));
+    let convert = Rc::new(convert);
+    let iter = Object::new(ctx.clone())?;
+    let r = rx.clone();
+    iter.set(
+        "next",
+        Func::new(Async(move |ctx: Ctx<'js>| {
+            let rx = r.clone();
+            let convert = convert.clone();
+            async move {
+                let result = Object::new(ctx.clone())?;
+                let record = rx.lock().await.recv().await;
+                match record {
+                    Some(Ok(record)) => {
+                        result.set("value", convert(&ctx, record)?)?;
+                        result.set("done", false)?;
+                    }
+                    Some(Err(e)) => {
+                        rx.lock().await.close();
+                        return Err(Exception::throw_message(
+                            &ctx,
+                            &format!("{label} Error: {e}"),
+                        ));
+                    }
+                    None => result.set("done", true)?,
+                }
+                Ok::<_, rquickjs::Error>(result)
+            }
+        })),
+    )?;
+    iter.set(
+        "return",
+        Func::new(Async(move |ctx: Ctx<'js>| {
+            let rx = rx.clone();
+            async move {
+                rx.lock().await.close();
+                let result = Object::new(ctx.clone())?;
+                result.set("done", true)?;
+                Ok::<_, rquickjs::Error>(result)
+            }
+        })),
+    )?;
+    iter.set(
+        Symbol::async_iterator(ctx.clone()),
+        Func::new(|this: This<Object<'js>>| this.0),
+    )?;
+    Ok(iter)
+}
+
 /// Register useful QJS functions
 pub fn register_fns(ctx: &Ctx<'_>) -> anyhow::Result<()> {
     ctx.globals().set("__print", js_print)?;
@@ -112,47 +371,70 @@ pub fn register_fns(ctx: &Ctx<'_>) -> anyhow::Result<()> {
     ctx.globals().set("__globals", js_globals)?;
     ctx.globals().set("__to_buffer", js_to_buffer)?;
     ctx.globals().set("__to_utf8", js_to_utf8)?;
-    ctx.globals().set("setTimeout", js_set_timeout)?;
-    // Add console.log function
-    let console = Object::new(ctx.clone())?;
-    console.set("log", js_log)?;
-    ctx.globals().set("console", console)?;
+    ctx.globals().set("atob", js_atob)?;
+    ctx.globals().set("btoa", js_btoa)?;
+    register_timers(ctx)?;
+    register_exit(ctx)?;
+    register_console(ctx)?;
     Ok(())
 }
 
-/// Print JS String
-#[rquickjs::function]
-fn print(s: String) {
-    println!("{}", s);
+/// Register `process` object (env, cwd(), platform, pid, exit())
+///
+/// `process.env` only holds the variables named in `env` (unset ones are
+/// omitted) so secrets in the host environment aren't exposed
+#[cfg(feature = "process")]
+pub fn register_process(ctx: &Ctx<'_>, env: &[String]) -> anyhow::Result<()> {
+    let process = rquickjs::Object::new(ctx.clone())?;
+    // Snapshot of allowed variables at registration
+    let vars = rquickjs::Object::new(ctx.clone())?;
+    for name in env {
+        if let Some(value) = std::env::var_os(name) {
+            vars.set(name.as_str(), value.to_string_lossy().as_ref())?;
+        }
+    }
+    process.set("env", vars)?;
+    process.set("cwd", js_cwd)?;
+    process.set("platform", platform())?;
+    process.set("pid", std::process::id())?;
+    crate::run::register_exit_code(ctx)?;
+    process.set("exit", crate::run::js_exit)?;
+    ctx.globals().set("process", process)?;
+    Ok(())
 }
 
-/// Convert Value to JSON String
-pub fn value_to_json<'js>(ctx: Ctx<'js>, v: Value<'js>) -> anyhow::Result<String> {
-    if v.is_undefined() {
-        Ok("null".into())
-    } else {
-        ctx.json_stringify(v)?
-            .and_then(|s| s.as_string().map(|s| s.to_string().ok()).flatten())
-            .ok_or(anyhow::anyhow!("JSON Error"))
+/// Platform name as reported by node (`darwin`, `win32`, `linux` etc)
+#[cfg(feature = "process")]
+fn platform() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        "windows" => "win32",
+        os => os,
     }
 }
 
-/// Convert JSON String to Value
-pub fn json_to_value<'js>(ctx: Ctx<'js>, json: &str) -> anyhow::Result<Value<'js>> {
-    match ctx.json_parse(json.as_bytes()) {
-        Ok(v) => Ok(v),
-        Err(e) => {
-            if let Ok(ex) = rquickjs::Exception::from_value(ctx.catch()) {
-                Err(anyhow::anyhow!(
-                    "JSON Error: {}\n{}",
-                    ex.message().unwrap_or("-".into()),
-                    ex.stack().unwrap_or("-".into())
-                ))
-            } else {
-                Err(anyhow::anyhow!("JSON Error: {e}"))
-            }
-        }
-    }
+/// process.cwd()
+#[cfg(feature = "process")]
+#[rquickjs::function]
+fn cwd(ctx: Ctx<'_>) -> rquickjs::Result<String> {
+    std::env::current_dir()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .map_err(|e| Exception::throw_message(&ctx, &format!("cwd: {e}")))
+}
+
+/// Global holding args passed to the script
+pub const SCRIPT_ARGS: &str = "scriptArgs";
+
+/// Set `scriptArgs` global to array of script args
+pub fn set_script_args(ctx: &Ctx<'_>, args: &[String]) -> anyhow::Result<()> {
+    ctx.globals().set(SCRIPT_ARGS, args)?;
+    Ok(())
+}
+
+/// Print JS String
+#[rquickjs::function]
+fn print(s: String) {
+    println!("{}", s);
 }
 
 /// Print JS Value as JSON
@@ -195,39 +477,91 @@ fn to_utf8<'js>(ctx: Ctx<'js>, a: rquickjs::ArrayBuffer<'js>) -> rquickjs::Resul
     Ok(String::from_utf8(bytes)?)
 }
 
-/// console.log
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Throw DOMException-style `InvalidCharacterError`
+fn invalid_character(ctx: &Ctx<'_>, msg: &str) -> rquickjs::Error {
+    match Exception::from_message(ctx.clone(), msg)
+        .and_then(|ex| ex.set("name", "InvalidCharacterError").map(|_| ex))
+    {
+        Ok(ex) => ctx.throw(ex.into_value()),
+        Err(e) => e,
+    }
+}
+
+/// btoa (binary string -> base64, throws for chars above U+00FF)
 #[rquickjs::function]
-fn log<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> rquickjs::Result<()> {
-    println!(
-        "{}",
-        args.iter()
-            .map(|a| -> rquickjs::Result<String> {
-                Ok(ctx
-                    .json_stringify(a)?
-                    .and_then(|s| s.as_string().map(|s| s.to_string().ok()).flatten())
-                    .unwrap_or_else(|| "<ERR>".to_string()))
-            })
-            .collect::<Result<Vec<_>, _>>()?
-            .join(", ")
-    );
-    Ok(())
+fn btoa(ctx: Ctx<'_>, s: String) -> rquickjs::Result<String> {
+    let bytes = s
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).ok())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            invalid_character(&ctx, "btoa: string contains characters outside Latin1")
+        })?;
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    Ok(out)
 }
 
+/// atob (forgiving base64 -> binary string, throws for invalid input)
 #[rquickjs::function]
-async fn sleep(n: u64) -> rquickjs::Result<()> {
-    tokio::time::sleep(Duration::from_secs(n)).await;
-    Ok(())
+fn atob(ctx: Ctx<'_>, s: String) -> rquickjs::Result<String> {
+    let invalid = || invalid_character(&ctx, "atob: invalid base64 string");
+    let mut data = s
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    if data.len() % 4 == 0 {
+        for _ in 0..2 {
+            if data.last() == Some(&b'=') {
+                data.pop();
+            }
+        }
+    }
+    if data.len() % 4 == 1 {
+        return Err(invalid());
+    }
+    let mut out = String::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let v = BASE64.iter().position(|b| b == c).ok_or_else(invalid)?;
+            n |= (v as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push(char::from((n >> (16 - 8 * i)) as u8));
+        }
+    }
+    Ok(out)
 }
 
+/// __sleep(secs, signal?)
 #[rquickjs::function]
-async fn set_timeout<'js>(
+async fn sleep<'js>(
     ctx: Ctx<'js>,
-    f: Function<'js>,
     n: u64,
-    args: Rest<Value<'js>>,
+    signal: Opt<Class<'js, AbortSignal<'js>>>,
 ) -> rquickjs::Result<()> {
-    tokio::time::sleep(Duration::from_secs(n)).await;
-    let mut arg = rquickjs::function::Args::new(ctx.clone(), args.len());
-    arg.push_args(args.iter())?;
-    f.call_arg(arg)
+    abortable(&ctx, signal.0.as_ref(), sleep_lag(Duration::from_secs(n))).await
+}
+
+/// Sleep recording the delay between the deadline and this future being
+/// resumed (ie. event loop lag)
+pub(crate) async fn sleep_lag(d: Duration) {
+    let deadline = Instant::now() + d;
+    compat::sleep(d).await;
+    record_lag(deadline.elapsed());
 }
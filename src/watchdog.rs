@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::compat::Instant;
+use crate::trace::{self, TraceCategory, TraceSpan};
+
+/// Host future pending with no progress
+#[derive(Debug, Clone)]
+pub struct StuckFuture {
+    /// Bridge the future belongs to (eg. `rx:recv`)
+    pub bridge: String,
+    /// Time since future was created
+    pub waiting: Duration,
+}
+
+#[derive(Debug)]
+struct Inner {
+    next_id: u64,
+    pending: HashMap<u64, (String, Instant)>,
+    last_progress: Instant,
+}
+
+/// Registry of pending host futures (by bridge) used to detect deadlocks
+#[derive(Debug, Clone)]
+pub struct PendingFutures {
+    inner: Arc<Mutex<Inner>>,
+}
+
+/// Removes future from registry (and records progress) when dropped
+#[derive(Debug)]
+pub struct PendingGuard {
+    registry: PendingFutures,
+    id: u64,
+    _span: Option<TraceSpan>,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.registry.inner.lock() {
+            inner.pending.remove(&self.id);
+            inner.last_progress = Instant::now();
+        }
+    }
+}
+
+impl Default for PendingFutures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PendingFutures {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                next_id: 0,
+                pending: HashMap::new(),
+                last_progress: Instant::now(),
+            })),
+        }
+    }
+
+    /// Process-wide registry used by the channel bridges in [`crate::util`]
+    pub fn global() -> &'static PendingFutures {
+        static GLOBAL: OnceLock<PendingFutures> = OnceLock::new();
+        GLOBAL.get_or_init(PendingFutures::new)
+    }
+
+    /// Track host future for `bridge` until guard is dropped
+    pub fn track(&self, bridge: &str) -> PendingGuard {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner
+            .pending
+            .insert(id, (bridge.to_string(), Instant::now()));
+        inner.last_progress = Instant::now();
+        PendingGuard {
+            registry: self.clone(),
+            id,
+            _span: trace::span(TraceCategory::Host, bridge),
+        }
+    }
+
+    /// Number of pending futures
+    pub fn len(&self) -> usize {
+        self.inner.lock().map(|i| i.pending.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pending futures if none have completed (or started) within `after`
+    pub fn stalled(&self, after: Duration) -> Option<Vec<StuckFuture>> {
+        let inner = self.inner.lock().ok()?;
+        if inner.pending.is_empty() || inner.last_progress.elapsed() < after {
+            return None;
+        }
+        let mut stuck = inner
+            .pending
+            .values()
+            .map(|(bridge, start)| StuckFuture {
+                bridge: bridge.clone(),
+                waiting: start.elapsed(),
+            })
+            .collect::<Vec<_>>();
+        stuck.sort_by(|a, b| b.waiting.cmp(&a.waiting));
+        Some(stuck)
+    }
+}
+
+/// Shortest interval between watchdog checks (avoids busy looping for tiny
+/// timeouts)
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Spawn watchdog task checking `registry` for stalls of `timeout`
+///
+/// `on_stall` is called once per stall (ie. until progress is made again)
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub fn spawn_watchdog<F>(registry: PendingFutures, timeout: Duration, on_stall: F)
+where
+    F: Fn(&[StuckFuture]) + Send + 'static,
+{
+    use crate::executor::{DefaultExecutor, Executor};
+    DefaultExecutor::spawn(async move {
+        let mut reported = false;
+        loop {
+            DefaultExecutor::sleep((timeout / 4).max(MIN_CHECK_INTERVAL)).await;
+            match registry.stalled(timeout) {
+                Some(stuck) if !reported => {
+                    on_stall(&stuck);
+                    reported = true;
+                }
+                Some(_) => {}
+                None => reported = false,
+            }
+        }
+    });
+}
+
+/// Default stall report (stderr)
+pub fn report_stall(stuck: &[StuckFuture]) {
+    eprintln!(
+        "[-] Watchdog: no progress with {} pending host future(s)",
+        stuck.len()
+    );
+    for f in stuck {
+        eprintln!("    {} (waiting {:.1}s)", f.bridge, f.waiting.as_secs_f64());
+    }
+}
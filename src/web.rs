@@ -0,0 +1,403 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rquickjs::{
+    class::{Trace, Tracer},
+    function::{Func, Opt, This},
+    Class, Ctx, Exception, FromJs, Function, IntoJs, JsLifetime, Object, Symbol, Value,
+};
+use url::form_urlencoded;
+use url::quirks;
+
+use crate::class::define_class;
+
+/// Parsed URL shared between a `URL` and its `searchParams`
+#[derive(Clone)]
+struct SharedUrl(Rc<RefCell<url::Url>>);
+
+impl<'js> Trace<'js> for SharedUrl {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+/// WHATWG `URL` (backed by the `url` crate)
+///
+/// `searchParams` is live - changes are reflected in `search`/`href` and
+/// vice versa
+#[derive(Trace, JsLifetime)]
+#[rquickjs::class(rename = "URL")]
+pub struct Url<'js> {
+    url: SharedUrl,
+    search_params: Class<'js, UrlSearchParams>,
+}
+
+impl<'js> Url<'js> {
+    fn from_url(ctx: &Ctx<'js>, url: url::Url) -> rquickjs::Result<Self> {
+        let url = SharedUrl(Rc::new(RefCell::new(url)));
+        let search_params = Class::instance(
+            ctx.clone(),
+            UrlSearchParams {
+                source: ParamsSource::Url(url.clone()),
+            },
+        )?;
+        Ok(Self { url, search_params })
+    }
+
+    /// Parsed URL
+    pub fn url(&self) -> url::Url {
+        self.url.0.borrow().clone()
+    }
+}
+
+fn parse(input: &str, base: Option<&str>) -> Result<url::Url, url::ParseError> {
+    match base {
+        Some(base) => url::Url::parse(base)?.join(input),
+        None => url::Url::parse(input),
+    }
+}
+
+#[rquickjs::methods]
+impl<'js> Url<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, input: String, base: Opt<String>) -> rquickjs::Result<Self> {
+        let url = parse(&input, base.0.as_deref())
+            .map_err(|e| Exception::throw_type(&ctx, &format!("Invalid URL: {input} [{e}]")))?;
+        Self::from_url(&ctx, url)
+    }
+
+    #[qjs(static, rename = "canParse")]
+    pub fn can_parse(input: String, base: Opt<String>) -> bool {
+        parse(&input, base.0.as_deref()).is_ok()
+    }
+
+    #[qjs(get, rename = "href")]
+    pub fn href(&self) -> String {
+        quirks::href(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "href")]
+    pub fn set_href(&self, ctx: Ctx<'js>, value: String) -> rquickjs::Result<()> {
+        quirks::set_href(&mut self.url.0.borrow_mut(), &value)
+            .map_err(|e| Exception::throw_type(&ctx, &format!("Invalid URL: {value} [{e}]")))
+    }
+
+    #[qjs(get, rename = "origin")]
+    pub fn origin(&self) -> String {
+        quirks::origin(&self.url.0.borrow())
+    }
+
+    #[qjs(get, rename = "protocol")]
+    pub fn protocol(&self) -> String {
+        quirks::protocol(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "protocol")]
+    pub fn set_protocol(&self, value: String) {
+        let _ = quirks::set_protocol(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "username")]
+    pub fn username(&self) -> String {
+        quirks::username(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "username")]
+    pub fn set_username(&self, value: String) {
+        let _ = quirks::set_username(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "password")]
+    pub fn password(&self) -> String {
+        quirks::password(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "password")]
+    pub fn set_password(&self, value: String) {
+        let _ = quirks::set_password(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "host")]
+    pub fn host(&self) -> String {
+        quirks::host(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "host")]
+    pub fn set_host(&self, value: String) {
+        let _ = quirks::set_host(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "hostname")]
+    pub fn hostname(&self) -> String {
+        quirks::hostname(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "hostname")]
+    pub fn set_hostname(&self, value: String) {
+        let _ = quirks::set_hostname(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "port")]
+    pub fn port(&self) -> String {
+        quirks::port(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "port")]
+    pub fn set_port(&self, value: String) {
+        let _ = quirks::set_port(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "pathname")]
+    pub fn pathname(&self) -> String {
+        quirks::pathname(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "pathname")]
+    pub fn set_pathname(&self, value: String) {
+        quirks::set_pathname(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "search")]
+    pub fn search(&self) -> String {
+        quirks::search(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "search")]
+    pub fn set_search(&self, value: String) {
+        quirks::set_search(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "hash")]
+    pub fn hash(&self) -> String {
+        quirks::hash(&self.url.0.borrow()).to_string()
+    }
+
+    #[qjs(set, rename = "hash")]
+    pub fn set_hash(&self, value: String) {
+        quirks::set_hash(&mut self.url.0.borrow_mut(), &value);
+    }
+
+    #[qjs(get, rename = "searchParams")]
+    pub fn search_params(&self) -> Class<'js, UrlSearchParams> {
+        self.search_params.clone()
+    }
+
+    #[qjs(rename = "toString")]
+    pub fn to_js_string(&self) -> String {
+        self.href()
+    }
+
+    #[qjs(rename = "toJSON")]
+    pub fn to_json(&self) -> String {
+        self.href()
+    }
+}
+
+/// Name/value pairs owned by a `URLSearchParams` or read from/written to
+/// the query of a `URL`
+enum ParamsSource {
+    Owned(Vec<(String, String)>),
+    Url(SharedUrl),
+}
+
+impl<'js> Trace<'js> for ParamsSource {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+/// WHATWG `URLSearchParams`
+#[derive(Trace, JsLifetime)]
+#[rquickjs::class(rename = "URLSearchParams")]
+pub struct UrlSearchParams {
+    source: ParamsSource,
+}
+
+impl UrlSearchParams {
+    /// Name/value pairs (in order)
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        match &self.source {
+            ParamsSource::Owned(pairs) => pairs.clone(),
+            ParamsSource::Url(url) => url.0.borrow().query_pairs().into_owned().collect(),
+        }
+    }
+
+    fn update(&mut self, f: impl FnOnce(&mut Vec<(String, String)>)) {
+        let mut pairs = self.pairs();
+        f(&mut pairs);
+        match &mut self.source {
+            ParamsSource::Owned(owned) => *owned = pairs,
+            ParamsSource::Url(url) => {
+                let mut url = url.0.borrow_mut();
+                if pairs.is_empty() {
+                    url.set_query(None);
+                } else {
+                    url.query_pairs_mut().clear().extend_pairs(pairs);
+                }
+            }
+        }
+    }
+}
+
+/// Pairs from `URLSearchParams` init (string, [name, value] pairs, record
+/// or another `URLSearchParams`)
+fn init_pairs<'js>(ctx: &Ctx<'js>, init: Value<'js>) -> rquickjs::Result<Vec<(String, String)>> {
+    if init.is_undefined() || init.is_null() {
+        return Ok(Vec::new());
+    }
+    if let Some(s) = init.as_string() {
+        let s = s.to_string()?;
+        let query = s.strip_prefix('?').unwrap_or(&s);
+        return Ok(form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect());
+    }
+    if let Some(obj) = init.as_object() {
+        if let Some(params) = Class::<UrlSearchParams>::from_object(obj) {
+            return Ok(params.borrow().pairs());
+        }
+        if init.is_array() {
+            return Vec::<Vec<String>>::from_js(ctx, init.clone())?
+                .into_iter()
+                .map(|pair| match <[String; 2]>::try_from(pair) {
+                    Ok([name, value]) => Ok((name, value)),
+                    Err(_) => Err(Exception::throw_type(
+                        ctx,
+                        "URLSearchParams: each pair must be [name, value]",
+                    )),
+                })
+                .collect();
+        }
+        return obj.props::<String, String>().collect();
+    }
+    Err(Exception::throw_type(ctx, "URLSearchParams: invalid init"))
+}
+
+/// Array iterator over `items` (for keys/values/entries)
+fn array_iter<'js, T: IntoJs<'js>>(ctx: &Ctx<'js>, items: Vec<T>) -> rquickjs::Result<Value<'js>> {
+    let array = items.into_js(ctx)?;
+    let values: Function = array.get::<Object>()?.get("values")?;
+    values.call((This(array),))
+}
+
+#[rquickjs::methods]
+impl UrlSearchParams {
+    #[qjs(constructor)]
+    pub fn new<'js>(ctx: Ctx<'js>, init: Opt<Value<'js>>) -> rquickjs::Result<Self> {
+        let pairs = match init.0 {
+            Some(init) => init_pairs(&ctx, init)?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            source: ParamsSource::Owned(pairs),
+        })
+    }
+
+    #[qjs(get, rename = "size")]
+    pub fn size(&self) -> usize {
+        self.pairs().len()
+    }
+
+    pub fn append(&mut self, name: String, value: String) {
+        self.update(|pairs| pairs.push((name, value)));
+    }
+
+    /// delete(name, value?)
+    pub fn delete(&mut self, name: String, value: Opt<String>) {
+        self.update(|pairs| {
+            pairs.retain(|(n, v)| n != &name || value.0.as_ref().is_some_and(|value| v != value))
+        });
+    }
+
+    /// First value for name (null if missing)
+    pub fn get(&self, name: String) -> Option<String> {
+        self.pairs()
+            .into_iter()
+            .find(|(n, _)| n == &name)
+            .map(|(_, v)| v)
+    }
+
+    #[qjs(rename = "getAll")]
+    pub fn get_all(&self, name: String) -> Vec<String> {
+        self.pairs()
+            .into_iter()
+            .filter(|(n, _)| n == &name)
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    /// has(name, value?)
+    pub fn has(&self, name: String, value: Opt<String>) -> bool {
+        self.pairs()
+            .iter()
+            .any(|(n, v)| n == &name && value.0.as_ref().is_none_or(|value| v == value))
+    }
+
+    /// Replace first value for name (removing others) or append
+    pub fn set(&mut self, name: String, value: String) {
+        self.update(|pairs| {
+            let mut found = false;
+            pairs.retain_mut(|(n, v)| {
+                if n != &name {
+                    true
+                } else if found {
+                    false
+                } else {
+                    found = true;
+                    *v = value.clone();
+                    true
+                }
+            });
+            if !found {
+                pairs.push((name, value));
+            }
+        });
+    }
+
+    /// Stable sort by name
+    pub fn sort(&mut self) {
+        self.update(|pairs| pairs.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16())));
+    }
+
+    pub fn keys<'js>(&self, ctx: Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        array_iter(&ctx, self.pairs().into_iter().map(|(n, _)| n).collect())
+    }
+
+    pub fn values<'js>(&self, ctx: Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        array_iter(&ctx, self.pairs().into_iter().map(|(_, v)| v).collect())
+    }
+
+    pub fn entries<'js>(&self, ctx: Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        array_iter(
+            &ctx,
+            self.pairs().into_iter().map(|(n, v)| vec![n, v]).collect(),
+        )
+    }
+
+    /// application/x-www-form-urlencoded serialisation (without `?`)
+    #[qjs(rename = "toString")]
+    pub fn to_js_string(&self) -> String {
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.pairs())
+            .finish()
+    }
+}
+
+/// Register `URL` and `URLSearchParams` classes
+pub fn register_web<'js>(ctx: &Ctx<'js>) -> anyhow::Result<()> {
+    define_class::<Url>(ctx)?;
+    let proto = define_class::<UrlSearchParams>(ctx)?;
+    // Defined outside the class so the callback can modify the params
+    proto.set(
+        "forEach",
+        Func::new(
+            |this: This<Class<'js, UrlSearchParams>>, f: Function<'js>| -> rquickjs::Result<()> {
+                let pairs = this.0.borrow().pairs();
+                for (name, value) in pairs {
+                    f.call::<_, ()>((value, name, this.0.clone()))?;
+                }
+                Ok(())
+            },
+        ),
+    )?;
+    // for (const [name, value] of params)
+    let entries: Function = proto.get("entries")?;
+    proto.set(Symbol::iterator(ctx.clone()), entries)?;
+    Ok(())
+}
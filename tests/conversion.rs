@@ -0,0 +1,122 @@
+#![cfg(feature = "sync")]
+
+use proptest::prelude::*;
+use rquickjs::class::Trace;
+use rquickjs::{Class, Context, JsLifetime, Runtime};
+use rquickjs_test::class::define_serde_class;
+use rquickjs_test::convert::{json_to_value, value_to_json};
+
+/// Serde value exposed to JS via `toJSON` (serialised as the bare value)
+#[derive(Trace, JsLifetime, serde::Serialize)]
+#[serde(transparent)]
+#[rquickjs::class]
+struct Wrapper {
+    #[qjs(skip_trace)]
+    value: serde_json::Value,
+}
+
+/// Arbitrary JSON value (finite numbers only - JSON has no NaN/Infinity)
+fn arb_json() -> impl Strategy<Value = serde_json::Value> {
+    let leaf = prop_oneof![
+        Just(serde_json::Value::Null),
+        any::<bool>().prop_map(serde_json::Value::from),
+        any::<i64>().prop_map(serde_json::Value::from),
+        any::<f64>()
+            .prop_filter("finite", |f| f.is_finite())
+            .prop_map(serde_json::Value::from),
+        any::<String>().prop_map(serde_json::Value::from),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(serde_json::Value::Array),
+            prop::collection::btree_map(any::<String>(), inner, 0..8)
+                .prop_map(|m| serde_json::Value::Object(m.into_iter().collect())),
+        ]
+    })
+}
+
+/// Compare JSON values with numbers as f64 (JS numbers are doubles so
+/// integers above 2^53 lose precision and `1.0` becomes `1`)
+fn json_eq(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    use serde_json::Value::*;
+    match (a, b) {
+        (Number(a), Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+            a == b || (a - b).abs() <= f64::EPSILON * a.abs().max(b.abs())
+        }
+        (Array(a), Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b)),
+        (Object(a), Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, v)| b.get(k).is_some_and(|other| json_eq(v, other)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+proptest! {
+    #[test]
+    fn json_round_trip(json in arb_json()) {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let out = ctx.with(|ctx| {
+            let v = json_to_value(ctx.clone(), &json.to_string()).unwrap();
+            value_to_json(ctx, v).unwrap()
+        });
+        let out: serde_json::Value = serde_json::from_str(&out).unwrap();
+        prop_assert!(json_eq(&json, &out), "{json} != {out}");
+    }
+
+    #[test]
+    fn serde_class_round_trip(json in arb_json()) {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let out = ctx.with(|ctx| {
+            define_serde_class::<Wrapper>(&ctx).unwrap();
+            let instance = Class::instance(ctx.clone(), Wrapper { value: json.clone() }).unwrap();
+            value_to_json(ctx, instance.into_value()).unwrap()
+        });
+        let out: serde_json::Value = serde_json::from_str(&out).unwrap();
+        prop_assert!(json_eq(&json, &out), "{json} != {out}");
+    }
+
+    #[test]
+    fn json_to_value_invalid_input(s in any::<String>()) {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let valid = serde_json::from_str::<serde_json::Value>(&s).is_ok();
+        let parsed = ctx.with(|ctx| json_to_value(ctx, &s).is_ok());
+        // QuickJS may accept some inputs serde rejects (eg. lone surrogates)
+        // but never the reverse
+        prop_assert!(!valid || parsed);
+    }
+}
+
+/// REPL multi-line detection (the REPL needs the async runtime)
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod multiline {
+    use super::arb_json;
+    use proptest::prelude::*;
+    use rquickjs_test::repl::needs_more_input;
+
+    proptest! {
+        #[test]
+        fn multiline_no_panic(s in any::<String>()) {
+            needs_more_input(&s);
+        }
+
+        #[test]
+        fn multiline_json_complete(json in arb_json()) {
+            prop_assert!(!needs_more_input(&format!("({json})")));
+        }
+
+        #[test]
+        fn multiline_json_truncated(json in arb_json()) {
+            let s = json.to_string();
+            // Drop closing bracket of array/object
+            if s.len() > 2 && (s.ends_with(']') || s.ends_with('}')) {
+                prop_assert!(needs_more_input(&s[..s.len() - 1]));
+            }
+        }
+    }
+}
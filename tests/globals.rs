@@ -0,0 +1,947 @@
+#![cfg(all(feature = "async", not(target_arch = "wasm32")))]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rquickjs::{async_with, AsyncContext, AsyncRuntime, Value};
+use rquickjs_test::cache::Cache;
+use rquickjs_test::convert::value_to_json;
+use rquickjs_test::env::JsEnvBuilder;
+use rquickjs_test::event_loop::drain_jobs;
+use rquickjs_test::kv::{FileKvBackend, MemoryKvBackend};
+use rquickjs_test::run::{run_script, take_exit_code};
+use rquickjs_test::util::{
+    register_broadcast_channel, register_rx_channel, register_watch_channel,
+};
+use serde_json::json;
+
+/// Run `script` until idle and return the `result` global and exit code
+async fn run(env: JsEnvBuilder, script: &str) -> (serde_json::Value, Option<i32>) {
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+    async_with!(ctx => |ctx| {
+        env.apply(ctx.clone()).await.unwrap();
+        run_script(ctx.clone(), script.to_string()).await.unwrap();
+    })
+    .await;
+    tokio::time::timeout(Duration::from_secs(5), rt.idle())
+        .await
+        .expect("script still running");
+    let code = async_with!(ctx => |ctx| { take_exit_code(&ctx) }).await;
+    (result(&ctx).await, code)
+}
+
+/// Value of the `result` global as JSON
+async fn result(ctx: &AsyncContext) -> serde_json::Value {
+    async_with!(ctx => |ctx| {
+        let v = ctx.globals().get::<_, Value>("result").unwrap();
+        serde_json::from_str(&value_to_json(ctx.clone(), v).unwrap()).unwrap()
+    })
+    .await
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rquickjs-test-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn timers_run_in_delay_order() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal(),
+        r#"
+        result = [];
+        setTimeout(() => result.push("c"), 30);
+        setTimeout((v) => result.push(v), 0, "a");
+        setTimeout(() => result.push("b"), 10);
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!(["a", "b", "c"]));
+}
+
+#[tokio::test]
+async fn clear_timeout_from_callback() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal(),
+        r#"
+        result = [];
+        const b = setTimeout(() => result.push("b"), 20);
+        setTimeout(() => { result.push("a"); clearTimeout(b); }, 0);
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!(["a"]));
+}
+
+#[tokio::test]
+async fn clear_interval_stops_interval() {
+    // run() fails if the interval keeps the runtime busy
+    let (result, _) = run(
+        JsEnvBuilder::minimal(),
+        r#"
+        result = 0;
+        const id = setInterval(() => { if (++result == 3) clearInterval(id); }, 1);
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!(3));
+}
+
+#[tokio::test]
+async fn exit_from_timer_cancels_timers() {
+    let (result, code) = run(
+        JsEnvBuilder::minimal(),
+        r#"
+        result = { n: 0 };
+        setInterval(() => { if (++result.n == 2) exit(3); }, 1);
+        setTimeout(() => { result.late = true; }, 100);
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!({ "n": 2 }));
+    assert_eq!(code, Some(3));
+}
+
+#[tokio::test]
+async fn microtasks_run_before_timers() {
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+    async_with!(ctx => |ctx| {
+        JsEnvBuilder::minimal().apply(ctx.clone()).await.unwrap();
+        run_script(ctx.clone(), r#"
+            result = [];
+            setTimeout(() => result.push("timeout"), 0);
+            queueMicrotask(() => result.push("micro"));
+            Promise.resolve().then(() => result.push("then"));
+        "#.to_string())
+        .await
+        .unwrap();
+    })
+    .await;
+    assert_eq!(drain_jobs(&ctx).await.unwrap(), 2);
+    assert_eq!(result(&ctx).await, json!(["micro", "then"]));
+    rt.idle().await;
+    assert_eq!(result(&ctx).await, json!(["micro", "then", "timeout"]));
+}
+
+#[tokio::test]
+async fn broadcast_closes_when_sender_dropped() {
+    let (tx, _) = tokio::sync::broadcast::channel::<String>(8);
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+    let js_tx = tx.clone();
+    async_with!(ctx => |ctx| {
+        JsEnvBuilder::minimal().apply(ctx.clone()).await.unwrap();
+        register_broadcast_channel(ctx.clone(), js_tx, "events").unwrap();
+        run_script(ctx.clone(), r#"
+            result = [];
+            const sub = events.subscribe();
+            (async () => {
+                try {
+                    for (;;) result.push(await sub.recv());
+                } catch (e) {
+                    result.push(e.message);
+                }
+            })();
+        "#.to_string())
+        .await
+        .unwrap();
+    })
+    .await;
+    tx.send("a".to_string()).unwrap();
+    tx.send("b".to_string()).unwrap();
+    drop(tx);
+    tokio::time::timeout(Duration::from_secs(5), rt.idle())
+        .await
+        .expect("subscriber still waiting");
+    assert_eq!(
+        result(&ctx).await,
+        json!(["a", "b", "Broadcast Channel Closed"])
+    );
+}
+
+#[tokio::test]
+async fn watch_concurrent_state_changed() {
+    let (tx, rx) = tokio::sync::watch::channel(0);
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+    async_with!(ctx => |ctx| {
+        JsEnvBuilder::minimal().apply(ctx.clone()).await.unwrap();
+        register_watch_channel(ctx.clone(), rx, "state").unwrap();
+        run_script(ctx.clone(), r#"
+            result = [state.getState()];
+            Promise.all([state.stateChanged(), state.stateChanged()])
+                .then((values) => result.push(values, state.getState()));
+        "#.to_string())
+        .await
+        .unwrap();
+    })
+    .await;
+    tx.send(1).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), rt.idle())
+        .await
+        .expect("stateChanged still waiting");
+    assert_eq!(result(&ctx).await, json!([0, [1, 1], 1]));
+}
+
+#[tokio::test]
+async fn base64() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal(),
+        r#"
+        const error = (f) => { try { f() } catch (e) { return e.name } };
+        result = [
+            btoa("hello"),
+            atob("aGVsbG8="),
+            atob(" aGVs bG8 "),
+            atob(btoa("\xff\x00")) == "\xff\x00",
+            error(() => btoa("✓")),
+            error(() => atob("a")),
+        ];
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!([
+            "aGVsbG8=",
+            "hello",
+            "hello",
+            true,
+            "InvalidCharacterError",
+            "InvalidCharacterError"
+        ])
+    );
+}
+
+const KV_SCRIPT: &str = r#"
+(async () => {
+    await kv.set("a/1", { x: 1 });
+    await kv.set("a/2", 2);
+    await kv.set("b", "three");
+    await kv.delete("a/2");
+    result = [await kv.get("a/1"), await kv.get("a/2"), await kv.get("b"), await kv.keys("a/"), await kv.keys()];
+})()
+"#;
+
+#[tokio::test]
+async fn kv_memory() {
+    let env = JsEnvBuilder::minimal().kv(Arc::new(MemoryKvBackend::new()));
+    let (result, _) = run(env, KV_SCRIPT).await;
+    assert_eq!(
+        result,
+        json!([{ "x": 1 }, null, "three", ["a/1"], ["a/1", "b"]])
+    );
+}
+
+#[tokio::test]
+async fn kv_file() {
+    let dir = temp_dir("kv");
+    let env = JsEnvBuilder::minimal().kv(Arc::new(FileKvBackend::new(&dir).unwrap()));
+    let (result, _) = run(env, KV_SCRIPT).await;
+    assert_eq!(
+        result,
+        json!([{ "x": 1 }, null, "three", ["a/1"], ["a/1", "b"]])
+    );
+    // Persisted for the next context
+    let env = JsEnvBuilder::minimal().kv(Arc::new(FileKvBackend::new(&dir).unwrap()));
+    let (result, _) = run(env, r#"(async () => { result = await kv.get("a/1") })()"#).await;
+    assert_eq!(result, json!({ "x": 1 }));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn cache_coalesces_concurrent_computes() {
+    let env = JsEnvBuilder::minimal().cache(Arc::new(Cache::new(10)));
+    let (result, _) = run(
+        env,
+        r#"
+        (async () => {
+            let calls = 0;
+            const compute = async () => {
+                calls++;
+                await new Promise((resolve) => setTimeout(resolve, 20));
+                return { n: calls };
+            };
+            const values = await Promise.all([
+                cache.getOrCompute("k", 1000, compute),
+                cache.getOrCompute("k", 1000, compute),
+            ]);
+            result = [calls, values, await cache.getOrCompute("k", 1000, compute)];
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!([1, [{ "n": 1 }, { "n": 1 }], { "n": 1 }]));
+}
+
+#[tokio::test]
+async fn cache_ttl_expiry() {
+    let env = JsEnvBuilder::minimal().cache(Arc::new(Cache::new(10)));
+    let (result, _) = run(
+        env,
+        r#"
+        (async () => {
+            await cache.getOrCompute("short", 10, async () => 1);
+            await cache.getOrCompute("forever", Infinity, async () => 2);
+            await new Promise((resolve) => setTimeout(resolve, 50));
+            result = [cache.get("short") ?? null, cache.get("forever")];
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!([null, 2]));
+}
+
+#[tokio::test]
+async fn cache_evicts_least_recently_used() {
+    let env = JsEnvBuilder::minimal().cache(Arc::new(Cache::new(2)));
+    let (result, _) = run(
+        env,
+        r#"
+        (async () => {
+            await cache.getOrCompute("a", 1000, async () => "a");
+            await cache.getOrCompute("b", 1000, async () => "b");
+            cache.get("a");
+            await cache.getOrCompute("c", 1000, async () => "c");
+            result = ["a", "b", "c"].map((k) => cache.get(k) ?? null);
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(result, json!(["a", null, "c"]));
+}
+
+#[tokio::test]
+async fn sql_tag() {
+    let (result, _) = run(
+        JsEnvBuilder::new().sql(true).lazy(true),
+        r#"
+        const name = "x'; drop table t; --";
+        const filter = sql`name = ${name}`;
+        const q = sql`select * from t where id in (${[1, 2, 3]}) and ${filter} and tag in (${[]}) and v = ${null}`;
+        result = [q instanceof SqlQuery, q.text, q.params];
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!([
+            true,
+            "select * from t where id in (?, ?, ?) and name = ? and tag in (NULL) and v = ?",
+            [1, 2, 3, "x'; drop table t; --", null]
+        ])
+    );
+}
+
+#[tokio::test]
+async fn url() {
+    let (result, _) = run(
+        JsEnvBuilder::new().url(true),
+        r#"
+        const u = new URL("../b?x=1&y=2#h", "https://example.com/a/c/d");
+        u.searchParams.append("z", "3 4");
+        const p = new URLSearchParams("a=1&a=2&b=");
+        result = [
+            u.href,
+            u.pathname,
+            u.searchParams.get("y"),
+            p.getAll("a"),
+            p.has("b"),
+            [...p.keys()],
+            URL.canParse("nope"),
+        ];
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!([
+            "https://example.com/a/b?x=1&y=2&z=3+4#h",
+            "/a/b",
+            "2",
+            ["1", "2"],
+            true,
+            ["a", "a", "b"],
+            false
+        ])
+    );
+}
+
+#[cfg(feature = "text")]
+#[tokio::test]
+async fn text() {
+    let (result, _) = run(
+        JsEnvBuilder::new().text(true),
+        r#"
+        const family = "\u{1F468}\u200D\u{1F469}\u200D\u{1F467}";
+        const thumbs = "\u{1F44D}\u{1F3FD}";
+        const invalid = (form) => {
+            try {
+                return text.normalize("a", form);
+            } catch (e) {
+                return e.name;
+            }
+        };
+        result = {
+            graphemes: text.graphemes(`a${family}e\u0301${thumbs}`).length,
+            family: text.graphemes(family).length,
+            widths: ["abc", "日本語", "e\u0301", family, thumbs].map((s) => text.width(s)),
+            nfc: text.normalize("e\u0301") === "\u00e9",
+            nfd: text.normalize("\u00e9", "NFD").length,
+            nfkc: text.normalize("\uFB01", "NFKC"),
+            invalid: ["nfc", "NFX", ""].map(invalid),
+        };
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "graphemes": 4,
+            "family": 1,
+            "widths": [3, 6, 1, 2, 2],
+            "nfc": true,
+            "nfd": 2,
+            "nfkc": "fi",
+            "invalid": ["RangeError", "RangeError", "RangeError"],
+        })
+    );
+}
+
+#[cfg(feature = "crypto")]
+#[tokio::test]
+async fn crypto() {
+    let (result, _) = run(
+        JsEnvBuilder::new().crypto(true),
+        r#"
+        (async () => {
+            const name = (f) => {
+                try {
+                    f();
+                    return null;
+                } catch (e) {
+                    return e.name;
+                }
+            };
+            const bytes = new Uint8Array(32);
+            const same = crypto.getRandomValues(bytes) === bytes;
+            const filled = bytes.some((b) => b !== 0);
+            const integers = [Int8Array, Uint8ClampedArray, Uint16Array, Int32Array, BigUint64Array]
+                .map((T) => name(() => crypto.getRandomValues(new T(4))));
+            const Float16 = globalThis.Float16Array ?? Float32Array;
+            const rejected = [
+                new Float16(4),
+                new Float64Array(4),
+                new DataView(new ArrayBuffer(4)),
+                { BYTES_PER_ELEMENT: 1, buffer: new ArrayBuffer(4), byteOffset: 0, byteLength: 4 },
+            ].map((v) => name(() => crypto.getRandomValues(v)));
+            const quota = name(() => crypto.getRandomValues(new Uint8Array(65537)));
+            const uuids = [crypto.randomUUID(), crypto.randomUUID()];
+            const v4 = /^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$/;
+            const hex = (b) => [...new Uint8Array(b)].map((x) => x.toString(16).padStart(2, "0")).join("");
+            const abc = new Uint8Array([0x61, 0x62, 0x63]);
+            const sha256 = hex(await crypto.subtle.digest("SHA-256", abc));
+            const byName = hex(await crypto.subtle.digest({ name: "sha-256" }, abc.buffer));
+            const sizes = await Promise.all(
+                ["SHA-384", "SHA-512"].map(async (a) => (await crypto.subtle.digest(a, new Uint8Array())).byteLength)
+            );
+            const unsupported = await crypto.subtle.digest("MD5", abc).catch((e) => e.name);
+            result = {
+                same,
+                filled,
+                integers,
+                rejected,
+                quota,
+                uuids: uuids.every((u) => v4.test(u)) && uuids[0] !== uuids[1],
+                sha256,
+                byName: byName === sha256,
+                sizes,
+                unsupported,
+            };
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "same": true,
+            "filled": true,
+            "integers": [null, null, null, null, null],
+            "rejected": ["TypeMismatchError", "TypeMismatchError", "TypeMismatchError", "TypeMismatchError"],
+            "quota": "QuotaExceededError",
+            "uuids": true,
+            "sha256": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "byName": true,
+            "sizes": [48, 64],
+            "unsupported": "NotSupportedError",
+        })
+    );
+}
+
+#[cfg(feature = "net")]
+#[tokio::test]
+async fn net_check_port() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (result, _) = run(
+        JsEnvBuilder::new().net(true),
+        &format!(
+            r#"
+            (async () => {{
+                const open = await net.checkPort("127.0.0.1", {port}, 1000);
+                const invalid = await net.checkPort("", {port}).catch((e) => e.message);
+                result = {{ ok: open.ok, latency: typeof open.latency, error: open.error, invalid }};
+            }})()
+            "#
+        ),
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({ "ok": true, "latency": "number", "error": null, "invalid": "Invalid host" })
+    );
+}
+
+#[tokio::test]
+async fn events() {
+    let (result, _) = run(
+        JsEnvBuilder::new().events(true),
+        r#"
+        const t = new EventTarget();
+        const calls = [];
+        const late = () => calls.push("late");
+        const removed = () => calls.push("removed");
+        t.addEventListener("ping", () => calls.push("once"), { once: true });
+        t.addEventListener("ping", () => {
+            calls.push("first");
+            t.addEventListener("ping", late);
+            t.removeEventListener("ping", removed);
+        });
+        t.addEventListener("ping", removed);
+        t.dispatchEvent(new CustomEvent("ping"));
+        t.dispatchEvent({ type: "ping" });
+
+        t.addEventListener("save", (e) => e.preventDefault());
+        const cancelable = new CustomEvent("save", { cancelable: true, detail: 1 });
+        const plain = new CustomEvent("save");
+        result = {
+            calls,
+            cancelable: [t.dispatchEvent(cancelable), cancelable.defaultPrevented],
+            plain: [t.dispatchEvent(plain), plain.defaultPrevented],
+            target: cancelable.target === t,
+            detail: [cancelable.detail, plain.detail],
+        };
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "calls": ["once", "first", "first", "late"],
+            "cancelable": [false, true],
+            "plain": [true, false],
+            "target": true,
+            "detail": [1, null],
+        })
+    );
+}
+
+#[tokio::test]
+async fn with_lock() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal().lock_dir(temp_dir("lock")),
+        r#"
+        (async () => {
+            const order = [];
+            const hold = (tag, ms) => async () => {
+                order.push(`${tag}+`);
+                await new Promise((r) => setTimeout(r, ms));
+                order.push(`${tag}-`);
+                return tag;
+            };
+            const values = await Promise.all([
+                lock.withLock("job", 1000, hold("a", 20)),
+                lock.withLock("job", 1000, hold("b", 0)),
+            ]);
+            const thrown = await lock.withLock("job", 1000, () => {
+                throw new Error("boom");
+            }).catch((e) => e.message);
+            const again = await lock.withLock("job", 1000, async () => "again", { timeoutMs: 0 });
+            // The lease outlives its 30ms ttl while the holder is pending
+            const slow = lock.withLock("slow", 30, hold("c", 150));
+            await new Promise((r) => setTimeout(r, 5));
+            const timedOut = await lock
+                .withLock("slow", 30, hold("d", 0), { timeoutMs: 50 })
+                .catch((e) => e.message);
+            result = { values, order, thrown, again, slow: await slow, timedOut };
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "values": ["a", "b"],
+            "order": ["a+", "a-", "b+", "b-", "c+", "c-"],
+            "thrown": "boom",
+            "again": "again",
+            "slow": "c",
+            "timedOut": "Lock Timeout: slow",
+        })
+    );
+}
+
+#[tokio::test]
+async fn circuit_breaker() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal().circuit_breaker(true),
+        r#"
+        (async () => {
+            let ok = false, calls = 0;
+            const errors = [], changes = [];
+            const f = circuitBreaker(async () => {
+                calls++;
+                if (!ok) throw new Error("down");
+                return "up";
+            }, {
+                failureThreshold: 2,
+                resetMs: 20,
+                onStateChange: ({ from, to }) => changes.push(`${from}->${to}`),
+            });
+            for (let i = 0; i < 3; i++) {
+                try { await f() } catch (e) { errors.push(e.name) }
+            }
+            const open = f.state;
+            await new Promise((r) => setTimeout(r, 30));
+            ok = true;
+            const value = await f();
+            const stuck = circuitBreaker(async () => { throw new Error("down") }, {
+                failureThreshold: 1,
+                resetMs: Infinity,
+            });
+            await stuck().catch(() => {});
+            const unbounded = await stuck().catch((e) => e.name);
+            result = { calls, errors, open, value, state: f.state, changes, unbounded };
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "calls": 3,
+            "errors": ["Error", "Error", "CircuitOpenError"],
+            "open": "open",
+            "value": "up",
+            "state": "closed",
+            "changes": ["closed->open", "open->half-open", "half-open->closed"],
+            "unbounded": "CircuitOpenError",
+        })
+    );
+}
+
+#[tokio::test]
+async fn dedupe() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal().dedupe(true),
+        r#"
+        (async () => {
+            let calls = 0, n = 0;
+            const f = dedupe(async (x) => {
+                calls++;
+                await new Promise((r) => setTimeout(r, 5));
+                if (x < 0) throw new Error("negative");
+                return x * 2;
+            });
+            const values = await Promise.all([f(1), f(1), f(2)]);
+            const r1 = f(-1), r2 = f(-1);
+            const rejected = await Promise.allSettled([r1, r2]);
+            // Sync throws are shared (and reported) as rejections
+            const g = dedupe(() => { n++; throw new TypeError("sync"); });
+            const s1 = g(), s2 = g();
+            const thrown = await Promise.allSettled([s1, s2]);
+            // Settled entries are removed so fn runs again
+            const again = await f(1);
+            result = {
+                values,
+                calls,
+                same: r1 === r2 && s1 === s2,
+                rejected: rejected.map((r) => r.reason.message),
+                thrown: thrown.map((r) => r.reason.name),
+                n,
+                again,
+            };
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "values": [2, 2, 4],
+            "calls": 4,
+            "same": true,
+            "rejected": ["negative", "negative"],
+            "thrown": ["TypeError", "TypeError"],
+            "n": 1,
+            "again": 2,
+        })
+    );
+}
+
+#[tokio::test]
+async fn combinators() {
+    let (result, _) = run(
+        JsEnvBuilder::minimal().combinators(true),
+        r#"
+        (async () => {
+            const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+            let timedOut;
+            try { await withTimeout(sleep(200), 10) } catch (e) { timedOut = e.name }
+            const fast = await withTimeout(async () => "fast", 100);
+            const unbounded = await withTimeout(async () => "unbounded", Infinity);
+            // Function tasks get a signal aborted on timeout
+            let aborted;
+            const work = (signal) => new Promise(() => {
+                signal.addEventListener("abort", () => { aborted = signal.reason.name });
+            });
+            try { await withTimeout(work, 10) } catch (e) {}
+            let running = 0, peak = 0;
+            const task = (v) => async () => {
+                peak = Math.max(peak, ++running);
+                await sleep(5);
+                running--;
+                if (v < 0) throw new Error("negative");
+                return v;
+            };
+            const settled = await allSettledLimited([task(1), task(-1), task(3), task(4)], 2);
+            result = {
+                timedOut,
+                fast,
+                unbounded,
+                aborted,
+                peak,
+                settled: settled.map((s) => s.status == "fulfilled" ? s.value : s.reason.message),
+            };
+        })()
+        "#,
+    )
+    .await;
+    assert_eq!(
+        result,
+        json!({
+            "timedOut": "TimeoutError",
+            "fast": "fast",
+            "unbounded": "unbounded",
+            "aborted": "TimeoutError",
+            "peak": 2,
+            "settled": [1, "negative", 3, 4],
+        })
+    );
+}
+
+#[tokio::test]
+async fn abort_signals() {
+    let (_tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+    async_with!(ctx => |ctx| {
+        JsEnvBuilder::minimal().abort(true).apply(ctx.clone()).await.unwrap();
+        register_rx_channel(ctx.clone(), rx, "recv").unwrap();
+        run_script(ctx.clone(), r#"
+            (async () => {
+                const settle = (p) => p.then(() => "resolved", (e) => e.name);
+                const c = new AbortController();
+                const pending = [settle(__sleep(60, c.signal)), settle(recv(c.signal))];
+                c.abort();
+                const cancelled = await Promise.all(pending);
+                const t = AbortSignal.timeout(10);
+                const timedOut = await new Promise((resolve) => {
+                    t.addEventListener("abort", () => resolve(t.reason.name));
+                });
+                const thrown = [t, AbortSignal.abort("why"), new AbortController().signal].map((s) => {
+                    try {
+                        s.throwIfAborted();
+                        return null;
+                    } catch (e) {
+                        return e.name ?? e;
+                    }
+                });
+                result = { cancelled, timedOut, thrown };
+            })()
+        "#.to_string())
+        .await
+        .unwrap();
+    })
+    .await;
+    tokio::time::timeout(Duration::from_secs(5), rt.idle())
+        .await
+        .expect("script still running");
+    assert_eq!(
+        result(&ctx).await,
+        json!({
+            "cancelled": ["AbortError", "AbortError"],
+            "timedOut": "TimeoutError",
+            "thrown": ["TimeoutError", "why", null],
+        })
+    );
+}
+
+#[tokio::test]
+async fn abort_signal_timeout_infinity() {
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+    async_with!(ctx => |ctx| {
+        JsEnvBuilder::minimal().abort(true).apply(ctx.clone()).await.unwrap();
+        run_script(ctx.clone(), "result = AbortSignal.timeout(Infinity).aborted".to_string())
+            .await
+            .unwrap();
+    })
+    .await;
+    // The timer never fires, so the runtime isn't run to idle
+    assert_eq!(result(&ctx).await, json!(false));
+}
+
+#[tokio::test]
+async fn fs_lines() {
+    let dir = temp_dir("lines");
+    let path = dir.join("log.txt");
+    std::fs::write(&path, "a\nbb\nccc\ndd").unwrap();
+    let script = format!(
+        r#"
+        (async () => {{
+            const read = async (from) => {{
+                const out = [];
+                for await (const {{ line, offset, partial }} of fs.lines({path}, {{ from }})) out.push([line, offset, partial]);
+                return out;
+            }};
+            result = [await read(0), await read(5), await read(9), await read(100)];
+        }})()
+        "#,
+        path = json!(path)
+    );
+    let (result, _) = run(JsEnvBuilder::minimal().fs_root(&dir), &script).await;
+    let all = json!([
+        ["a", 2, false],
+        ["bb", 5, false],
+        ["ccc", 9, false],
+        ["dd", 9, true]
+    ]);
+    let rest = json!([["ccc", 9, false], ["dd", 9, true]]);
+    assert_eq!(result, json!([all, rest, [["dd", 9, true]], all]));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn fs_lines_outside_root() {
+    let dir = temp_dir("lines-root");
+    let (result, _) = run(
+        JsEnvBuilder::minimal().fs_root(&dir),
+        r#"try { fs.lines("/etc/passwd") } catch (e) { result = "denied" }"#,
+    )
+    .await;
+    assert_eq!(result, json!("denied"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn json_stream_roots() {
+    let dir = temp_dir("json-stream");
+    let path = dir.join("records.json");
+    std::fs::write(&path, r#"[{"a": 1}, {"a": 2}]"#).unwrap();
+    let script = format!(
+        r#"(async () => {{
+            result = [];
+            for await (const r of json.stream({path})) result.push(r.a);
+            for (const p of ["/etc/passwd", "-"]) {{
+                try {{ json.stream(p) }} catch (e) {{ result.push("denied") }}
+            }}
+        }})()"#,
+        path = json!(path)
+    );
+    let builder = JsEnvBuilder::minimal().json(true).fs_root(&dir);
+    let (result, _) = run(builder, &script).await;
+    assert_eq!(result, json!([1, 2, "denied", "denied"]));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "tail")]
+#[tokio::test]
+async fn fs_tail() {
+    use std::io::Write;
+
+    let dir = temp_dir("tail");
+    let path = dir.join("log.txt");
+    std::fs::write(&path, "old\n").unwrap();
+    let writer = {
+        let path = path.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            let mut f = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+            f.write_all(b"new 1\nnew 2\n").unwrap();
+        })
+    };
+    let script = format!(
+        r#"
+        (async () => {{
+            result = [];
+            for await (const {{ line }} of fs.tail({path})) {{
+                result.push(line);
+                if (result.length == 2) break;
+            }}
+        }})()
+        "#,
+        path = json!(path)
+    );
+    let (result, _) = run(JsEnvBuilder::minimal().fs_root(&dir), &script).await;
+    writer.join().unwrap();
+    assert_eq!(result, json!(["new 1", "new 2"]));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(all(feature = "tail", unix))]
+#[tokio::test]
+async fn fs_tail_rotation() {
+    use std::io::Write;
+
+    let dir = temp_dir("tail-rotation");
+    let path = dir.join("log.txt");
+    std::fs::write(&path, "").unwrap();
+    let writer = {
+        let path = path.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            let mut old = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            old.write_all(b"before\n").unwrap();
+            std::fs::rename(&path, path.with_extension("1")).unwrap();
+            // Still written through the old handle after the rename
+            old.write_all(b"late\n").unwrap();
+            std::fs::write(&path, "new\n").unwrap();
+        })
+    };
+    let script = format!(
+        r#"
+        (async () => {{
+            result = [];
+            for await (const {{ line }} of fs.tail({path})) {{
+                result.push(line);
+                if (result.length == 3) break;
+            }}
+        }})()
+        "#,
+        path = json!(path)
+    );
+    let (result, _) = run(JsEnvBuilder::minimal().fs_root(&dir), &script).await;
+    writer.join().unwrap();
+    assert_eq!(result, json!(["before", "late", "new"]));
+    std::fs::remove_dir_all(dir).unwrap();
+}